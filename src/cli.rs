@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use console::Style;
+
+/// A command line option, either a simple switch or an option taking a value.
+pub struct Flag {
+    pub short: Option<char>,
+    pub long: &'static str,
+    pub aliases: &'static [&'static str],
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+impl Flag {
+    pub const fn switch(short: Option<char>, long: &'static str, help: &'static str) -> Flag {
+        Flag { short, long, aliases: &[], value: None, help }
+    }
+
+    pub const fn option(short: Option<char>, long: &'static str, value: &'static str, help: &'static str) -> Flag {
+        Flag { short, long, aliases: &[], value: Some(value), help }
+    }
+
+    pub const fn aliases(self, aliases: &'static [&'static str]) -> Flag {
+        Flag { aliases, ..self }
+    }
+
+    fn matches_long(&self, name: &str) -> bool {
+        self.long == name || self.aliases.contains(&name)
+    }
+
+    /// The left column of the help listing, e.g. `-f, --file <PATH>`.
+    pub fn signature(&self) -> String {
+        let mut names = Vec::new();
+        if let Some(short) = self.short {
+            names.push(format!("-{}", short));
+        }
+        names.push(format!("--{}", self.long));
+        names.extend(self.aliases.iter().map(|alias| format!("--{}", alias)));
+
+        match self.value {
+            Some(value) => format!("{} <{}>", names.join(", "), value),
+            None => names.join(", "),
+        }
+    }
+}
+
+/// A `domake` subcommand with its own flags and help.
+pub struct Subcommand {
+    pub name: &'static str,
+    pub about: &'static str,
+    /// The positional part of the usage line, e.g. `<TARGET> [ARGS]...`.
+    pub args: &'static str,
+    pub flags: &'static [Flag],
+    pub notes: &'static [&'static str],
+}
+
+/// The options understood before any subcommand.
+pub const GLOBAL_FLAGS: &[Flag] = &[
+    Flag::switch(Some('h'), "help", "Prints help information"),
+    Flag::switch(Some('v'), "version", "Prints version information"),
+];
+
+pub enum Invocation {
+    Help(Option<&'static Subcommand>),
+    Version,
+    Command(Matches),
+}
+
+pub struct CliError {
+    pub command: Option<&'static Subcommand>,
+    pub message: String,
+}
+
+/// The flags and positional arguments given to a subcommand.
+pub struct Matches {
+    pub command: &'static Subcommand,
+    values: HashMap<&'static str, Vec<String>>,
+    pub positionals: Vec<String>,
+}
+
+impl Matches {
+    /// Whether the switch (or option) has been given at least once.
+    pub fn flag(&self, long: &str) -> bool {
+        self.values.contains_key(long)
+    }
+
+    /// The last value given for the option.
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values.get(long).and_then(|values| values.last()).map(|value| value.as_str())
+    }
+
+    /// Every value given for a repeatable option, in order.
+    pub fn values(&self, long: &str) -> &[String] {
+        self.values.get(long).map(|values| values.as_slice()).unwrap_or_default()
+    }
+}
+
+/// Parses the arguments (without the program name) against the known subcommands.
+///
+/// When the first argument isn't a subcommand, `default` is used so that a bare
+/// `domake [OPTIONS]` keeps working.
+pub fn parse(
+    args: Vec<String>,
+    commands: &[&'static Subcommand],
+    default: &'static Subcommand,
+) -> Result<Invocation, CliError> {
+    let mut args = args.into_iter().peekable();
+
+    let command = match args.peek().map(|arg| arg.as_str()) {
+        Some("-h") | Some("--help") => return Ok(Invocation::Help(None)),
+        Some("-v") | Some("--version") => return Ok(Invocation::Version),
+        Some("help") => {
+            args.next();
+            return match args.next() {
+                None => Ok(Invocation::Help(None)),
+                Some(name) => find(commands, &name).map(|cmd| Invocation::Help(Some(cmd))),
+            };
+        }
+        Some(arg) if !arg.starts_with('-') => {
+            let name = args.next().unwrap();
+            find(commands, &name)?
+        }
+        _ => default,
+    };
+
+    let error = |message: String| CliError { command: Some(command), message };
+
+    let mut matches = Matches { command, values: HashMap::new(), positionals: Vec::new() };
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            matches.positionals.extend(args.by_ref());
+            break;
+        }
+        if arg == "-h" || arg == "--help" {
+            return Ok(Invocation::Help(Some(command)));
+        }
+
+        let (flag, inline) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let flag = command.flags.iter().find(|flag| flag.matches_long(name));
+            (flag.ok_or_else(|| error(format!("Unknown option `--{}`", name)))?, inline)
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            let short = arg[1..].chars().next().unwrap();
+            let flag = command.flags.iter().find(|flag| flag.short == Some(short));
+            let flag = flag.ok_or_else(|| error(format!("Unknown option `-{}`", short)))?;
+            let rest = &arg[1 + short.len_utf8()..];
+            if !rest.is_empty() && flag.value.is_none() {
+                // the switches can be bundled, like `-vv` or `-qy`
+                for short in rest.chars() {
+                    let bundled = command.flags.iter().find(|flag| flag.short == Some(short) && flag.value.is_none());
+                    let bundled = bundled.ok_or_else(|| error(format!("Unknown option `{}`", arg)))?;
+                    matches.values.entry(bundled.long).or_default().push(String::new());
+                }
+            }
+            (flag, (flag.value.is_some() && !rest.is_empty()).then(|| rest.to_string()))
+        } else {
+            matches.positionals.push(arg);
+            continue;
+        };
+
+        let value = match flag.value {
+            None if inline.is_some() => return Err(error(format!("`--{}` doesn't take a value", flag.long))),
+            None => String::new(),
+            Some(_) => match inline.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return Err(error(format!("Missing value for `--{}`", flag.long))),
+            },
+        };
+        matches.values.entry(flag.long).or_default().push(value);
+    }
+
+    Ok(Invocation::Command(matches))
+}
+
+fn find(commands: &[&'static Subcommand], name: &str) -> Result<&'static Subcommand, CliError> {
+    commands.iter().find(|cmd| cmd.name == name).copied().ok_or_else(|| CliError {
+        command: None,
+        message: format!("Unknown command `{}`", name),
+    })
+}
+
+/// Prints the general usage, or the usage of a single subcommand.
+pub fn usage(commands: &[&'static Subcommand], command: Option<&Subcommand>) {
+    let title_style = Style::new().bold().green();
+    let text_style = Style::new().bold().cyan();
+
+    let print_table = |rows: Vec<(String, &str)>| {
+        let width = rows.iter().map(|(left, _)| left.len()).max().unwrap_or(0) + 4;
+        for (left, right) in rows {
+            println!("\t{}{}", text_style.apply_to(format!("{:width$}", left)), text_style.apply_to(right));
+        }
+    };
+
+    match command {
+        None => {
+            println!("{}", title_style.apply_to("Usage:"));
+            println!("{}", text_style.apply_to("\tdomake [COMMAND] [OPTIONS]"));
+            println!("{}", title_style.apply_to("Commands:"));
+            print_table(commands.iter().map(|cmd| (cmd.name.to_string(), cmd.about)).collect());
+            println!("{}", title_style.apply_to("Options:"));
+            print_table(GLOBAL_FLAGS.iter().map(|flag| (flag.signature(), flag.help)).collect());
+            println!("{}", text_style.apply_to("\nRun `domake help <COMMAND>` for the options of a command."));
+        }
+        Some(command) => {
+            println!("{}", title_style.apply_to("Usage:"));
+            let args = if command.args.is_empty() { String::new() } else { format!(" {}", command.args) };
+            println!("{}", text_style.apply_to(format!("\tdomake {} [OPTIONS]{}", command.name, args)));
+            println!("{}", text_style.apply_to(format!("\t{}", command.about)));
+            println!("{}", title_style.apply_to("Options:"));
+            let mut rows = vec![("-h, --help".to_string(), "Prints help information")];
+            rows.extend(command.flags.iter().map(|flag| (flag.signature(), flag.help)));
+            print_table(rows);
+            if !command.notes.is_empty() {
+                println!("{}", title_style.apply_to("Conditions:"));
+                for note in command.notes {
+                    println!("{}", text_style.apply_to(format!("\t- {}", note)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GEN: Subcommand = Subcommand {
+        name: "gen",
+        about: "",
+        args: "",
+        flags: &[Flag::switch(Some('q'), "quiet", ""), Flag::switch(Some('v'), "verbose", ""), Flag::switch(Some('n'), "dry-run", "")],
+        notes: &[],
+    };
+
+    fn parse_args(args: &[&str]) -> Result<Invocation, CliError> {
+        parse(args.iter().map(|arg| arg.to_string()).collect(), &[&GEN], &GEN)
+    }
+
+    #[test]
+    fn a_leading_v_prints_the_version() {
+        assert!(matches!(parse_args(&["-v"]), Ok(Invocation::Version)));
+        assert!(matches!(parse_args(&["-v", "--dry-run"]), Ok(Invocation::Version)));
+    }
+
+    #[test]
+    fn a_v_after_the_command_is_verbose() {
+        let verbosity = |args: &[&str]| match parse_args(args) {
+            Ok(Invocation::Command(matches)) => matches.values("verbose").len(),
+            _ => panic!("expected a command"),
+        };
+        assert_eq!(verbosity(&["gen", "-v"]), 1);
+        assert_eq!(verbosity(&["gen", "-vv"]), 2);
+        assert_eq!(verbosity(&["gen", "--verbose", "-qvn"]), 2);
+        let Ok(Invocation::Command(matches)) = parse_args(&["gen", "-qvn"]) else { panic!("expected a command") };
+        assert!(matches.flag("quiet") && matches.flag("dry-run"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::is_valid_name;
+use crate::{abort, default_dofile, expect_dofile_syntax, fail, load_dofile, parse, prepare, regenerate, Overrides, DEFAULT_MAKEFILE, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "add",
+    about: "Adds a target to the Dofile and regenerates the Makefile",
+    args: "<TARGET>",
+    flags: &[
+        Flag::option(Some('d'), "desc", "TEXT", "Describes the target (required)"),
+        Flag::option(None, "dep", "TARGET", "Adds a prerequisite, can be repeated"),
+        Flag::option(Some('c'), "cmd", "INSTRUCTION", "Adds an instruction, can be repeated (at least one)"),
+        Flag::option(Some('f'), "file", "PATH", "Edits the Dofile at <PATH> (default: `Dofile`)"),
+        Flag::option(Some('o'), "output", "PATH", "Regenerates the Makefile at <PATH> (default: `Makefile`)"),
+        Flag::switch(None, "no-gen", "Only edits the Dofile"),
+    ],
+    notes: &[
+        "the Makefile is only regenerated if it was generated by domake.",
+    ],
+};
+
+pub fn run(matches: &Matches) -> ! {
+    let [target] = matches.positionals.as_slice() else {
+        fail(Some(matches.command), "Expected exactly one target");
+    };
+    if !is_valid_name(target) {
+        fail(Some(matches.command), format!("Invalid target name `{}`", target).as_str());
+    }
+    let Some(description) = matches.value("desc") else {
+        fail(Some(matches.command), "Missing `--desc`");
+    };
+    let instructions = matches.values("cmd");
+    if instructions.is_empty() {
+        fail(Some(matches.command), "Missing `--cmd`");
+    }
+    let path = matches.value("file").unwrap_or(default_dofile());
+    expect_dofile_syntax(matches.command, path);
+    if path == STDIN_PATH {
+        fail(Some(matches.command), "Cannot edit a Dofile read from stdin");
+    }
+    let makefile = matches.value("output").unwrap_or(DEFAULT_MAKEFILE);
+
+    // a missing Dofile is created
+    let mut content = if fs::exists(path).unwrap_or(false) { load_dofile(path) } else { String::new() };
+    let commands = parse(path, &content).commands;
+    if commands.iter().any(|cmd| &cmd.name == target) {
+        abort(format!("Target `{}` already exists in `{}`", target, path).as_str(), 1);
+    }
+
+    if !content.is_empty() {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push('\n');
+    }
+    let mut header = format!("[{}]", target);
+    for dep in matches.values("dep") {
+        header.push(' ');
+        header.push_str(dep);
+    }
+    content.push_str(format!("{}\n# {}\n", header, description).as_str());
+    for instruction in instructions {
+        content.push_str(format!("{}\n", instruction).as_str());
+    }
+
+    if let Err(err) = fs::write(path, &content) {
+        abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2);
+    }
+    println!("{} {}", style(format!("-> Target `{}` added to", target)).bold().green(), path);
+
+    if !matches.flag("no-gen") {
+        match regenerate(&prepare(path, &content, &Overrides::default()), &Overrides::default(), makefile) {
+            Ok(_) => println!("{} {}", style("-> Makefile regenerated:").bold().green(), makefile),
+            Err(err) => println!("{} {}", style("Warning:").bold().yellow(), style(err).yellow()),
+        }
+    }
+    exit(0)
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::json::Json;
+use domake::{comparable_lines, merge_makefile};
+use crate::output;
+use crate::{abort, dofile_paths, fail, overrides, prepare_all, render, DEFAULT_MAKEFILE};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "check",
+    about: "Checks that the Makefile is up to date with the Dofile",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`); repeated, each one is laid over the ones before"),
+        Flag::option(Some('D'), "define", "KEY=VALUE", "Sets the variable <KEY> to <VALUE>, overriding the Dofile, can be repeated"),
+        Flag::option(None, "profile", "NAME", "Keeps the `%profile` sections of the profile <NAME> (e.g. `dev`, `ci`, `release`)"),
+        Flag::switch(None, "silent-all", "Silences every instruction, as if they all started with `@`"),
+        Flag::option(None, "target-os", "OS", "Keeps only the `@OS:` instructions of <OS> (`linux`, `macos` or `windows`) instead of choosing when make runs"),
+        Flag::option(None, "target-arch", "ARCH", "Keeps only the `@ARCH:` instructions of <ARCH> (`amd64` or `arm64`) instead of choosing when make runs"),
+        Flag::switch(None, "expand-includes", "Includes each file matched by the `*` and `?` of the includes, instead of leaving the patterns to make"),
+        Flag::switch(None, "reproducible", "Leaves the date out of the header, so that the same Dofile always gives the same Makefile"),
+        Flag::option(None, "sort", "ORDER", "Orders the targets of the Makefile: `source`, `name` or `dependencies` (prerequisites first) (default: `source`)"),
+        Flag::option(None, "make-version", "VERSION", "Writes the Makefile for GNU make <VERSION> (e.g. `3.81`, `4.x`), warning of what it doesn't support (default: the latest)"),
+        Flag::option(None, "flavor", "MAKE", "Writes the Makefile for `gnu` make, Microsoft `nmake` or `bsd` make, leaving out what it doesn't support (default: `gnu`)"),
+        Flag::option(Some('o'), "output", "PATH", "Checks the Makefile at <PATH> (default: `Makefile`)"),
+        Flag::switch(None, "json", "Prints the result as JSON on stdout: the status, the warnings and the differences"),
+        Flag::switch(Some('q'), "quiet", "Prints nothing but the errors, on stderr, leaving out the progress messages and the warnings"),
+        Flag::switch(Some('v'), "verbose", "Traces on stderr the parsed constructs and the resolved includes of the Dofile checked; given twice, also traces each decision of the parser (e.g. the lines a `%profile` leaves out)"),
+    ],
+    notes: &[
+        "exits with status 1 when the Makefile differs from what `domake gen` would write (the generation date is ignored).",
+    ],
+};
+
+/// The targets declared by a Makefile, from its `.PHONY` lines.
+fn targets(makefile: &str) -> Vec<&str> {
+    makefile.lines().filter_map(|line| line.strip_prefix(".PHONY:")).flat_map(|line| line.split_whitespace()).collect()
+}
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let dofiles = dofile_paths(matches);
+    let path = matches.value("output").unwrap_or(DEFAULT_MAKEFILE);
+
+    let overrides = overrides(matches);
+    let generated = render(&prepare_all(&dofiles, &overrides), &overrides);
+    let Ok(actual) = fs::read_to_string(path) else {
+        abort(format!("No Makefile found at `{}`, run `domake gen` to create it", path).as_str(), 1);
+    };
+    let expected = merge_makefile(&actual, &generated);
+
+    let (expected_lines, actual_lines) = (comparable_lines(&expected), comparable_lines(&actual));
+    let (expected_targets, actual_targets) = (targets(&expected), targets(&actual));
+    let missing = expected_targets.iter().filter(|target| !actual_targets.contains(target)).copied().collect::<Vec<_>>();
+    let unexpected = actual_targets.iter().filter(|target| !expected_targets.contains(target)).copied().collect::<Vec<_>>();
+    let first = expected_lines.iter().zip(&actual_lines).position(|(expected, actual)| expected != actual)
+        .unwrap_or(expected_lines.len().min(actual_lines.len()));
+    if output::is_json() {
+        let up_to_date = expected_lines == actual_lines;
+        output::finish(if up_to_date { 0 } else { 1 }, if up_to_date { "ok" } else { "failed" }, vec![
+            ("makefile", path.into()),
+            ("up_to_date", up_to_date.into()),
+            ("missing_targets", missing.into()),
+            ("unexpected_targets", unexpected.into()),
+            ("first_difference", if up_to_date { Json::Null } else { (first + 1).into() }),
+        ]);
+    }
+    if expected_lines == actual_lines {
+        output::status(&format!("{} {}", style("-> Makefile is up to date:").bold().green(), path));
+        exit(0);
+    }
+
+    eprintln!("{} {}", style("Error:").bold().red(), style(format!("`{}` is out of date with `{}`", path, dofiles.join("` and `"))).red());
+    for target in missing {
+        eprintln!("\t{} {}", style("missing target:").yellow(), target);
+    }
+    for target in unexpected {
+        eprintln!("\t{} {}", style("unexpected target:").yellow(), target);
+    }
+    eprintln!("\t{} {}", style("first difference at line").yellow(), first + 1);
+    eprintln!("Run `domake gen` to update it.");
+    exit(1)
+}
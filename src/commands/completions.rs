@@ -0,0 +1,178 @@
+use std::process::exit;
+use crate::cli::{Flag, Matches, Subcommand, GLOBAL_FLAGS};
+use crate::commands::{ALL, DEFAULT};
+use crate::fail;
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "completions",
+    about: "Prints the shell completion script of domake",
+    args: "<SHELL>",
+    flags: &[],
+    notes: &[
+        "<SHELL> is one of `bash`, `zsh`, `fish` or `powershell`.",
+        "e.g. `domake completions bash > /etc/bash_completion.d/domake`.",
+    ],
+};
+
+/// The subcommands whose arguments are targets of the Dofile, completed at completion time.
+const TARGET_COMMANDS: &[&str] = &["run", "make", "explain", "remove"];
+
+/// The shell command listing the targets of the Dofile in the current directory.
+const LIST_TARGETS: &str = "domake targets --names 2>/dev/null";
+
+/// The `--long` and `-s` spellings of a flag.
+fn spellings(flag: &Flag) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(short) = flag.short {
+        names.push(format!("-{}", short));
+    }
+    names.push(format!("--{}", flag.long));
+    names.extend(flag.aliases.iter().map(|alias| format!("--{}", alias)));
+    names
+}
+
+/// Whether the value of the flag is a path, completed with file names.
+fn takes_path(flag: &Flag) -> bool {
+    flag.value == Some("PATH")
+}
+
+/// The flags understood by `domake` before (or instead of) a subcommand.
+fn top_level_flags() -> impl Iterator<Item = &'static Flag> {
+    GLOBAL_FLAGS.iter().chain(DEFAULT.flags)
+}
+
+fn bash() -> String {
+    let names = ALL.iter().map(|cmd| cmd.name).collect::<Vec<_>>().join(" ");
+    let words = |flags: &mut dyn Iterator<Item = &Flag>| flags.flat_map(spellings).collect::<Vec<_>>().join(" ");
+    let mut paths = ALL.iter().flat_map(|cmd| cmd.flags.iter()).filter(|flag| takes_path(flag))
+        .flat_map(spellings).collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let mut script = String::from("_domake() {\n    local cur prev command word opts\n");
+    script.push_str("    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n\n");
+    script.push_str(format!("    case \"$prev\" in\n        {})\n            COMPREPLY=($(compgen -f -- \"$cur\"))\n            return;;\n    esac\n\n", paths.join("|")).as_str());
+    script.push_str("    command=\"\"\n    for word in \"${COMP_WORDS[@]:1:COMP_CWORD-1}\"; do\n        case \"$word\" in\n");
+    script.push_str(format!("            {}|help) command=\"$word\"; break;;\n", names.replace(' ', "|")).as_str());
+    script.push_str("        esac\n    done\n\n    case \"$command\" in\n");
+    script.push_str(format!("        \"\") opts=\"{} help {}\";;\n", names, words(&mut top_level_flags())).as_str());
+    script.push_str(format!("        help) opts=\"{}\";;\n", names).as_str());
+    for cmd in ALL {
+        let help = Flag::switch(Some('h'), "help", "");
+        let targets = if TARGET_COMMANDS.contains(&cmd.name) { format!(" $({})", LIST_TARGETS) } else { String::new() };
+        script.push_str(format!("        {}) opts=\"{}{}\";;\n", cmd.name, words(&mut [&help].into_iter().chain(cmd.flags)), targets).as_str());
+    }
+    script.push_str("    esac\n    COMPREPLY=($(compgen -W \"$opts\" -- \"$cur\"))\n}\n\ncomplete -F _domake domake\n");
+    script
+}
+
+/// Escapes a description for zsh's `_arguments` and `_describe`.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]").replace(':', "\\:")
+}
+
+fn zsh_arguments(flags: &mut dyn Iterator<Item = &Flag>) -> String {
+    flags.flat_map(|flag| {
+        let value = match flag.value {
+            Some(value) if takes_path(flag) => format!(":{}:_files", value),
+            Some(value) => format!(":{}: ", value),
+            None => String::new(),
+        };
+        spellings(flag).into_iter()
+            .map(move |name| format!(" \\\n                '{}[{}]{}'", name, zsh_escape(flag.help), value))
+    }).collect()
+}
+
+fn zsh() -> String {
+    let mut script = String::from("#compdef domake\n\n");
+    script.push_str(format!("_domake_targets() {{\n    local -a targets\n    targets=(${{(f)\"$({})\"}})\n    _describe 'target' targets\n}}\n\n", LIST_TARGETS).as_str());
+    script.push_str("_domake() {\n    local -a commands\n    commands=(\n");
+    for cmd in ALL {
+        script.push_str(format!("        '{}:{}'\n", cmd.name, zsh_escape(cmd.about)).as_str());
+    }
+    script.push_str("    )\n\n    if (( CURRENT == 2 )); then\n        _describe 'command' commands\n");
+    script.push_str(format!("        _arguments{}\n        return\n    fi\n\n", zsh_arguments(&mut top_level_flags())).as_str());
+    script.push_str("    case $words[2] in\n        help)\n            _describe 'command' commands;;\n");
+    for cmd in ALL {
+        let argument = if TARGET_COMMANDS.contains(&cmd.name) { "*:target:_domake_targets" } else { "*:argument: " };
+        script.push_str(format!("        {})\n            _arguments{} \\\n                '{}';;\n", cmd.name, zsh_arguments(&mut cmd.flags.iter()), argument).as_str());
+    }
+    script.push_str(format!("        *)\n            _arguments{};;\n", zsh_arguments(&mut DEFAULT.flags.iter())).as_str());
+    script.push_str("    esac\n}\n\n_domake \"$@\"\n");
+    script
+}
+
+fn fish_line(condition: &str, flag: &Flag) -> String {
+    let mut line = format!("complete -c domake -n '{}'", condition);
+    if let Some(short) = flag.short {
+        line.push_str(format!(" -s {}", short).as_str());
+    }
+    line.push_str(format!(" -l {}", flag.long).as_str());
+    for alias in flag.aliases {
+        line.push_str(format!(" -l {}", alias).as_str());
+    }
+    if takes_path(flag) {
+        line.push_str(" -r -F");
+    } else if flag.value.is_some() {
+        line.push_str(" -r");
+    }
+    line.push_str(format!(" -d '{}'\n", flag.help.replace('\'', "\\'")).as_str());
+    line
+}
+
+fn fish() -> String {
+    let names = ALL.iter().map(|cmd| cmd.name).collect::<Vec<_>>().join(" ");
+    let mut script = String::from("complete -c domake -f\n");
+    for cmd in ALL {
+        script.push_str(format!("complete -c domake -n '__fish_use_subcommand' -a {} -d '{}'\n", cmd.name, cmd.about.replace('\'', "\\'")).as_str());
+    }
+    script.push_str(format!("complete -c domake -n '__fish_seen_subcommand_from help' -a '{}'\n", names).as_str());
+    script.push_str(format!("complete -c domake -n '__fish_seen_subcommand_from {}' -a '({})'\n", TARGET_COMMANDS.join(" "), LIST_TARGETS).as_str());
+    for flag in top_level_flags() {
+        script.push_str(fish_line("__fish_use_subcommand", flag).as_str());
+    }
+    for cmd in ALL {
+        let condition = format!("__fish_seen_subcommand_from {}", cmd.name);
+        for flag in cmd.flags {
+            script.push_str(fish_line(&condition, flag).as_str());
+        }
+    }
+    script
+}
+
+fn powershell() -> String {
+    let quote = |words: Vec<String>| words.iter().map(|word| format!("'{}'", word)).collect::<Vec<_>>().join(", ");
+    let mut script = String::from("Register-ArgumentCompleter -Native -CommandName domake -ScriptBlock {\n");
+    script.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n\n    $commands = @{\n");
+    for cmd in ALL {
+        let flags = ["-h".to_string(), "--help".to_string()].into_iter().chain(cmd.flags.iter().flat_map(spellings)).collect();
+        script.push_str(format!("        '{}' = @({})\n", cmd.name, quote(flags)).as_str());
+    }
+    script.push_str("    }\n");
+    script.push_str(format!("    $topLevel = @({})\n\n", quote(top_level_flags().flat_map(spellings).collect())).as_str());
+    script.push_str("    $words = $commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object { $_.ToString() }\n");
+    script.push_str("    $command = $words | Where-Object { $commands.ContainsKey($_) } | Select-Object -First 1\n");
+    script.push_str("    if ($words -contains 'help') {\n        $candidates = @($commands.Keys)\n");
+    script.push_str("    } elseif ($command) {\n        $candidates = $commands[$command]\n");
+    script.push_str(format!("        if (@({}) -contains $command) {{\n", quote(TARGET_COMMANDS.iter().map(|name| name.to_string()).collect())).as_str());
+    script.push_str("            $candidates += @(& domake targets --names 2>$null)\n        }\n");
+    script.push_str("    } else {\n        $candidates = @($commands.Keys) + @('help') + $topLevel\n    }\n\n");
+    script.push_str("    $candidates | Where-Object { $_ -like \"$wordToComplete*\" } | Sort-Object | ForEach-Object {\n");
+    script.push_str("        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n    }\n}\n");
+    script
+}
+
+pub fn run(matches: &Matches) -> ! {
+    let [shell] = matches.positionals.as_slice() else {
+        fail(Some(matches.command), "Expected exactly one shell");
+    };
+    let script = match shell.as_str() {
+        "bash" => bash(),
+        "zsh" => zsh(),
+        "fish" => fish(),
+        "powershell" | "pwsh" => powershell(),
+        shell => fail(Some(matches.command), format!("Unsupported shell `{}`", shell).as_str()),
+    };
+    print!("{}", script);
+    exit(0)
+}
@@ -0,0 +1,56 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::{merge_makefile, same_line};
+use crate::{diff, dofile_paths, fail, overrides, prepare_all, render, DEFAULT_MAKEFILE};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "diff",
+    about: "Shows the changes `gen` would make to the Makefile",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`); repeated, each one is laid over the ones before"),
+        Flag::option(Some('D'), "define", "KEY=VALUE", "Sets the variable <KEY> to <VALUE>, overriding the Dofile, can be repeated"),
+        Flag::option(None, "profile", "NAME", "Keeps the `%profile` sections of the profile <NAME> (e.g. `dev`, `ci`, `release`)"),
+        Flag::switch(None, "silent-all", "Silences every instruction, as if they all started with `@`"),
+        Flag::option(None, "target-os", "OS", "Keeps only the `@OS:` instructions of <OS> (`linux`, `macos` or `windows`) instead of choosing when make runs"),
+        Flag::option(None, "target-arch", "ARCH", "Keeps only the `@ARCH:` instructions of <ARCH> (`amd64` or `arm64`) instead of choosing when make runs"),
+        Flag::switch(None, "expand-includes", "Includes each file matched by the `*` and `?` of the includes, instead of leaving the patterns to make"),
+        Flag::switch(None, "reproducible", "Leaves the date out of the header, so that the same Dofile always gives the same Makefile"),
+        Flag::option(None, "sort", "ORDER", "Orders the targets of the Makefile: `source`, `name` or `dependencies` (prerequisites first) (default: `source`)"),
+        Flag::option(None, "make-version", "VERSION", "Writes the Makefile for GNU make <VERSION> (e.g. `3.81`, `4.x`), warning of what it doesn't support (default: the latest)"),
+        Flag::option(None, "flavor", "MAKE", "Writes the Makefile for `gnu` make, Microsoft `nmake` or `bsd` make, leaving out what it doesn't support (default: `gnu`)"),
+        Flag::option(Some('o'), "output", "PATH", "Compares with the Makefile at <PATH> (default: `Makefile`)"),
+        Flag::option(Some('U'), "context", "LINES", "Shows <LINES> lines of context (default: 3)"),
+    ],
+    notes: &[
+        "exits with status 1 when there are differences, like `diff` does (the generation date is ignored).",
+    ],
+};
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let path = matches.value("output").unwrap_or(DEFAULT_MAKEFILE);
+    let context = match matches.value("context").map(|lines| lines.parse::<usize>()) {
+        None => 3,
+        Some(Ok(lines)) => lines,
+        Some(Err(_)) => fail(Some(matches.command), "`--context` expects a number of lines"),
+    };
+
+    // a missing Makefile is shown as entirely added
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let overrides = overrides(matches);
+    let generated = merge_makefile(&existing, &render(&prepare_all(&dofile_paths(matches), &overrides), &overrides));
+
+    let (old, new) = (existing.lines().collect::<Vec<_>>(), generated.lines().collect::<Vec<_>>());
+    let hunks = diff::hunks(&old, &new, context, same_line);
+    if hunks.is_empty() {
+        println!("{} {}", style("-> Makefile is up to date:").bold().green(), path);
+        exit(0);
+    }
+    diff::print(path, "generated", &hunks);
+    exit(1)
+}
@@ -0,0 +1,60 @@
+use std::process::exit;
+use console::{style, Style};
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::Command;
+use crate::{abort, default_dofile, fail, load_dofile, parse};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "explain",
+    about: "Shows the details of a target and the rule generated for it",
+    args: "<TARGET>",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`)"),
+    ],
+    notes: &[],
+};
+
+/// Collects the prerequisites of `name` recursively, each one after its own prerequisites.
+fn transitive<'a>(commands: &'a [Command], name: &str, visited: &mut Vec<&'a str>, order: &mut Vec<&'a str>) {
+    let Some(cmd) = commands.iter().find(|cmd| cmd.name == name) else { return };
+    for prior in cmd.prior_commands.iter().map(String::as_str) {
+        if !visited.contains(&prior) {
+            visited.push(prior);
+            transitive(commands, prior, visited, order);
+            order.push(prior);
+        }
+    }
+}
+
+pub fn run(matches: &Matches) -> ! {
+    let [target] = matches.positionals.as_slice() else {
+        fail(Some(matches.command), "Expected exactly one target");
+    };
+    let path = matches.value("file").unwrap_or(default_dofile());
+    let commands = parse(path, &load_dofile(path)).commands;
+
+    let Some(cmd) = commands.iter().find(|cmd| &cmd.name == target) else {
+        abort(format!("No target named `{}` in the Dofile", target).as_str(), 1);
+    };
+
+    let mut prerequisites = Vec::new();
+    transitive(&commands, &cmd.name, &mut vec![cmd.name.as_str()], &mut prerequisites);
+    let direct = cmd.prior_commands.iter().map(String::as_str).collect::<Vec<_>>();
+    let indirect = prerequisites.iter().filter(|name| !direct.contains(name)).copied().collect::<Vec<_>>();
+    let or_none = |names: &[&str]| if names.is_empty() { "(none)".to_string() } else { names.join(" ") };
+
+    let title_style = Style::new().bold().green();
+    println!("{} {}", title_style.apply_to("Target:"), style(&cmd.name).bold().cyan());
+    println!("{} {}:{}", title_style.apply_to("Defined at:"), path, cmd.line);
+    println!("{} {}", title_style.apply_to("Description:"), cmd.description.replace('\n', "\n             "));
+    println!("{} {}", title_style.apply_to("Prerequisites:"), or_none(&direct));
+    if let Some(advice) = &cmd.deprecated {
+        println!("{} {}", title_style.apply_to("Deprecated:"), if advice.is_empty() { "yes" } else { advice });
+    }
+    println!("{} {}", title_style.apply_to("Requires:"), or_none(&cmd.requires.iter().map(String::as_str).collect::<Vec<_>>()));
+    println!("{} {}", title_style.apply_to("Transitive prerequisites:"), or_none(&indirect));
+    println!("{} {}", title_style.apply_to("Run order:"), prerequisites.iter().chain([&cmd.name.as_str()]).copied().collect::<Vec<_>>().join(" -> "));
+    println!("{}", title_style.apply_to("Makefile rule:"));
+    print!("{}", cmd.to_makefile());
+    exit(0)
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::json::Json;
+use domake::{export, Dofile};
+use crate::{abort, dofile_paths, fail, overrides, prepare_all};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "export",
+    about: "Renders the Dofile for another task runner than make",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`); repeated, each one is laid over the ones before"),
+        Flag::option(Some('D'), "define", "KEY=VALUE", "Sets the variable <KEY> to <VALUE>, overriding the Dofile, can be repeated"),
+        Flag::option(None, "profile", "NAME", "Keeps the `%profile` sections of the profile <NAME> (e.g. `dev`, `ci`, `release`)"),
+        Flag::option(None, "format", "FORMAT", "Renders a `just` file, a `taskfile` (a `Taskfile.yml` for Task), a `ninja` file of the file targets, a `sh`, `ps1` (PowerShell) or `bat` script, the `npm` scripts of a `package.json`, a GitHub Actions workflow (`gha`), a `gitlab` CI pipeline, or the `json` of the parsed Dofile"),
+        Flag::option(None, "group", "NAME", "With `--format gha` or `gitlab`, runs the targets of the group <NAME> (default: `ci`)"),
+        Flag::switch(None, "inline", "With `--format npm`, writes the instructions in the scripts instead of `make <target>`"),
+        Flag::option(Some('o'), "output", "PATH", "Writes to <PATH> instead of stdout"),
+    ],
+    notes: &[
+        "what the format cannot express (e.g. the includes, which are Makefiles) is left out, with a comment saying so.",
+        "`--format gha` writes a workflow with a `make <target>` step for each target tagged with `#@ group: ci` (or the group of `--group`), which runs the committed Makefile.",
+        "`--format gitlab` writes a `.gitlab-ci.yml` with a `make <target>` job for each target of the group, in stages following their prerequisites.",
+        "`--format npm` merges the targets into the `scripts` of the `package.json` at <PATH> (default: `package.json`), keeping the others.",
+    ],
+};
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    if matches.flag("inline") && matches.value("format") != Some("npm") {
+        fail(Some(matches.command), "`--inline` only applies to `--format npm`");
+    }
+    if matches.value("group").is_some() && !matches!(matches.value("format"), Some("gha" | "gitlab")) {
+        fail(Some(matches.command), "`--group` only applies to `--format gha` and `gitlab`");
+    }
+    let package = |dofile: &Dofile| package(matches, dofile);
+    let workflow = |dofile: &Dofile| workflow(matches, dofile);
+    let render: &dyn Fn(&Dofile) -> String = match matches.value("format") {
+        Some("just") => &export::justfile,
+        Some("taskfile") => &export::taskfile,
+        Some("ninja") => &export::ninja,
+        Some("sh") => &export::shell_script,
+        Some("ps1") => &export::powershell_script,
+        Some("bat") => &export::batch_file,
+        Some("npm") => &package,
+        Some("gha" | "gitlab") => &workflow,
+        Some("json") => &export::json,
+        Some(other) => fail(Some(matches.command), format!("Unknown format `{}`, expected `just`, `taskfile`, `ninja`, `sh`, `ps1`, `bat`, `npm`, `gha`, `gitlab` or `json`", other).as_str()),
+        None => fail(Some(matches.command), "Missing `--format`"),
+    };
+    let exported = render(&prepare_all(&dofile_paths(matches), &overrides(matches)));
+
+    let Some(path) = matches.value("output") else {
+        print!("{}", exported);
+        exit(0);
+    };
+    if let Err(err) = fs::write(path, exported) {
+        abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2);
+    }
+    // the script is run as `./do.sh`
+    #[cfg(unix)]
+    if matches.value("format") == Some("sh") {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(err) = fs::set_permissions(path, fs::Permissions::from_mode(0o755)) {
+            abort(format!("Cannot make `{}` executable: {}", path, err).as_str(), 2);
+        }
+    }
+    println!("{} {}", style("-> Dofile exported:").bold().green(), path);
+    exit(0)
+}
+
+/// The CI workflow of `--format` running the targets of the group of `--group`, or `ci`, exiting if
+/// there are none.
+fn workflow(matches: &Matches, dofile: &Dofile) -> String {
+    let render = if matches.value("format") == Some("gitlab") { export::gitlab_ci } else { export::github_actions };
+    render(dofile, matches.value("group").unwrap_or("ci")).unwrap_or_else(|err| abort(&err, 1))
+}
+
+/// The `package.json` at the output path, or `package.json`, with the targets merged into its
+/// scripts, warning of the scripts they replace.
+fn package(matches: &Matches, dofile: &Dofile) -> String {
+    let path = matches.value("output").unwrap_or("package.json");
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => match Json::parse(&content) {
+            Ok(package) => Some(package),
+            Err(err) => abort(format!("Cannot read `{}`: {}", path, err).as_str(), 2),
+        },
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => abort(format!("Cannot read `{}`: {}", path, err).as_str(), 2),
+    };
+    let (package, replaced) = export::package_json(dofile, existing, matches.flag("inline"))
+        .unwrap_or_else(|err| abort(format!("Cannot merge into `{}`: {}", path, err).as_str(), 2));
+    for name in replaced {
+        let message = format!("the script `{}` of `{}` is replaced by the target", name, path);
+        eprintln!("{} {}", style("Warning:").bold().yellow(), style(message).yellow());
+    }
+    format!("{}\n", package.pretty())
+}
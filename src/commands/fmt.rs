@@ -0,0 +1,178 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use regex::Regex;
+use domake::block_end;
+use crate::cli::{Flag, Matches, Subcommand};
+use crate::{abort, default_dofile, diff, expect_dofile_syntax, fail, load_dofile, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "fmt",
+    about: "Formats the Dofile in place",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Formats the Dofile at <PATH>, `-` for stdin to stdout (default: `Dofile`)"),
+        Flag::switch(None, "check", "Doesn't write anything, exits with status 1 if the Dofile isn't formatted"),
+    ],
+    notes: &[
+        "trailing whitespace is trimmed and consecutive blank lines are merged.",
+        "targets are separated by exactly one blank line, written `[name] prerequisites`.",
+        "consecutive includes are sorted, each run on its own so that none leaves its `%if` or `%profile` section.",
+        "raw ```make blocks are kept as they are.",
+    ],
+};
+
+fn format(content: &str) -> String {
+    let re_header = Regex::new(r"^\[\s*(?<name>[^]]*?)\s*](?<prior_commands>.*)$").unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+
+    // the lines of the raw blocks, fences included, are Makefile syntax left as it is
+    let mut in_raw = false;
+    let raw = lines.iter().map(|line| {
+        let fence = if in_raw { line.trim() == "```" } else { matches!(line.trim(), "```make" | "```raw") };
+        let raw = in_raw || fence;
+        in_raw ^= fence;
+        raw
+    }).collect::<Vec<_>>();
+
+    // the lines of the targets after their header, up to where the parser ends them, are instructions
+    let mut instruction = vec![false; lines.len()];
+    let mut index = 0;
+    while index < lines.len() {
+        if !raw[index] && re_header.is_match(lines[index].trim()) {
+            let end = block_end(&lines, index);
+            instruction[index + 1..end].fill(true);
+            index = end;
+        } else {
+            index += 1;
+        }
+    }
+
+    let is_include = |index: usize| !raw[index] && !instruction[index] && (lines[index].starts_with("include ") || lines[index].starts_with("include? "));
+
+    let mut formatted: Vec<String> = Vec::new();
+    let mut run_end = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if index < run_end {
+            continue;
+        }
+        if raw[index] {
+            formatted.push(line.to_string());
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if formatted.last().is_some_and(|last| !last.is_empty()) {
+                formatted.push(String::new());
+            }
+            continue;
+        }
+        if instruction[index] {
+            formatted.push(line.to_string());
+            continue;
+        }
+        if is_include(index) {
+            run_end = (index..lines.len()).find(|&next| !is_include(next)).unwrap_or(lines.len());
+            let mut includes = lines[index..run_end].iter()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(keyword, path)| format!("{} {}", keyword, path.trim()))
+                .collect::<Vec<_>>();
+            includes.sort();
+            includes.dedup();
+            formatted.append(&mut includes);
+            continue;
+        }
+        // the includes stand apart, unless their section closes right after them
+        if !line.trim_start().starts_with('%') && formatted.last().is_some_and(|last| last.starts_with("include ") || last.starts_with("include? ")) {
+            formatted.push(String::new());
+        }
+
+        // the instructions are past, so an opening bracket starts a target (not e.g. `[ -f x ]`)
+        match re_header.captures(line.trim_start()) {
+            Some(c) => {
+                // apart from the line before, unless it opens its section or comments on it
+                if formatted.last().is_some_and(|last| !last.is_empty() && !last.starts_with(['%', '#'])) {
+                    formatted.push(String::new());
+                }
+                let mut header = format!("[{}]", &c["name"]);
+                for prior in c["prior_commands"].split_whitespace() {
+                    header.push(' ');
+                    header.push_str(prior);
+                }
+                formatted.push(header);
+            }
+            None => formatted.push(line.to_string()),
+        }
+    }
+
+    while formatted.last().is_some_and(|last| last.is_empty()) {
+        formatted.pop();
+    }
+    let mut buffer = formatted.join("\n");
+    buffer.push('\n');
+    buffer
+}
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let path = matches.value("file").unwrap_or(default_dofile());
+    expect_dofile_syntax(matches.command, path);
+    let content = load_dofile(path);
+    let formatted = format(&content);
+
+    if matches.flag("check") {
+        if formatted == content {
+            println!("{} {}", style("-> Dofile is formatted:").bold().green(), path);
+            exit(0);
+        }
+        let (old, new) = (content.lines().collect::<Vec<_>>(), formatted.lines().collect::<Vec<_>>());
+        let hunks = diff::hunks(&old, &new, 3, |a, b| a == b);
+        if hunks.is_empty() {
+            println!("{}", style("-> Missing newline at the end of the file").yellow());
+        } else {
+            diff::print(path, "formatted", &hunks);
+        }
+        eprintln!("{} {}", style("Error:").bold().red(), style(format!("`{}` isn't formatted, run `domake fmt`", path)).red());
+        exit(1);
+    }
+
+    if path == STDIN_PATH {
+        print!("{}", formatted);
+        exit(0);
+    }
+    if formatted == content {
+        println!("{} {}", style("-> Dofile already formatted:").bold().green(), path);
+        exit(0);
+    }
+    if let Err(err) = fs::write(path, formatted) {
+        abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2);
+    }
+    println!("{} {}", style("-> Dofile formatted:").bold().green(), path);
+    exit(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_instructions_of_a_target_are_kept_in_place() {
+        let content = "include b.mk\n\n[build]\n# Builds\necho build\ninclude a.mk\n\n  echo spaced\n\n[lib]\n# Lib\necho lib\n";
+        assert_eq!(format(content), content);
+    }
+
+    #[test]
+    fn the_includes_are_sorted_within_their_section() {
+        let content = "include b.mk\ninclude a.mk\n%if os windows\ninclude win.mk\n%end\ninclude c.mk\n";
+        assert_eq!(format(content), "include a.mk\ninclude b.mk\n%if os windows\ninclude win.mk\n%end\ninclude c.mk\n");
+    }
+
+    #[test]
+    fn a_header_right_after_a_directive_is_formatted() {
+        let content = "default build\n[ build ]   lib\n# Builds\necho build\n";
+        assert_eq!(format(content), "default build\n\n[build] lib\n# Builds\necho build\n");
+        assert_eq!(format(&format(content)), format(content));
+    }
+}
@@ -0,0 +1,510 @@
+use std::{env, fs};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use console::{style, Term};
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::{comparable_lines, is_generated, is_structured, managed_region, merge_makefile, parse_dofile_partial, parse_dofile_partial_with, parse_structured_dofile, rule_targets, Command, Dofile, HELPER_TARGETS};
+use crate::output;
+use crate::{abort, confirm, customize, dofile_paths, fail, find_dofile, first_error, is_makefile, is_up_to_date, load_dofile, overrides, parse_structured, prepare, prepare_all, prepare_json, read_file, render, source, write, Overrides, DEFAULT_DOFILE, DEFAULT_MAKEFILE, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "gen",
+    about: "Generates the Makefile from the Dofile (default command)",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`); repeated, each one is laid over the ones before"),
+        Flag::option(Some('D'), "define", "KEY=VALUE", "Sets the variable <KEY> to <VALUE>, overriding the Dofile, can be repeated"),
+        Flag::option(None, "profile", "NAME", "Keeps the `%profile` sections of the profile <NAME> (e.g. `dev`, `ci`, `release`)"),
+        Flag::switch(None, "silent-all", "Silences every instruction, as if they all started with `@`"),
+        Flag::option(None, "target-os", "OS", "Keeps only the `@OS:` instructions of <OS> (`linux`, `macos` or `windows`) instead of choosing when make runs"),
+        Flag::option(None, "target-arch", "ARCH", "Keeps only the `@ARCH:` instructions of <ARCH> (`amd64` or `arm64`) instead of choosing when make runs"),
+        Flag::switch(None, "expand-includes", "Includes each file matched by the `*` and `?` of the includes, instead of leaving the patterns to make"),
+        Flag::switch(None, "reproducible", "Leaves the date out of the header, so that the same Dofile always gives the same Makefile"),
+        Flag::option(None, "sort", "ORDER", "Orders the targets of the Makefile: `source`, `name` or `dependencies` (prerequisites first) (default: `source`)"),
+        Flag::option(None, "make-version", "VERSION", "Writes the Makefile for GNU make <VERSION> (e.g. `3.81`, `4.x`), warning of what it doesn't support (default: the latest)"),
+        Flag::option(None, "flavor", "MAKE", "Writes the Makefile for `gnu` make, Microsoft `nmake` or `bsd` make, leaving out what it doesn't support (default: `gnu`)"),
+        Flag::option(None, "from-json", "PATH", "Reads the model of the Dofile from the JSON at <PATH>, `-` for stdin, as `domake export --format json` writes it"),
+        Flag::option(Some('o'), "output", "PATH", "Writes the Makefile to <PATH> (default: `Makefile`)"),
+        Flag::option(None, "overwrite", "POLICY", "What to do when a Makefile exists: `prompt` (if not generated by domake), `always`, `never` or `backup` (always, keeping a backup) (default: `$DOMAKE_OVERWRITE`, else `prompt`)"),
+        Flag::switch(Some('y'), "force", "Overwrites an existing Makefile without asking, like `--overwrite always`").aliases(&["yes"]),
+        Flag::switch(None, "no-overwrite", "Aborts if a Makefile already exists, like `--overwrite never`"),
+        Flag::option(None, "backups", "COUNT", "Keeps the last <COUNT> replaced Makefiles as `Makefile.<time>.bak`, 0 for none (default: 1, as `Makefile.bak`)"),
+        Flag::switch(None, "merge", "Adds the generated targets to an existing Makefile instead of replacing it, refusing to touch the rules domake doesn't own"),
+        Flag::switch(None, "dry-run", "Prints the generated Makefile without writing it"),
+        Flag::switch(None, "stdout", "Writes the Makefile to stdout (status messages go to stderr)"),
+        Flag::switch(Some('r'), "recursive", "Generates the Makefile next to every Dofile under the current directory, and a root Makefile running their targets"),
+        Flag::switch(None, "json", "Prints the result as JSON on stdout: the status, the warnings and the files written"),
+        Flag::switch(Some('q'), "quiet", "Prints nothing but the errors, on stderr, leaving out the progress messages and the warnings"),
+        Flag::switch(Some('v'), "verbose", "Traces on stderr the parsed constructs, the resolved includes and the files written; given twice, also traces each decision of the parser (e.g. the lines a `%profile` leaves out)"),
+        Flag::switch(Some('w'), "watch", "Keeps running and regenerates the Makefile whenever the Dofile or an included file changes"),
+    ],
+    notes: &[
+        "you need to have a valid `Dofile` in the current directory (or one given with `--file`); without one, the closest parent directory with a `Dofile` is used, up to the root of the git repository, and the Makefile is written there.",
+        "without a `Dofile`, a structured `Dofile.yml`, `Dofile.yaml` or `Dofile.toml` is read: its `targets` map each name to a `description`, `deps` and a `script`, next to its `shell`, `default`, `include`, `dotenv`, `use`, `variables` and raw `make` blocks.",
+        "with `--recursive`, the root Makefile gets a `sub/name` target running `make -C sub name` for each target of `sub/Dofile`, and a `name` target running it in every subdirectory; the targets of a root `Dofile` come first.",
+        "with `--merge`, the generated part goes between `# BEGIN DOMAKE` and `# END DOMAKE` at the end of the existing Makefile, without asking; the next generations only replace that part.",
+        "with `--from-json`, the `line` and `span` of each part, the booleans and the empty arrays can be left out of the model, for the programs building it.",
+        "with `-f Dofile -f Dofile.ci`, the targets of `Dofile.ci` replace those of `Dofile` with the same name, each one reported.",
+        "any existing `Makefile` (or file given with `--output`) will be erased, after confirmation if it wasn't generated by domake, unless the overwrite policy says otherwise.",
+    ],
+};
+
+/// How often `--watch` looks for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What to do when a Makefile exists, from `--overwrite`.
+enum Overwrite {
+    Prompt,
+    Always,
+    Never,
+    /// Overwrites it without asking, keeping at least one backup.
+    Backup,
+}
+
+struct Options {
+    dofiles: Vec<String>,
+    /// The JSON model read instead of the Dofiles, from `--from-json`.
+    json: Option<String>,
+    makefile: String,
+    overwrite: Overwrite,
+    merge: bool,
+    /// How many of the replaced Makefiles are kept.
+    backups: usize,
+    dry_run: bool,
+    stdout: bool,
+    recursive: bool,
+    watch: bool,
+    overrides: Overrides,
+}
+
+impl Options {
+    fn from_matches(matches: &Matches) -> Options {
+        if !matches.positionals.is_empty() {
+            fail(Some(matches.command), "Wrong argument");
+        }
+        let policy = matches.value("overwrite").map(str::to_string).or_else(|| env::var("DOMAKE_OVERWRITE").ok());
+        let overwrite = if matches.flag("force") {
+            Overwrite::Always
+        } else if matches.flag("no-overwrite") {
+            Overwrite::Never
+        } else {
+            match policy.as_deref() {
+                None | Some("prompt") => Overwrite::Prompt,
+                Some("always") => Overwrite::Always,
+                Some("never") => Overwrite::Never,
+                Some("backup") => Overwrite::Backup,
+                Some(other) => fail(Some(matches.command), &format!("`--overwrite` expects `prompt`, `always`, `never` or `backup`, not `{}`", other)),
+            }
+        };
+
+        let backups = match matches.value("backups").map(|count| count.parse::<usize>()) {
+            None => 1,
+            Some(Ok(count)) => count,
+            Some(Err(_)) => fail(Some(matches.command), "`--backups` expects a number of copies"),
+        };
+
+        Options {
+            dofiles: dofile_paths(matches),
+            json: matches.value("from-json").map(str::to_string),
+            makefile: matches.value("output").unwrap_or(DEFAULT_MAKEFILE).to_string(),
+            overwrite,
+            merge: matches.flag("merge"),
+            backups,
+            dry_run: matches.flag("dry-run"),
+            stdout: matches.flag("stdout"),
+            recursive: matches.flag("recursive"),
+            watch: matches.flag("watch"),
+            overrides: overrides(matches),
+        }
+    }
+}
+
+pub fn run(matches: &Matches) -> ! {
+    let options = Options::from_matches(matches);
+    if options.json.is_some() && (!matches.values("file").is_empty() || options.recursive || options.watch) {
+        fail(Some(matches.command), "`--from-json` reads the model instead of the Dofiles, it cannot be used with `--file`, `--recursive` or `--watch`");
+    }
+    let stdin = options.dofiles.iter().chain(&options.json).any(|dofile| dofile == STDIN_PATH);
+    if matches.flag("json") && (options.stdout || options.watch) {
+        fail(Some(matches.command), "`--json` prints the result on stdout, it cannot be used with `--stdout` or `--watch`");
+    }
+    if options.watch && (options.dry_run || options.stdout || stdin) {
+        fail(Some(matches.command), "`--watch` needs a Dofile on disk and a Makefile to write");
+    }
+    if options.recursive {
+        if !matches.values("file").is_empty() || options.dry_run || options.stdout || options.watch {
+            fail(Some(matches.command), "`--recursive` finds the Dofiles itself and writes their Makefiles, it cannot be used with `--file`, `--dry-run`, `--stdout` or `--watch`");
+        }
+        recursive(&options);
+    }
+
+    // status messages must not pollute the Makefile when it is written to stdout
+    let status = if options.stdout { Term::stderr() } else { Term::stdout() };
+    let report = |message: String| {
+        if output::shows_status() {
+            let _ = status.write_line(&message);
+        }
+    };
+
+    let dofile = match &options.json {
+        Some(path) => {
+            report(format!("{} {}", style("-> Model found:").bold().green(), source(path)));
+            prepare_json(path, &options.overrides)
+        }
+        None => {
+            for path in &options.dofiles {
+                report(format!("{} {}", style("-> Dofile found:").bold().green(), source(path)));
+            }
+            prepare_all(&options.dofiles, &options.overrides)
+        }
+    };
+    report(format!("{}", style("-> Content parsed").bold().green()));
+
+    let makefile = render(&dofile, &options.overrides);
+    if options.dry_run || options.stdout {
+        if options.dry_run {
+            report(format!("{}", style("-> Dry run, nothing written:").bold().green()));
+        }
+        if output::is_json() {
+            output::finish(0, "ok", vec![("makefile", makefile.as_str().into())]);
+        }
+        print!("{}", makefile);
+        exit(0);
+    }
+
+    let foreign = fs::read_to_string(&options.makefile).is_ok_and(|existing| !is_generated(&existing));
+    if stdin && matches!(options.overwrite, Overwrite::Prompt) && foreign {
+        abort("Cannot ask for confirmation while reading the Dofile from stdin, use `--overwrite always`, `never` or `backup`", 1)
+    }
+    let makefile = if options.merge { merge(&dofile, &options.makefile, makefile) } else { makefile };
+    if !save(&options, &options.makefile, &makefile) {
+        exit(0);
+    }
+    if options.watch {
+        watch(&options);
+    }
+    done()
+}
+
+/// Exits once the Makefiles are written, with the JSON result for `--json`.
+fn done() -> ! {
+    if output::is_json() {
+        output::finish(0, "ok", Vec::new());
+    }
+    exit(0)
+}
+
+/// Writes the Makefile at `path`, following the overwrite policy if one exists;
+/// returns whether it was written.
+fn save(options: &Options, path: &str, makefile: &str) -> bool {
+    if is_up_to_date(path, makefile) {
+        output::status(&format!("{} {}", style("-> Makefile is up to date:").bold().green(), path));
+        output::file(path, "up to date");
+        return true;
+    }
+    if is_makefile(path) {
+        match options.overwrite {
+            Overwrite::Always | Overwrite::Backup => {}
+            Overwrite::Never => abort(format!("A Makefile already exists at `{}`", path).as_str(), 3),
+            // nothing written by hand is lost
+            Overwrite::Prompt if options.merge || fs::read_to_string(path).is_ok_and(|existing| is_generated(&existing)) => {}
+            Overwrite::Prompt if output::is_json() => abort("Cannot ask for confirmation with `--json`, use `--overwrite always`, `never` or `backup`", 1),
+            Overwrite::Prompt => {
+                if !confirm(path, makefile) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    let backups = if matches!(options.overwrite, Overwrite::Backup) { options.backups.max(1) } else { options.backups };
+    match backup(path, makefile, backups) {
+        Ok(Some(copy)) => {
+            output::status(&format!("{} {}", style("-> Previous Makefile saved:").bold().green(), copy));
+            output::file(&copy, "backup");
+        }
+        Ok(None) => {}
+        Err(err) => abort(format!("Cannot back up `{}`: {}", path, err).as_str(), 2),
+    }
+    match write(path, makefile) {
+        Ok(_) => {
+            output::status(&format!("{} {}", style("-> Makefile successfully created:").bold().green(), path));
+            output::file(path, "written");
+            true
+        }
+        Err(err) => abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2),
+    }
+}
+
+/// Copies the Makefile at `path` before `makefile` replaces it, if it changes: to `Makefile.bak`,
+/// or to `Makefile.<time>.bak` when more than one copy is kept, removing the oldest ones.
+/// Returns the path of the copy.
+fn backup(path: &str, makefile: &str, count: usize) -> Result<Option<String>, std::io::Error> {
+    let Ok(existing) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    if count == 0 || comparable_lines(&existing) == comparable_lines(&merge_makefile(&existing, makefile)) {
+        return Ok(None);
+    }
+    if count == 1 {
+        let copy = format!("{}.bak", path);
+        fs::write(&copy, existing)?;
+        return Ok(Some(copy));
+    }
+
+    // the backups made within the same second are numbered after the first one
+    let time = chrono::offset::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut copy = format!("{}.{}.bak", path, time);
+    for number in 1.. {
+        if !Path::new(&copy).exists() {
+            break;
+        }
+        copy = format!("{}.{}-{}.bak", path, time, number);
+    }
+    fs::write(&copy, existing)?;
+    let target = Path::new(path);
+    let directory = target.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let prefix = format!("{}.", target.file_name().unwrap_or_default().to_string_lossy());
+    let mut copies = fs::read_dir(directory)?.flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter_map(|name| Some((backup_order(name.strip_prefix(&prefix)?.strip_suffix(".bak")?)?, name)))
+        .collect::<Vec<_>>();
+    copies.sort();
+    for (_, old) in &copies[..copies.len().saturating_sub(count)] {
+        fs::remove_file(directory.join(old))?;
+    }
+    Ok(Some(copy))
+}
+
+/// The time and number of a backup from the `%Y%m%d-%H%M%S` or `%Y%m%d-%H%M%S-N` of its name,
+/// which sort the oldest first.
+fn backup_order(stamp: &str) -> Option<(String, usize)> {
+    let (time, number) = stamp.split_at_checked(15)?;
+    if time.as_bytes()[8] != b'-' || !time.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return None;
+    }
+    match number {
+        "" => Some((time.to_string(), 0)),
+        _ => Some((time.to_string(), number.strip_prefix('-')?.parse().ok()?)),
+    }
+}
+
+/// The Makefile to write for `--merge`: the existing one followed by the generated part, unless
+/// it has it already; exits if a rule outside that part makes a target of the Dofile.
+fn merge(dofile: &Dofile, path: &str, makefile: String) -> String {
+    let Ok(existing) = fs::read_to_string(path) else {
+        return makefile;
+    };
+    let region = managed_region(&existing);
+    let outside = match &region {
+        Some(region) => format!("{}{}", &existing[..region.start], &existing[region.end..]),
+        None => existing.clone(),
+    };
+    let owned = dofile.commands.iter().map(|cmd| cmd.name.as_str())
+        .chain(dofile.raws.iter().flat_map(|raw| raw.targets()))
+        .chain(HELPER_TARGETS.iter().copied())
+        .collect::<Vec<_>>();
+    let mut conflicts = rule_targets(&outside).into_iter().filter(|name| owned.contains(name)).collect::<Vec<_>>();
+    conflicts.dedup();
+    if !conflicts.is_empty() {
+        let message = format!("`{}` already has rules for `{}`, which domake doesn't own; rename them or leave out `--merge`", path, conflicts.join("`, `"));
+        abort(&message, 3);
+    }
+    match region {
+        Some(_) => makefile,
+        None if existing.trim().is_empty() => makefile,
+        None => format!("{}\n\n{}", existing.trim_end(), makefile),
+    }
+}
+
+/// The subdirectories of `directory` with a Dofile, recursively and in order, leaving out
+/// the hidden ones (e.g. `.git`) and symbolic links.
+fn subdirectories(directory: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    let mut entries = entries.flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()) && !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    for entry in entries {
+        if find_dofile(&entry).is_some() {
+            found.push(entry.clone());
+        }
+        subdirectories(&entry, found);
+    }
+}
+
+/// Generates the Makefile next to every Dofile under the current directory, then the root
+/// Makefile running their targets with `make -C`.
+fn recursive(options: &Options) -> ! {
+    let mut found = Vec::new();
+    subdirectories(Path::new("."), &mut found);
+    if found.is_empty() {
+        abort("No Dofile found in the subdirectories", 1);
+    }
+    let root = env::current_dir().unwrap_or_else(|err| abort(format!("Cannot read the current directory: {}", err).as_str(), 2));
+
+    let mut dispatch: Vec<Command> = Vec::new();
+    for directory in found {
+        let name = directory.strip_prefix(".").unwrap_or(&directory).to_string_lossy().replace('\\', "/");
+        let path = find_dofile(&directory).unwrap_or(DEFAULT_DOFILE);
+        output::status(&format!("{} {}/{}", style("-> Dofile found:").bold().green(), name, path));
+        // its includes, `.env` files and used Dofiles are next to it
+        if let Err(err) = env::set_current_dir(&directory) {
+            abort(format!("Cannot enter `{}`: {}", name, err).as_str(), 2);
+        }
+        let dofile = prepare(path, &load_dofile(path), &options.overrides);
+        if let Err(err) = env::set_current_dir(&root) {
+            abort(format!("Cannot go back to `{}`: {}", root.display(), err).as_str(), 2);
+        }
+        save(options, &format!("{}/{}", name, DEFAULT_MAKEFILE), &render(&dofile, &options.overrides));
+
+        for cmd in dofile.commands.iter().filter(|cmd| !cmd.is_hidden() && !cmd.file) {
+            dispatch.push(Command {
+                line: 0,
+                span: 0..0,
+                name: format!("{}/{}", name, cmd.name),
+                file: false,
+                description: cmd.description.clone(),
+                prior_commands: Vec::new(),
+                requires: Vec::new(),
+                group: Some(name.clone()),
+                deprecated: None,
+                variables: Vec::new(),
+                oneshell: false,
+                notparallel: false,
+                instructions: vec![format!("@$(MAKE) -C {} {}", name, cmd.name)],
+            });
+        }
+    }
+
+    let mut dofile = match find_dofile(Path::new(".")) {
+        Some(path) => {
+            output::status(&format!("{} {}", style("-> Dofile found:").bold().green(), path));
+            prepare(path, &load_dofile(path), &options.overrides)
+        }
+        None => Dofile::default(),
+    };
+    // a target of every subdirectory that has it, unless the root Dofile defines it
+    let mut names = dispatch.iter().filter_map(|cmd| cmd.name.rsplit_once('/')).map(|(_, name)| name.to_string()).collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let prior_commands = dispatch.iter().filter(|cmd| cmd.name.rsplit_once('/').is_some_and(|(_, own)| own == name))
+            .map(|cmd| cmd.name.clone())
+            .collect::<Vec<_>>();
+        let directories = prior_commands.iter().filter_map(|prior| prior.rsplit_once('/')).map(|(directory, _)| directory).collect::<Vec<_>>();
+        let description = format!("Runs `{}` in {}", name, directories.join(", "));
+        dispatch.push(Command {
+            line: 0,
+            span: 0..0,
+            name,
+            file: false,
+            description,
+            prior_commands,
+            requires: Vec::new(),
+            group: None,
+            deprecated: None,
+            variables: Vec::new(),
+            oneshell: false,
+            notparallel: false,
+            instructions: Vec::new(),
+        });
+    }
+    for cmd in dispatch {
+        if !dofile.commands.iter().any(|own| own.name == cmd.name) {
+            dofile.commands.push(cmd);
+        }
+    }
+    save(options, &options.makefile, &render(&dofile, &options.overrides));
+    done()
+}
+
+/// The modification times of the Dofiles and of the files they include, load or use.
+fn snapshot(dofiles: &[String]) -> Vec<(String, Option<SystemTime>)> {
+    dofiles.iter().flat_map(|dofile| files(dofile)).collect()
+}
+
+/// The modification times of the Dofile and of the files it includes, loads or uses.
+fn files(dofile: &str) -> Vec<(String, Option<SystemTime>)> {
+    let modified = |path: &str| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let includes = read_file(dofile).map(|content| {
+        let mut parsed = if is_structured(dofile) { parse_structured_dofile(dofile, &content).unwrap_or_default() } else { parse_dofile_partial(&content).0 };
+        // the files matched by the patterns, so that a new one is noticed too
+        parsed.expand_includes();
+        let uses = parsed.uses.into_iter().map(|import| import.path);
+        parsed.includes.into_iter().chain(parsed.dotenvs).map(|include| include.path).chain(uses).collect::<Vec<_>>()
+    }).unwrap_or_default();
+    [dofile.to_string()].into_iter().chain(includes)
+        .map(|path| {
+            let time = modified(&path);
+            (path, time)
+        })
+        .collect()
+}
+
+/// Polls the Dofile and its includes, regenerating the Makefile on every change.
+fn watch(options: &Options) -> ! {
+    output::status(&format!("{}", style("-> Watching for changes (press Ctrl+C to stop)").bold().green()));
+    let mut last = snapshot(&options.dofiles);
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        let current = snapshot(&options.dofiles);
+        if current == last {
+            continue;
+        }
+        last = current;
+
+        let time = chrono::offset::Local::now().format("%H:%M:%S");
+        let result = options.dofiles.iter()
+            .try_fold(Dofile::default(), |mut dofile, path| {
+                let content = read_file(path).map_err(|err| format!("Cannot read `{}`: {}", path, err))?;
+                let mut layer = if is_structured(path) {
+                    parse_structured(path, &content, &dofile)?
+                } else {
+                    let (layer, errors) = parse_dofile_partial_with(&content, &options.overrides.context);
+                    if let Some(err) = first_error(errors, &dofile) {
+                        return Err(format!("{}:{}:{}: {}", source(path), err.line, err.column, err));
+                    }
+                    layer
+                };
+                layer.load_uses(&options.overrides.context)?;
+                layer.load_dotenvs()?;
+                dofile.layer(layer);
+                Ok(dofile)
+            })
+            .and_then(|mut dofile| {
+                customize(&mut dofile, &options.overrides);
+                let makefile = render(&dofile, &options.overrides);
+                if is_up_to_date(&options.makefile, &makefile) {
+                    return Ok(false);
+                }
+                write(&options.makefile, &makefile).map_err(|err| format!("Cannot write `{}`: {}", options.makefile, err))?;
+                Ok(true)
+            });
+        match result {
+            Ok(false) => output::status(&format!("[{}] {} {}", time, style("-> Makefile is up to date:").bold().green(), options.makefile)),
+            Ok(true) => output::status(&format!("[{}] {} {}", time, style("-> Makefile regenerated:").bold().green(), options.makefile)),
+            // the errors are kept with `--quiet`
+            Err(err) => eprintln!("[{}] {} {}", time, style("Error:").bold().red(), style(err).red()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_backups_of_a_same_second_sort_by_number() {
+        let mut stamps = ["20261016-120000-10", "20261016-120000-2", "20261016-120000", "20261015-235959-1"];
+        stamps.sort_by_key(|stamp| backup_order(stamp));
+        assert_eq!(stamps, ["20261015-235959-1", "20261016-120000", "20261016-120000-2", "20261016-120000-10"]);
+        assert_eq!(backup_order("20261016-120000-x"), None);
+        assert_eq!(backup_order("old"), None);
+    }
+}
@@ -0,0 +1,89 @@
+use std::process::exit;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::Command;
+use crate::{default_dofile, fail, load_dofile, parse};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "graph",
+    about: "Prints the dependency graph of the targets",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`)"),
+        Flag::option(None, "format", "FORMAT", "Prints the graph as `dot` (Graphviz) or `mermaid` (default: `dot`)"),
+    ],
+    notes: &[
+        "arrows go from a target to its prerequisites; prerequisites that aren't targets are drawn as files.",
+    ],
+};
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The prerequisites that aren't targets, i.e. files.
+fn files(commands: &[Command]) -> Vec<&str> {
+    let mut files = Vec::new();
+    for prior in commands.iter().flat_map(|cmd| cmd.prior_commands.iter().map(String::as_str)) {
+        if !commands.iter().any(|cmd| cmd.name == prior) && !files.contains(&prior) {
+            files.push(prior);
+        }
+    }
+    files
+}
+
+fn dot(commands: &[Command]) -> String {
+    let mut buffer = String::from("digraph domake {\n    rankdir=LR;\n    node [shape=box];\n");
+    for cmd in commands {
+        buffer.push_str(format!("    \"{}\" [tooltip=\"{}\"];\n", escape(&cmd.name), escape(cmd.summary())).as_str());
+    }
+    for file in files(commands) {
+        buffer.push_str(format!("    \"{}\" [shape=note];\n", escape(file)).as_str());
+    }
+    for cmd in commands {
+        for prior in cmd.prior_commands.iter().map(String::as_str) {
+            buffer.push_str(format!("    \"{}\" -> \"{}\";\n", escape(&cmd.name), escape(prior)).as_str());
+        }
+    }
+    buffer.push_str("}\n");
+    buffer
+}
+
+fn mermaid(commands: &[Command]) -> String {
+    let files = files(commands);
+    // mermaid ids can't contain most punctuation, so nodes are numbered
+    let id = |name: &str| {
+        match commands.iter().position(|cmd| cmd.name == name) {
+            Some(index) => format!("t{}", index),
+            None => format!("f{}", files.iter().position(|file| *file == name).unwrap()),
+        }
+    };
+
+    let mut buffer = String::from("graph LR\n");
+    for cmd in commands {
+        buffer.push_str(format!("    {}[\"{}\"]\n", id(&cmd.name), cmd.name.replace('"', "#quot;")).as_str());
+    }
+    for file in &files {
+        buffer.push_str(format!("    {}[/\"{}\"/]\n", id(file), file.replace('"', "#quot;")).as_str());
+    }
+    for cmd in commands {
+        for prior in cmd.prior_commands.iter().map(String::as_str) {
+            buffer.push_str(format!("    {} --> {}\n", id(&cmd.name), id(prior)).as_str());
+        }
+    }
+    buffer
+}
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let path = matches.value("file").unwrap_or(default_dofile());
+    let commands = parse(path, &load_dofile(path)).commands;
+
+    match matches.value("format").unwrap_or("dot") {
+        "dot" => print!("{}", dot(&commands)),
+        "mermaid" => print!("{}", mermaid(&commands)),
+        format => fail(Some(matches.command), format!("Unknown graph format `{}`", format).as_str()),
+    }
+    exit(0)
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::{import, parse_dofile};
+use crate::{abort, fail, DEFAULT_DOFILE};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "import",
+    about: "Writes a Dofile from the file of another task runner",
+    args: "[SOURCE]",
+    flags: &[
+        Flag::option(None, "from", "FORMAT", "Reads a `makefile` (default: `Makefile`), a `just` file (default: `justfile`), a `taskfile` of Task (default: `Taskfile.yml`) or the `cargo-make` tasks of a `Makefile.toml`"),
+        Flag::option(Some('f'), "file", "PATH", "Writes the Dofile at <PATH> (default: `Dofile`)"),
+        Flag::switch(Some('y'), "force", "Overwrites an existing Dofile").aliases(&["yes"]),
+        Flag::switch(None, "stdout", "Prints the Dofile instead of writing it"),
+    ],
+    notes: &[
+        "what has no Dofile equivalent is kept in ```make blocks when it is Makefile syntax, and reported otherwise (e.g. the includes and templates of a Taskfile).",
+    ],
+};
+
+/// Reads the file of another task runner.
+type Reader = fn(&str) -> Result<import::Import, String>;
+
+pub fn run(matches: &Matches) -> ! {
+    if matches.positionals.len() > 1 {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let (reader, defaults): (Reader, &[&str]) = match matches.value("from") {
+        Some("makefile") => (|content| Ok(import::makefile(content)), &["Makefile"]),
+        Some("just") => (|content| Ok(import::justfile(content)), &["justfile", "Justfile", ".justfile"]),
+        Some("taskfile") => (import::taskfile, &["Taskfile.yml", "taskfile.yml", "Taskfile.yaml", "taskfile.yaml", "Taskfile.dist.yml"]),
+        Some("cargo-make") => (import::cargo_make, &["Makefile.toml"]),
+        Some(other) => fail(Some(matches.command), format!("Unknown format `{}`, expected `makefile`, `just`, `taskfile` or `cargo-make`", other).as_str()),
+        None => fail(Some(matches.command), "Missing `--from`"),
+    };
+    let default = defaults.iter().find(|path| fs::exists(path).unwrap_or(false)).unwrap_or(&defaults[0]);
+    let source = matches.positionals.first().map(String::as_str).unwrap_or(default);
+    let content = fs::read_to_string(source).unwrap_or_else(|err| abort(format!("Cannot read `{}`: {}", source, err).as_str(), 2));
+
+    let imported = reader(&content).unwrap_or_else(|err| abort(format!("Cannot read `{}`: {}", source, err).as_str(), 2));
+    for note in &imported.notes {
+        eprintln!("{} {}", style("Warning:").bold().yellow(), style(note).yellow());
+    }
+    let dofile = imported.to_dofile(source);
+    if let Err(err) = parse_dofile(&dofile) {
+        let message = format!("the imported Dofile needs fixing by hand: {}", err);
+        eprintln!("{} {}", style("Warning:").bold().yellow(), style(message).yellow());
+    }
+
+    if matches.flag("stdout") {
+        print!("{}", dofile);
+        exit(0);
+    }
+    let path = matches.value("file").unwrap_or(DEFAULT_DOFILE);
+    if fs::exists(path).unwrap_or(false) && !matches.flag("force") {
+        abort(format!("A Dofile already exists at `{}`, use `--force` to overwrite it", path).as_str(), 3);
+    }
+    if let Err(err) = fs::write(path, dofile) {
+        abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2);
+    }
+    println!("{} {}", style("-> Dofile imported:").bold().green(), path);
+    let report = format!("{} target(s), {} variable(s), {} thing(s) to check above", imported.targets.len(), imported.variables.len(), imported.notes.len());
+    println!("{} {}", style("-> Conversion report:").bold().green(), report);
+    exit(0)
+}
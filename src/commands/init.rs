@@ -0,0 +1,33 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use crate::{abort, fail, DEFAULT_DOFILE};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "init",
+    about: "Creates a starter Dofile in the current directory",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Creates the Dofile at <PATH> (default: `Dofile`)"),
+        Flag::switch(Some('y'), "force", "Overwrites an existing Dofile").aliases(&["yes"]),
+    ],
+    notes: &[],
+};
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let path = matches.value("file").unwrap_or(DEFAULT_DOFILE);
+
+    if fs::exists(path).unwrap_or(false) && !matches.flag("force") {
+        abort(format!("A Dofile already exists at `{}`, use `--force` to overwrite it", path).as_str(), 3);
+    }
+
+    if let Err(err) = fs::write(path, include_str!("../../starter_dofile")) {
+        abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2);
+    }
+    println!("{} {}", style("-> Dofile successfully created:").bold().green(), path);
+    exit(0)
+}
@@ -0,0 +1,313 @@
+use std::ops::Range;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::{is_structured, parse_dofile_partial_with, parse_structured_dofile, prerequisite_span, Command, Context, ErrorKind, ParseError, HELPER_TARGETS};
+use domake::json::Json;
+use crate::output;
+use crate::report::{self, Label};
+use crate::{default_dofile, fail, is_installed, load_dofile, source, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "lint",
+    about: "Reports mistakes in the Dofile",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`)"),
+        Flag::option(None, "profile", "NAME", "Checks the `%profile` sections of the profile <NAME> (e.g. `dev`, `ci`, `release`)"),
+        Flag::switch(None, "json", "Prints the result as JSON on stdout: the status, the warnings and the diagnostics"),
+        Flag::switch(Some('q'), "quiet", "Prints only the problems that fail the check, leaving out the warnings and the message when there are none"),
+        Flag::switch(Some('v'), "verbose", "Traces on stderr the parsed constructs and the resolved includes of the Dofile linted; given twice, also traces each decision of the parser (e.g. the lines a `%profile` leaves out)"),
+        Flag::option(None, "format", "FORMAT", "Prints the problems as `text` or as a `sarif` log, for GitHub code scanning and the other SARIF consumers (default: `text`)"),
+    ],
+    notes: NOTES,
+};
+
+/// A rule of the lint, listed in the help and in the SARIF log.
+struct Rule {
+    code: &'static str,
+    name: &'static str,
+    description: &'static str,
+}
+
+/// Defines the `RULES` from their code, name, ` (warning)` if they only warn, and description,
+/// with the `NOTES` of the help listing them before the `notes` given.
+macro_rules! rules {
+    ($($code:literal $name:literal $severity:literal: $description:literal,)* notes: [$($note:literal,)*]) => {
+        const RULES: &[Rule] = &[$(Rule { code: $code, name: $name, description: $description },)*];
+        const NOTES: &[&str] = &[$(concat!($code, " ", $name, $severity, ": ", $description),)* $($note,)*];
+    };
+}
+
+rules! {
+    "D000" "syntax-error" "": "a line of the Dofile cannot be read.",
+    "D001" "missing-description" "": "a target has no `#` description line.",
+    "D002" "duplicate-target" "": "a target is defined more than once.",
+    "D003" "empty-recipe" "": "a target has no instructions.",
+    "D004" "undefined-prerequisite" "": "a prerequisite is neither a target nor an existing file.",
+    "D005" "reserved-target" "": "a target clashes with a helper of the generated Makefile.",
+    "D006" "circular-dependency" "": "targets require each other.",
+    "D007" "invalid-name" "": "a target name contains whitespace or one of `:;%$#=`.",
+    "D008" "missing-tool" " (warning)": "a tool of a `requires:` line is not found in the PATH.",
+    "D009" "deprecated-prerequisite" " (warning)": "a target requires a target marked `deprecated:`.",
+    notes: [
+        "a structured Dofile is checked on the targets it declares, its problems have no line.",
+        "exits with status 1 when violations other than warnings are found.",
+    ]
+}
+
+struct Diagnostic {
+    code: &'static str,
+    /// Whether the Dofile is fine and only this machine lacks something.
+    warning: bool,
+    /// 0 for a problem of a structured Dofile, which has no line to point at.
+    line: usize,
+    message: String,
+    labels: Vec<Label>,
+    hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// The column of the problem on its line, starting at 1, or 0 without a line.
+    fn column(&self, content: &str) -> usize {
+        if self.line == 0 {
+            return 0;
+        }
+        let start = self.labels.first().map_or(0, |label| label.span.start.min(content.len()));
+        content[..start].chars().rev().take_while(|c| *c != '\n').count() + 1
+    }
+}
+
+/// The rules as SARIF `reportingDescriptor`s: their id, name and description.
+fn rules() -> Vec<Json> {
+    RULES.iter().map(|rule| {
+        Json::object([("id", rule.code.into()), ("name", rule.name.into()), ("shortDescription", Json::object([("text", rule.description.into())]))])
+    }).collect()
+}
+
+/// The problems of the Dofile at `path` as a SARIF 2.1.0 log of a single run; a Dofile read from
+/// stdin has no URI, so its results only have a region, and a problem without a line none.
+fn sarif(path: &str, content: &str, diagnostics: &[Diagnostic]) -> Json {
+    let results = diagnostics.iter().map(|diagnostic| {
+        let mut physical = Vec::new();
+        if path != STDIN_PATH {
+            physical.push(("artifactLocation".to_string(), Json::object([("uri", path.into())])));
+        }
+        if diagnostic.line > 0 {
+            let region = Json::object([("startLine", diagnostic.line.into()), ("startColumn", diagnostic.column(content).into())]);
+            physical.push(("region".to_string(), region));
+        }
+        let locations = if physical.is_empty() { Vec::new() } else { vec![Json::object([("physicalLocation", Json::Object(physical))])] };
+        let message = match &diagnostic.hint {
+            Some(hint) => format!("{} ({})", diagnostic.message, hint),
+            None => diagnostic.message.clone(),
+        };
+        Json::object([
+            ("ruleId", diagnostic.code.into()),
+            ("level", if diagnostic.warning { "warning" } else { "error" }.into()),
+            ("message", Json::object([("text", message.as_str().into())])),
+            ("locations", Json::Array(locations)),
+        ])
+    }).collect::<Vec<_>>();
+    let driver = Json::object([("name", "domake".into()), ("version", env!("CARGO_PKG_VERSION").into()), ("rules", Json::Array(rules()))]);
+    Json::object([
+        ("$schema", "https://json.schemastore.org/sarif-2.1.0.json".into()),
+        ("version", "2.1.0".into()),
+        ("runs", Json::Array(vec![Json::object([("tool", Json::object([("driver", driver)])), ("results", Json::Array(results))])])),
+    ])
+}
+
+/// The rule broken by a mistake that keeps the parser from reading a target.
+fn code(kind: &ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::MissingDescription(_) => "D001",
+        ErrorKind::EmptyRecipe(_) => "D003",
+        ErrorKind::DuplicateTarget(..) => "D002",
+        ErrorKind::CircularDependency(_) => "D006",
+        ErrorKind::UndefinedPrerequisite(..) => "D004",
+        ErrorKind::InvalidName(..) => "D007",
+        _ => "D000",
+    }
+}
+
+/// The bytes of `tool` in the `requires:` line of `cmd`, or else of its header.
+fn requires_span(content: &str, cmd: &Command, tool: &str) -> Range<usize> {
+    let mut start = cmd.span.start;
+    for line in content[cmd.span.start..].split_inclusive('\n').take_while(|line| !line.trim().is_empty()) {
+        if line.trim_start().starts_with("requires:") {
+            if let Some(index) = line.find(tool) {
+                return start + index..start + index + tool.len();
+            }
+        }
+        start += line.len();
+    }
+    cmd.span.clone()
+}
+
+fn lint(content: &str, commands: &[Command], errors: &[ParseError]) -> Vec<Diagnostic> {
+    let mut diagnostics = errors.iter().map(|err| Diagnostic {
+        code: code(&err.kind),
+        warning: false,
+        line: err.line,
+        message: err.to_string(),
+        labels: report::labels(err),
+        hint: Some(err.hint()),
+    }).collect::<Vec<_>>();
+
+    for cmd in commands {
+        if HELPER_TARGETS.contains(&cmd.name.as_str()) {
+            diagnostics.push(Diagnostic {
+                code: "D005",
+                warning: false,
+                line: cmd.line,
+                message: format!("target `{}` is already defined by the Makefile helpers", cmd.name),
+                labels: vec![Label::new(cmd.span.clone(), "")],
+                hint: Some("`help` and `confirm` are always generated, rename the target".to_string()),
+            });
+        }
+        for prior in &cmd.prior_commands {
+            let Some(advice) = commands.iter().find(|other| &other.name == prior).and_then(|other| other.deprecated.as_ref()) else {
+                continue;
+            };
+            let span = prerequisite_span(content, cmd, prior);
+            diagnostics.push(Diagnostic {
+                code: "D009",
+                warning: true,
+                line: content[..span.start].matches('\n').count() + 1,
+                message: format!("target `{}` requires `{}`, which is deprecated", cmd.name, prior),
+                labels: vec![Label::new(span, "")],
+                hint: Some(if advice.is_empty() { format!("stop requiring `{}`", prior) } else { advice.clone() }),
+            });
+        }
+        for tool in cmd.requires.iter().filter(|tool| !is_installed(tool)) {
+            let span = requires_span(content, cmd, tool);
+            diagnostics.push(Diagnostic {
+                code: "D008",
+                warning: true,
+                line: content[..span.start].matches('\n').count() + 1,
+                message: format!("target `{}` requires `{}`, which is not installed", cmd.name, tool),
+                labels: vec![Label::new(span, "")],
+                hint: Some(format!("install `{}`, or add its directory to the PATH", tool)),
+            });
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+/// Lints the structured Dofile at `path` on its model, a document that cannot be read being its
+/// only problem.
+fn lint_structured(path: &str, content: &str) -> Vec<Diagnostic> {
+    let dofile = match parse_structured_dofile(path, content) {
+        Ok(dofile) => dofile,
+        Err(message) => return vec![Diagnostic { code: "D000", warning: false, line: 0, message, labels: Vec::new(), hint: None }],
+    };
+    let mut diagnostics = lint("", &dofile.commands, &dofile.problems());
+    for diagnostic in &mut diagnostics {
+        diagnostic.line = 0;
+        diagnostic.labels.clear();
+    }
+    diagnostics
+}
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let sarif_log = match matches.value("format") {
+        None | Some("text") => false,
+        Some("sarif") => true,
+        Some(other) => fail(Some(matches.command), format!("`--format` expects `text` or `sarif`, not `{}`", other).as_str()),
+    };
+    if sarif_log && matches.flag("json") {
+        fail(Some(matches.command), "`--json` and `--format sarif` both print the result on stdout, choose one");
+    }
+    let path = matches.value("file").unwrap_or(default_dofile());
+    let content = load_dofile(path);
+    let diagnostics = if is_structured(path) {
+        lint_structured(path, &content)
+    } else {
+        let context = Context { profile: matches.value("profile").map(str::to_string), ..Context::default() };
+        let (dofile, errors) = parse_dofile_partial_with(&content, &context);
+        lint(&content, &dofile.commands, &errors)
+    };
+    // warnings alone don't fail the check
+    let failed = diagnostics.iter().any(|diagnostic| !diagnostic.warning);
+    if output::is_json() {
+        let found = diagnostics.iter()
+            .map(|diagnostic| output::diagnostic(source(path), diagnostic.line, diagnostic.column(&content), Some(diagnostic.code), diagnostic.warning, &diagnostic.message, diagnostic.hint.as_deref()))
+            .collect::<Vec<_>>();
+        output::finish(if failed { 1 } else { 0 }, if failed { "failed" } else { "ok" }, vec![("diagnostics", Json::Array(found))]);
+    }
+    if sarif_log {
+        println!("{}", sarif(path, &content, &diagnostics).pretty());
+        exit(if failed { 1 } else { 0 });
+    }
+    if diagnostics.is_empty() || (output::is_quiet() && !failed) {
+        output::status(&format!("{}", style("-> No problem found").bold().green()));
+        exit(0);
+    }
+
+    for diagnostic in diagnostics.iter().filter(|diagnostic| !(output::is_quiet() && diagnostic.warning)) {
+        let code = if diagnostic.warning { style(diagnostic.code).bold().yellow() } else { style(diagnostic.code).bold().red() };
+        if diagnostic.line == 0 {
+            println!("{}: {} {}", source(path), code, diagnostic.message);
+        } else {
+            println!("{}:{}: {} {}", source(path), diagnostic.line, code, diagnostic.message);
+        }
+        print!("{}", report::snippet(&content, &diagnostic.labels, diagnostic.hint.as_deref()));
+    }
+    let summary = format!("-> {} problem(s) found", diagnostics.iter().filter(|diagnostic| !(output::is_quiet() && diagnostic.warning)).count());
+    if !failed {
+        println!("{}", style(summary).bold().yellow());
+        exit(0)
+    }
+    println!("{}", style(summary).bold().red());
+    exit(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_notes_list_the_rules() {
+        assert_eq!(NOTES[0], "D000 syntax-error: a line of the Dofile cannot be read.");
+        assert_eq!(NOTES[8], "D008 missing-tool (warning): a tool of a `requires:` line is not found in the PATH.");
+        assert_eq!(NOTES.len(), RULES.len() + 2);
+        assert_eq!(rules()[9].get("id").and_then(Json::as_str), Some("D009"));
+    }
+
+    #[test]
+    fn a_dofile_read_from_stdin_has_no_artifact_location() {
+        let content = "[build] nope\n# Builds\necho build\n";
+        let (dofile, errors) = parse_dofile_partial_with(content, &Context::default());
+        let diagnostics = lint(content, &dofile.commands, &errors);
+        let location = |path: &str| {
+            let log = sarif(path, content, &diagnostics);
+            let result = &log.get("runs").and_then(Json::as_array).unwrap()[0].get("results").and_then(Json::as_array).unwrap()[0];
+            result.get("locations").and_then(Json::as_array).unwrap()[0].get("physicalLocation").cloned().unwrap()
+        };
+        assert!(location(STDIN_PATH).get("artifactLocation").is_none());
+        assert_eq!(location("Dofile").get("artifactLocation").and_then(|artifact| artifact.get("uri")).and_then(Json::as_str), Some("Dofile"));
+    }
+
+    #[test]
+    fn a_structured_dofile_is_linted_on_its_model() {
+        let content = "targets:\n  help:\n    description: Helps\n    script: echo help\n  build:\n    description: Builds\n    deps: [missing]\n    script: echo build\n";
+        let diagnostics = lint_structured("Dofile.yml", content);
+        let mut codes = diagnostics.iter().map(|diagnostic| diagnostic.code).collect::<Vec<_>>();
+        codes.sort();
+        assert_eq!(codes, ["D004", "D005"]);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.line == 0 && diagnostic.column(content) == 0));
+        let results = sarif("Dofile.yml", content, &diagnostics);
+        let result = &results.get("runs").and_then(Json::as_array).unwrap()[0].get("results").and_then(Json::as_array).unwrap()[0];
+        let location = &result.get("locations").and_then(Json::as_array).unwrap()[0];
+        assert!(location.get("physicalLocation").and_then(|physical| physical.get("region")).is_none());
+
+        let diagnostics = lint_structured("Dofile.yml", "targets: [\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "D000");
+    }
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use std::process::{exit, Command as Process};
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use crate::{abort, dofile_paths, overrides, prepare_all, regenerate, Overrides, DEFAULT_MAKEFILE, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "make",
+    about: "Regenerates the Makefile if the Dofile changed, then runs make",
+    args: "[TARGET]... [-- MAKE_ARGS...]",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`); repeated, each one is laid over the ones before"),
+        Flag::option(Some('D'), "define", "KEY=VALUE", "Sets the variable <KEY> to <VALUE>, overriding the Dofile, can be repeated"),
+        Flag::option(None, "profile", "NAME", "Keeps the `%profile` sections of the profile <NAME> (e.g. `dev`, `ci`, `release`)"),
+        Flag::switch(None, "silent-all", "Silences every instruction, as if they all started with `@`"),
+        Flag::option(None, "target-os", "OS", "Keeps only the `@OS:` instructions of <OS> (`linux`, `macos` or `windows`) instead of choosing when make runs"),
+        Flag::option(None, "target-arch", "ARCH", "Keeps only the `@ARCH:` instructions of <ARCH> (`amd64` or `arm64`) instead of choosing when make runs"),
+        Flag::switch(None, "expand-includes", "Includes each file matched by the `*` and `?` of the includes, instead of leaving the patterns to make"),
+        Flag::switch(None, "reproducible", "Leaves the date out of the header, so that the same Dofile always gives the same Makefile"),
+        Flag::option(None, "sort", "ORDER", "Orders the targets of the Makefile: `source`, `name` or `dependencies` (prerequisites first) (default: `source`)"),
+        Flag::option(None, "make-version", "VERSION", "Writes the Makefile for GNU make <VERSION> (e.g. `3.81`, `4.x`), warning of what it doesn't support (default: the latest)"),
+        Flag::option(Some('o'), "output", "PATH", "Writes the Makefile to <PATH> (default: `Makefile`)"),
+        Flag::switch(Some('v'), "verbose", "Traces on stderr the parsed constructs, the resolved includes and the Makefile written, before make runs; given twice, also traces each decision of the parser (e.g. the lines a `%profile` leaves out)"),
+    ],
+    notes: &[
+        "every argument is forwarded to make; use `--` before make's own options (e.g. `domake make build -- -j4`).",
+        "only Makefiles generated by domake are overwritten; run `domake gen` to replace another one.",
+        "the `MAKE` environment variable selects the make program (default: `make`).",
+    ],
+};
+
+/// Whether the Makefile is missing or older than one of the Dofiles.
+fn is_outdated(dofiles: &[String], makefile: &str) -> bool {
+    let modified = |path: &str| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    dofiles.iter().any(|dofile| match (modified(dofile), modified(makefile)) {
+        _ if dofile == STDIN_PATH => true,
+        (Some(dofile), Some(makefile)) => dofile > makefile,
+        _ => true,
+    })
+}
+
+pub fn run(matches: &Matches) -> ! {
+    let makefile = matches.value("output").unwrap_or(DEFAULT_MAKEFILE);
+    make(&dofile_paths(matches), makefile, &overrides(matches), &matches.positionals)
+}
+
+/// Regenerates the Makefile from the Dofiles if needed, then runs make with `args` and exits with its status.
+pub fn make(dofiles: &[String], makefile: &str, overrides: &Overrides, args: &[String]) -> ! {
+    // the profile and variables given on the command line may differ from the last generation
+    if *overrides != Overrides::default() || is_outdated(dofiles, makefile) {
+        match regenerate(&prepare_all(dofiles, overrides), overrides, makefile) {
+            Ok(true) => println!("{} {}", style("-> Makefile regenerated:").bold().green(), makefile),
+            Ok(false) => {}
+            Err(err) => abort(&err, 3),
+        }
+    }
+
+    let program = env::var("MAKE").unwrap_or_else(|_| "make".to_string());
+    let mut make = Process::new(&program);
+    if makefile != DEFAULT_MAKEFILE {
+        make.args(["-f", makefile]);
+    }
+    make.args(args);
+
+    match make.status() {
+        Ok(status) => exit(status.code().unwrap_or(2)),
+        Err(err) => abort(format!("Cannot run `{}`: {}", program, err).as_str(), 2),
+    }
+}
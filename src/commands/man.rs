@@ -0,0 +1,79 @@
+use std::process::exit;
+use crate::cli::{Flag, Matches, Subcommand, GLOBAL_FLAGS};
+use crate::commands::ALL;
+use crate::fail;
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "man",
+    about: "Prints the man page of domake",
+    args: "",
+    flags: &[],
+    notes: &[
+        "e.g. `domake man > domake.1` or `domake man | man -l -`.",
+    ],
+};
+
+/// Escapes text for roff, so that it is never read as a request or an escape.
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+fn flag(buffer: &mut String, flag: &Flag) {
+    let mut names = Vec::new();
+    if let Some(short) = flag.short {
+        names.push(format!("\\fB\\-{}\\fR", short));
+    }
+    names.push(format!("\\fB\\-\\-{}\\fR", escape(flag.long)));
+    names.extend(flag.aliases.iter().map(|alias| format!("\\fB\\-\\-{}\\fR", escape(alias))));
+    let value = flag.value.map(|value| format!(" \\fI{}\\fR", value)).unwrap_or_default();
+
+    buffer.push_str(format!(".TP\n{}{}\n{}\n", names.join(", "), value, escape(flag.help)).as_str());
+}
+
+fn page() -> String {
+    let mut buffer = String::new();
+    buffer.push_str(format!(".TH DOMAKE 1 \"\" \"{} {}\" \"User Commands\"\n", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")).as_str());
+    buffer.push_str(".SH NAME\ndomake \\- generate a Makefile from a simpler Dofile\n");
+    buffer.push_str(".SH SYNOPSIS\n\\fBdomake\\fR [\\fICOMMAND\\fR] [\\fIOPTIONS\\fR]\n");
+    buffer.push_str(".SH DESCRIPTION\n\\fBdomake\\fR is a simple CLI tool that generates a Makefile from a custom and simpler file named \\fIDofile\\fR.\n");
+    buffer.push_str(".PP\nWithout a command, \\fBdomake\\fR runs \\fBgen\\fR with the given options.\n");
+
+    buffer.push_str(".SH OPTIONS\n");
+    for global in GLOBAL_FLAGS {
+        flag(&mut buffer, global);
+    }
+
+    buffer.push_str(".SH COMMANDS\n");
+    for cmd in ALL {
+        let args = if cmd.args.is_empty() { String::new() } else { format!(" \\fI{}\\fR", escape(cmd.args)) };
+        buffer.push_str(format!(".SS \"domake {} [OPTIONS]{}\"\n{}\n", cmd.name, args, escape(cmd.about)).as_str());
+        if !cmd.flags.is_empty() {
+            buffer.push_str(".RS\n");
+            for command_flag in cmd.flags {
+                flag(&mut buffer, command_flag);
+            }
+            buffer.push_str(".RE\n");
+        }
+        for note in cmd.notes {
+            buffer.push_str(format!(".PP\n{}\n", escape(note)).as_str());
+        }
+    }
+
+    buffer.push_str(".SH FILES\n.TP\n\\fIDofile\\fR\nThe targets to generate, read from the current directory by default.\n");
+    buffer.push_str(".TP\n\\fIMakefile\\fR\nThe generated Makefile, written in the current directory by default.\n");
+    buffer.push_str(".SH EXIT STATUS\n0 on success, 1 on usage errors or failed checks, 2 when a file cannot be written or a target fails, 3 when an existing file is kept.\n");
+    buffer
+}
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    print!("{}", page());
+    exit(0)
+}
@@ -0,0 +1,72 @@
+use crate::cli::{Matches, Subcommand};
+
+mod add;
+mod check;
+mod completions;
+mod diff;
+mod explain;
+mod export;
+mod fmt;
+mod gen;
+mod graph;
+mod import;
+mod init;
+mod lint;
+mod make;
+mod man;
+mod remove;
+mod rename;
+mod run;
+mod targets;
+mod ui;
+
+/// Every subcommand, in the order they are listed in the help.
+pub const ALL: &[&Subcommand] = &[
+    &gen::SPEC,
+    &init::SPEC,
+    &run::SPEC,
+    &make::SPEC,
+    &check::SPEC,
+    &diff::SPEC,
+    &lint::SPEC,
+    &fmt::SPEC,
+    &graph::SPEC,
+    &targets::SPEC,
+    &explain::SPEC,
+    &export::SPEC,
+    &import::SPEC,
+    &add::SPEC,
+    &remove::SPEC,
+    &rename::SPEC,
+    &ui::SPEC,
+    &completions::SPEC,
+    &man::SPEC,
+];
+
+/// The subcommand used when `domake` is called without one.
+pub const DEFAULT: &Subcommand = &gen::SPEC;
+
+pub fn run(matches: &Matches) -> ! {
+    match matches.command.name {
+        "gen" => gen::run(matches),
+        "init" => init::run(matches),
+        "run" => run::run(matches),
+        "make" => make::run(matches),
+        "check" => check::run(matches),
+        "diff" => diff::run(matches),
+        "lint" => lint::run(matches),
+        "fmt" => fmt::run(matches),
+        "graph" => graph::run(matches),
+        "targets" => targets::run(matches),
+        "explain" => explain::run(matches),
+        "export" => export::run(matches),
+        "import" => import::run(matches),
+        "add" => add::run(matches),
+        "remove" => remove::run(matches),
+        "rename" => rename::run(matches),
+        "ui" => ui::run(matches),
+        "completions" => completions::run(matches),
+        "man" => man::run(matches),
+        name => unreachable!("subcommand `{}` has no handler", name),
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::{block_end, Command, Dofile};
+use crate::{abort, default_dofile, expect_dofile_syntax, fail, load_dofile, parse, prepare_over, regenerate, Overrides, DEFAULT_MAKEFILE, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "remove",
+    about: "Removes a target from the Dofile and regenerates the Makefile",
+    args: "<TARGET>",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Edits the Dofile at <PATH> (default: `Dofile`)"),
+        Flag::option(Some('o'), "output", "PATH", "Regenerates the Makefile at <PATH> (default: `Makefile`)"),
+        Flag::switch(Some('y'), "force", "Removes the target even if other targets depend on it"),
+        Flag::switch(None, "no-gen", "Only edits the Dofile"),
+    ],
+    notes: &[
+        "the Makefile is only regenerated if it was generated by domake.",
+    ],
+};
+
+/// Removes the block starting at `header` (1-based), where the parser ends it, along with the
+/// blank line separating it from its neighbours.
+fn remove_block(content: &str, header: usize) -> String {
+    let mut lines = content.lines().collect::<Vec<_>>();
+    let mut start = header - 1;
+    let mut end = block_end(&lines, start);
+
+    if lines.get(end).is_some_and(|line| line.trim().is_empty()) {
+        end += 1;
+    } else if start > 0 && lines[start - 1].trim().is_empty() {
+        // the last block of the file, or of its section, takes the blank line before it
+        start -= 1;
+    }
+    lines.drain(start..end);
+    join(lines)
+}
+
+/// Removes the line `number` (1-based), e.g. the `default` line of a removed target, along with
+/// the blank line after it when it stood apart.
+fn remove_line(content: &str, number: usize) -> String {
+    let mut lines = content.lines().collect::<Vec<_>>();
+    lines.remove(number - 1);
+    let apart = number == 1 || lines[number - 2].trim().is_empty();
+    if apart && lines.get(number - 1).is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(number - 1);
+    } else if apart && number > 1 && number > lines.len() {
+        // the last line takes the blank line before it
+        lines.remove(number - 2);
+    }
+    join(lines)
+}
+
+/// Removes the target `cmd` of the Dofile, and the `default` line naming it.
+fn remove_target(content: &str, dofile: &Dofile, cmd: &Command) -> String {
+    // the later of the block and the `default` line goes first, so that the other keeps its number
+    match dofile.default.as_ref().filter(|goal| goal.name == cmd.name) {
+        Some(goal) if goal.line > cmd.line => remove_block(&remove_line(content, goal.line), cmd.line),
+        Some(goal) => remove_line(&remove_block(content, cmd.line), goal.line),
+        None => remove_block(content, cmd.line),
+    }
+}
+
+fn join(lines: Vec<&str>) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+    let mut buffer = lines.join("\n");
+    buffer.push('\n');
+    buffer
+}
+
+pub fn run(matches: &Matches) -> ! {
+    let [target] = matches.positionals.as_slice() else {
+        fail(Some(matches.command), "Expected exactly one target");
+    };
+    let path = matches.value("file").unwrap_or(default_dofile());
+    expect_dofile_syntax(matches.command, path);
+    if path == STDIN_PATH {
+        fail(Some(matches.command), "Cannot edit a Dofile read from stdin");
+    }
+    let makefile = matches.value("output").unwrap_or(DEFAULT_MAKEFILE);
+
+    let content = load_dofile(path);
+    let dofile = parse(path, &content);
+    let commands = &dofile.commands;
+    let Some(cmd) = commands.iter().find(|cmd| &cmd.name == target) else {
+        abort(format!("No target named `{}` in `{}`", target, path).as_str(), 1);
+    };
+
+    let dependents = commands.iter()
+        .filter(|other| other.prior_commands.iter().any(|prior| prior == target))
+        .map(|other| other.name.as_str())
+        .collect::<Vec<_>>();
+    if !dependents.is_empty() {
+        let message = format!("Target `{}` is a prerequisite of: {}", target, dependents.join(", "));
+        if !matches.flag("force") {
+            abort(format!("{} (use `--force` to remove it anyway)", message).as_str(), 1);
+        }
+        println!("{} {}", style("Warning:").bold().yellow(), style(message).yellow());
+    }
+
+    // the prerequisites left to the removed target fail make, not the regeneration
+    let removed = Dofile { commands: vec![cmd.clone()], ..Dofile::default() };
+    if dofile.default.as_ref().is_some_and(|goal| &goal.name == target) {
+        println!("{} {}", style("Warning:").bold().yellow(), style(format!("Target `{}` was the default, its `default` line is removed", target)).yellow());
+    }
+    let content = remove_target(&content, &dofile, cmd);
+    if let Err(err) = fs::write(path, &content) {
+        abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2);
+    }
+    println!("{} {}", style(format!("-> Target `{}` removed from", target)).bold().green(), path);
+
+    if !matches.flag("no-gen") {
+        match regenerate(&prepare_over(path, &content, &Overrides::default(), &removed), &Overrides::default(), makefile) {
+            Ok(_) => println!("{} {}", style("-> Makefile regenerated:").bold().green(), makefile),
+            Err(err) => println!("{} {}", style("Warning:").bold().yellow(), style(err).yellow()),
+        }
+    }
+    exit(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domake::parse_dofile;
+
+    fn remove(content: &str, name: &str) -> String {
+        let dofile = parse_dofile(content).unwrap();
+        let cmd = dofile.commands.iter().find(|cmd| cmd.name == name).unwrap();
+        remove_target(content, &dofile, cmd)
+    }
+
+    #[test]
+    fn the_indented_lines_after_a_blank_line_are_removed_with_the_target() {
+        let content = "[build]\n# Builds\necho build\n\n  echo spaced\n\n[lib]\n# Lib\necho lib\n";
+        assert_eq!(remove(content, "build"), "[lib]\n# Lib\necho lib\n");
+    }
+
+    #[test]
+    fn the_sections_around_a_target_are_kept() {
+        let content = "[lib]\n# Lib\necho lib\n\n%if os linux\n[build]\n# Builds\necho build\n%end\n";
+        assert_eq!(remove(content, "build"), "[lib]\n# Lib\necho lib\n\n%if os linux\n%end\n");
+    }
+
+    #[test]
+    fn removing_the_default_target_removes_the_default_line() {
+        let content = "default build\n\n[build]\n# Builds\necho build\n\n[lib]\n# Lib\necho lib\n";
+        let content = remove(content, "build");
+        assert_eq!(content, "[lib]\n# Lib\necho lib\n");
+        assert!(parse_dofile(&content).unwrap().default.is_none());
+    }
+
+    #[test]
+    fn a_default_line_after_the_target_is_removed_too() {
+        let content = "[lib]\n# Lib\necho lib\n\n[build]\n# Builds\necho build\n\ndefault build\n";
+        assert_eq!(remove(content, "build"), "[lib]\n# Lib\necho lib\n");
+        assert_eq!(remove(content, "lib"), "[build]\n# Builds\necho build\n\ndefault build\n");
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::process::exit;
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::{is_valid_name, Dofile};
+use crate::{abort, default_dofile, expect_dofile_syntax, fail, load_dofile, parse, prepare, regenerate, Overrides, DEFAULT_MAKEFILE, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "rename",
+    about: "Renames a target and every reference to it, then regenerates the Makefile",
+    args: "<OLD> <NEW>",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Edits the Dofile at <PATH> (default: `Dofile`)"),
+        Flag::option(Some('o'), "output", "PATH", "Regenerates the Makefile at <PATH> (default: `Makefile`)"),
+        Flag::switch(None, "no-gen", "Only edits the Dofile"),
+    ],
+    notes: &[
+        "references inside the instructions (e.g. `$(MAKE) old`) are left untouched.",
+        "the Makefile is only regenerated if it was generated by domake.",
+    ],
+};
+
+/// Replaces `old` by `new` in a whitespace separated list of prerequisites.
+fn rename_all(prerequisites: &str, old: &str, new: &str) -> String {
+    prerequisites.split_whitespace()
+        .map(|prior| if prior == old { new } else { prior })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Renames the target `old` of the Dofile to `new` in its header, the prerequisite lists and the
+/// `default` line, with the number of references updated.
+fn rename_target(content: &str, dofile: &Dofile, old: &str, new: &str) -> (String, usize) {
+    let mut lines = content.lines().map(|line| line.to_string()).collect::<Vec<_>>();
+    let mut references = 0;
+    for cmd in &dofile.commands {
+        let header = cmd.line - 1;
+        let rest = lines[header].split_once(']').map(|(_, rest)| rest.to_string()).unwrap_or_default();
+        let name = if cmd.name == old { new } else { &cmd.name };
+        let name = if cmd.file { format!("file: {}", name) } else { name.to_string() };
+        references += cmd.prior_commands.iter().map(String::as_str).filter(|prior| *prior == old).count();
+
+        if rest.trim().is_empty() && !cmd.prior_commands.is_empty() {
+            // the prerequisites are on their own line
+            lines[header] = format!("[{}]", name);
+            lines[header + 1] = rename_all(&lines[header + 1], old, new);
+        } else if rest.trim().is_empty() {
+            lines[header] = format!("[{}]", name);
+        } else {
+            lines[header] = format!("[{}] {}", name, rename_all(&rest, old, new));
+        }
+    }
+    if let Some(goal) = dofile.default.as_ref().filter(|goal| goal.name == old) {
+        // the name is the first word after the keyword
+        let line = &lines[goal.line - 1];
+        let (keyword, rest) = line.split_at(line.find("default").unwrap_or_default() + "default".len());
+        lines[goal.line - 1] = format!("{}{}", keyword, rest.replacen(old, new, 1));
+        references += 1;
+    }
+    let mut content = lines.join("\n");
+    content.push('\n');
+    (content, references)
+}
+
+pub fn run(matches: &Matches) -> ! {
+    let [old, new] = matches.positionals.as_slice() else {
+        fail(Some(matches.command), "Expected the old and the new name of the target");
+    };
+    if !is_valid_name(new) {
+        fail(Some(matches.command), format!("Invalid target name `{}`", new).as_str());
+    }
+    let path = matches.value("file").unwrap_or(default_dofile());
+    expect_dofile_syntax(matches.command, path);
+    if path == STDIN_PATH {
+        fail(Some(matches.command), "Cannot edit a Dofile read from stdin");
+    }
+    let makefile = matches.value("output").unwrap_or(DEFAULT_MAKEFILE);
+
+    let content = load_dofile(path);
+    let dofile = parse(path, &content);
+    let commands = &dofile.commands;
+    if !commands.iter().any(|cmd| &cmd.name == old) {
+        abort(format!("No target named `{}` in `{}`", old, path).as_str(), 1);
+    }
+    if commands.iter().any(|cmd| &cmd.name == new) {
+        abort(format!("Target `{}` already exists in `{}`", new, path).as_str(), 1);
+    }
+
+    let (content, references) = rename_target(&content, &dofile, old, new);
+
+    // write next to the Dofile then swap, so that it is never left half renamed
+    let temporary = format!("{}.tmp", path);
+    if let Err(err) = fs::write(&temporary, &content).and_then(|_| fs::rename(&temporary, path)) {
+        let _ = fs::remove_file(&temporary);
+        abort(format!("Cannot write `{}`: {}", path, err).as_str(), 2);
+    }
+    println!("{} {}", style(format!("-> Target `{}` renamed to `{}`, {} reference(s) updated in", old, new, references)).bold().green(), path);
+
+    if !matches.flag("no-gen") {
+        match regenerate(&prepare(path, &content, &Overrides::default()), &Overrides::default(), makefile) {
+            Ok(_) => println!("{} {}", style("-> Makefile regenerated:").bold().green(), makefile),
+            Err(err) => println!("{} {}", style("Warning:").bold().yellow(), style(err).yellow()),
+        }
+    }
+    exit(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domake::parse_dofile;
+
+    #[test]
+    fn renaming_the_default_target_renames_the_default_line() {
+        let content = "default build\n\n[build] lib\n# Builds\necho build\n\n[lib]\n# Lib\necho lib\n";
+        let (content, references) = rename_target(content, &parse_dofile(content).unwrap(), "build", "all");
+        assert_eq!(references, 1);
+        let renamed = parse_dofile(&content).unwrap();
+        assert_eq!(renamed.default.as_ref().unwrap().name, "all");
+
+        let (content, references) = rename_target(&content, &renamed, "lib", "library");
+        assert_eq!(references, 1);
+        assert_eq!(content, "default all\n\n[all] library\n# Builds\necho build\n\n[library]\n# Lib\necho lib\n");
+    }
+}
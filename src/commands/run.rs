@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet};
+use std::{env, fs};
+use std::process::{exit, Command as Process};
+use console::style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::{Assignment, Command, Context, Dofile, Shell, Variable};
+use crate::{abort, dofile_paths, fail, is_installed, overrides, prepare_all};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "run",
+    about: "Runs targets directly from the Dofile, without a Makefile",
+    args: "[TARGET]...",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`); repeated, each one is laid over the ones before"),
+        Flag::option(Some('D'), "define", "KEY=VALUE", "Sets the variable <KEY> to <VALUE>, overriding the Dofile, can be repeated"),
+        Flag::option(None, "profile", "NAME", "Keeps the `%profile` sections of the profile <NAME> (e.g. `dev`, `ci`, `release`)"),
+        Flag::switch(None, "silent-all", "Silences every instruction, as if they all started with `@`"),
+        Flag::switch(Some('n'), "dry-run", "Prints the instructions without running them"),
+        Flag::switch(Some('v'), "verbose", "Traces on stderr the parsed constructs and the resolved includes, before the instructions run; given twice, also traces each decision of the parser (e.g. the lines a `%profile` leaves out)"),
+    ],
+    notes: &[
+        "without targets, runs the target of the `default` line, like make does.",
+        "prerequisites are run first, once each, like make does.",
+        "a `[file: path]` target is skipped when its file is newer than its prerequisites.",
+        "pattern rules like `[%.o] %.c` are left to make.",
+        "each instruction runs in its own shell (`sh`, or the one of the `shell` line), unless the target is oneshell; a leading `@` silences it and a leading `-` ignores its failure.",
+        "`$(NAME)` is replaced by the Dofile variable `NAME`, or else the environment variable, like make does.",
+        "instructions prefixed with `@linux:`, `@macos:` or `@windows:` only run on that operating system, and those prefixed with `@amd64:` or `@arm64:` on that architecture.",
+    ],
+};
+
+/// The value of a make variable.
+#[derive(Clone)]
+struct Value {
+    text: String,
+    /// Whether the variables of the text are already expanded, as with `:=`.
+    expanded: bool,
+    /// Whether the instructions get it in their environment, as with `export`.
+    exported: bool,
+}
+
+/// The variables of the Dofile, assigned like make does.
+#[derive(Clone)]
+struct Variables(HashMap<String, Value>);
+
+impl Variables {
+    fn new(dofile: &Dofile) -> Variables {
+        let mut variables = Variables(HashMap::new());
+        for variable in &dofile.variables {
+            variables.assign(variable);
+        }
+        variables
+    }
+
+    fn assign(&mut self, variable: &Variable) {
+        // make reads `\#` as a `#` that doesn't start a comment
+        let text = variable.value.replace("\\#", "#");
+        // once exported, a variable stays exported, like in make
+        let exported = variable.exported || self.0.get(&variable.name).is_some_and(|value| value.exported);
+        let value = match variable.assignment {
+            Assignment::Recursive => Value { text, expanded: false, exported },
+            Assignment::Simple => Value { text: self.expand(&text, 0), expanded: true, exported },
+            Assignment::Conditional if self.0.contains_key(&variable.name) || env::var_os(&variable.name).is_some() => {
+                if let Some(value) = self.0.get_mut(&variable.name) {
+                    value.exported = exported;
+                }
+                return;
+            }
+            Assignment::Conditional => Value { text, expanded: false, exported },
+            Assignment::Append => {
+                // like make, the environment is the initial value of any variable
+                let current = self.0.remove(&variable.name)
+                    .or_else(|| env::var(&variable.name).ok().map(|text| Value { text, expanded: false, exported }));
+                match current {
+                    Some(current) if current.expanded => Value { text: format!("{} {}", current.text, self.expand(&text, 0)), expanded: true, exported },
+                    Some(current) => Value { text: format!("{} {}", current.text, text), expanded: false, exported },
+                    None => Value { text, expanded: false, exported },
+                }
+            }
+        };
+        self.0.insert(variable.name.clone(), value);
+    }
+
+    /// The exported variables with their expanded value, for the environment of the instructions.
+    fn exported(&self) -> Vec<(String, String)> {
+        self.0.iter().filter(|(_, value)| value.exported)
+            .map(|(name, value)| (name.clone(), if value.expanded { value.text.clone() } else { self.expand(&value.text, 1) }))
+            .collect()
+    }
+
+    /// Expands the make variables of `text` like make does: Dofile variables first, then
+    /// environment variables, else nothing; `$$` stands for a literal `$`.
+    fn expand(&self, text: &str, depth: usize) -> String {
+        let mut buffer = String::new();
+        let mut rest = text;
+        while let Some(index) = rest.find('$') {
+            buffer.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+            let close = match rest.chars().next() {
+                Some('$') => {
+                    buffer.push('$');
+                    rest = &rest[1..];
+                    continue;
+                }
+                Some('(') => ')',
+                Some('{') => '}',
+                _ => {
+                    buffer.push('$');
+                    continue;
+                }
+            };
+            let reference = rest[1..].split_once(close).map(|(name, _)| name)
+                .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+            let Some(name) = reference else {
+                // make functions like `$(shell ...)` are left to the shell
+                buffer.push('$');
+                continue;
+            };
+            rest = &rest[name.len() + 2..];
+            match self.0.get(name) {
+                Some(value) if value.expanded => buffer.push_str(&value.text),
+                // the values may use other variables, within reason
+                Some(value) if depth < 16 => buffer.push_str(&self.expand(&value.text, depth + 1)),
+                Some(value) => buffer.push_str(&value.text),
+                None => buffer.push_str(&env::var(name).unwrap_or_default()),
+            }
+        }
+        buffer.push_str(rest);
+        buffer
+    }
+}
+
+struct Runner<'a> {
+    commands: &'a [Command],
+    /// The shell of the `shell` line, if any.
+    shell: Option<&'a Shell>,
+    variables: Variables,
+    dry_run: bool,
+    done: HashSet<String>,
+    /// The targets being resolved, to detect circular prerequisites.
+    stack: Vec<String>,
+}
+
+impl Runner<'_> {
+    fn run(&mut self, target: &str) {
+        if self.done.contains(target) {
+            return;
+        }
+        if self.stack.iter().any(|name| name == target) {
+            abort(format!("Circular dependency: {} -> {}", self.stack.join(" -> "), target).as_str(), 2);
+        }
+
+        let Some(cmd) = self.commands.iter().find(|cmd| cmd.name == target) else {
+            // like make, a prerequisite may be an existing file instead of a target
+            if !self.stack.is_empty() && fs::exists(target).unwrap_or(false) {
+                return;
+            }
+            abort(format!("No target named `{}` in the Dofile", target).as_str(), 2);
+        };
+
+        self.stack.push(target.to_string());
+        // like make, the `vars:` of the target also apply to its prerequisites
+        let outer = self.variables.clone();
+        for variable in &cmd.variables {
+            self.variables.assign(variable);
+        }
+        for prior in &cmd.prior_commands {
+            self.run(prior);
+        }
+        self.stack.pop();
+
+        if cmd.file && self.is_up_to_date(cmd) {
+            println!("{} {}", style("-> Up to date:").bold().green(), style(target).bold().cyan());
+        } else {
+            println!("{} {}", style("-> Running").bold().green(), style(target).bold().cyan());
+            if let Some(advice) = &cmd.deprecated {
+                let advice = if advice.is_empty() { String::new() } else { format!(", {}", advice) };
+                eprintln!("{} {}", style("Warning:").bold().yellow(), style(format!("target `{}` is deprecated{}", target, advice)).yellow());
+            }
+            if let Some(tool) = cmd.requires.iter().find(|tool| !self.dry_run && !is_installed(tool)) {
+                abort(format!("Target `{}` requires `{}`, which was not found in the PATH", target, tool).as_str(), 2);
+            }
+            if cmd.oneshell {
+                let (prefix, commands) = cmd.oneshell_script();
+                self.execute(target, &format!("{}{}", prefix, commands.join("\n")));
+            } else {
+                for instruction in &cmd.instructions {
+                    self.execute(target, instruction);
+                }
+            }
+        }
+        self.variables = outer;
+        self.done.insert(target.to_string());
+    }
+
+    /// Whether the file of a file target is newer than its prerequisites, like make decides;
+    /// a `.PHONY` prerequisite is always newer.
+    fn is_up_to_date(&self, cmd: &Command) -> bool {
+        let modified = |path: &str| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        let Some(built) = modified(&cmd.name) else {
+            return false;
+        };
+        cmd.prior_commands.iter().all(|prior| {
+            let phony = self.commands.iter().any(|other| &other.name == prior && !other.file);
+            !phony && modified(prior).is_some_and(|time| time <= built)
+        })
+    }
+
+    fn execute(&self, target: &str, instruction: &str) {
+        let mut line = instruction;
+        let mut silent = false;
+        let mut ignore_errors = false;
+        loop {
+            if let Some(rest) = line.strip_prefix('@') {
+                silent = true;
+                line = rest;
+            } else if let Some(rest) = line.strip_prefix('-') {
+                ignore_errors = true;
+                line = rest;
+            } else if let Some(rest) = line.strip_prefix('+') {
+                // `+` only matters to `make -n`
+                line = rest;
+            } else {
+                break;
+            }
+        }
+        let line = self.variables.expand(line, 0);
+
+        if !silent || self.dry_run {
+            println!("{}", line);
+        }
+        if self.dry_run {
+            return;
+        }
+
+        let (program, arguments) = match self.shell {
+            Some(shell) => (shell.name.as_str(), shell.arguments()),
+            None if cfg!(windows) => ("cmd", vec!["/C"]),
+            None => ("sh", vec!["-c"]),
+        };
+        let status = Process::new(program).args(arguments).arg(&line).envs(self.variables.exported()).status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) if ignore_errors => {
+                println!("{}", style(format!("-> `{}` failed ({}), ignored", line, status)).yellow());
+            }
+            Ok(status) => abort(format!("Target `{}` failed: `{}` exited with {}", target, line, status).as_str(), 2),
+            Err(err) => abort(format!("Target `{}` failed: cannot run `{}`: {}", target, line, err).as_str(), 2),
+        }
+    }
+}
+
+pub fn run(matches: &Matches) -> ! {
+    let dofile = prepare_all(&dofile_paths(matches), &overrides(matches));
+    let targets = match (&dofile.default, matches.positionals.is_empty()) {
+        (Some(goal), true) => vec![goal.name.clone()],
+        (None, true) => fail(Some(matches.command), "Missing target to run (or `default` line in the Dofile)"),
+        (_, false) => matches.positionals.clone(),
+    };
+    run_targets(&dofile, &targets, matches.flag("dry-run"))
+}
+
+/// Runs the targets and their prerequisites, exiting on the first failure.
+pub fn run_targets(dofile: &Dofile, targets: &[String], dry_run: bool) -> ! {
+    let mut dofile = dofile.clone();
+    let host = Context::default();
+    dofile.restrict_to(&host.os);
+    dofile.restrict_to(&host.arch);
+    let mut runner = Runner {
+        commands: &dofile.commands,
+        shell: dofile.shell.as_ref(),
+        variables: Variables::new(&dofile),
+        dry_run,
+        done: HashSet::new(),
+        stack: Vec::new(),
+    };
+    for target in targets {
+        runner.run(target);
+    }
+    exit(0)
+}
@@ -0,0 +1,65 @@
+use std::process::exit;
+use console::Style;
+use crate::cli::{Flag, Matches, Subcommand};
+use domake::json::Json;
+use crate::{default_dofile, fail, load_dofile, parse};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "targets",
+    about: "Lists the targets of the Dofile",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH>, `-` for stdin (default: `Dofile`)"),
+        Flag::switch(Some('a'), "all", "Also lists the hidden targets, whose name starts with `_`"),
+        Flag::switch(None, "json", "Prints the targets as a JSON array"),
+        Flag::switch(None, "names", "Prints only the target names, one per line (used by the shell completions)"),
+    ],
+    notes: &[],
+};
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let path = matches.value("file").unwrap_or(default_dofile());
+    let mut commands = parse(path, &load_dofile(path)).commands;
+    if !matches.flag("all") {
+        commands.retain(|cmd| !cmd.is_hidden());
+    }
+
+    if matches.flag("names") {
+        for cmd in &commands {
+            println!("{}", cmd.name);
+        }
+        exit(0);
+    }
+
+    if matches.flag("json") {
+        let targets = commands.iter().map(|cmd| Json::object([
+            ("name", cmd.name.as_str().into()),
+            ("description", cmd.summary().into()),
+            ("prerequisites", cmd.prior_commands.iter().map(String::as_str).collect::<Vec<_>>().into()),
+            ("group", cmd.group.as_deref().into()),
+            ("line", cmd.line.into()),
+        ])).collect::<Vec<_>>();
+        println!("{}", Json::Array(targets).pretty());
+        exit(0);
+    }
+
+    let title_style = Style::new().bold().green();
+    let name_style = Style::new().bold().cyan();
+    let prior_style = Style::new().yellow();
+
+    let prerequisites = commands.iter().map(|cmd| cmd.prior_commands.join(" ")).collect::<Vec<_>>();
+    let name_width = commands.iter().map(|cmd| cmd.name.len()).chain(["TARGET".len()]).max().unwrap() + 2;
+    let prior_width = prerequisites.iter().map(|prior| prior.len()).chain(["PREREQUISITES".len()]).max().unwrap() + 2;
+
+    println!("{}", title_style.apply_to(format!("{:name_width$}{:prior_width$}{}", "TARGET", "PREREQUISITES", "DESCRIPTION")));
+    for (cmd, prior) in commands.iter().zip(&prerequisites) {
+        println!("{}{}{}",
+                 name_style.apply_to(format!("{:name_width$}", cmd.name)),
+                 prior_style.apply_to(format!("{:prior_width$}", prior)),
+                 cmd.summary());
+    }
+    exit(0)
+}
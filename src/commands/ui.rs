@@ -0,0 +1,146 @@
+use std::io;
+use std::process::exit;
+use console::{style, Key, Term};
+use crate::cli::{Flag, Matches, Subcommand};
+use crate::commands::{make, run as runner};
+use domake::Command;
+use crate::{abort, default_dofile, fail, load_dofile, prepare, Overrides, DEFAULT_MAKEFILE, STDIN_PATH};
+
+pub const SPEC: Subcommand = Subcommand {
+    name: "ui",
+    about: "Picks a target interactively and runs it",
+    args: "",
+    flags: &[
+        Flag::option(Some('f'), "file", "PATH", "Reads the Dofile at <PATH> (default: `Dofile`)"),
+        Flag::option(Some('o'), "output", "PATH", "Uses the Makefile at <PATH> (default: `Makefile`)"),
+        Flag::switch(None, "builtin", "Runs the target with `domake run` instead of make"),
+    ],
+    notes: &[
+        "type to filter the targets, use the arrows to move, Enter to run, Esc to quit.",
+    ],
+};
+
+/// The number of targets shown at once.
+const MAX_VISIBLE: usize = 15;
+
+/// Scores how well `query` fuzzily matches `text`: its characters must appear
+/// in order, and matches that are early or consecutive score lower (better).
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase();
+    let mut chars = text.char_indices();
+    let mut score = 0;
+    let mut last: Option<usize> = None;
+    for wanted in query.to_lowercase().chars() {
+        let (index, _) = chars.by_ref().find(|(_, c)| *c == wanted)?;
+        score += match last {
+            Some(last) => index - last - 1,
+            None => index,
+        };
+        last = Some(index);
+    }
+    Some(score)
+}
+
+/// The targets matching the query, best first; the name matters more than the description.
+fn filter<'a>(commands: &'a [Command], query: &str) -> Vec<&'a Command> {
+    let mut matching = commands.iter().filter_map(|cmd| {
+        let score = fuzzy_score(query, &cmd.name)
+            .or_else(|| fuzzy_score(query, cmd.summary()).map(|score| score + 1000))?;
+        Some((score, cmd))
+    }).collect::<Vec<_>>();
+    matching.sort_by_key(|(score, _)| *score);
+    matching.into_iter().map(|(_, cmd)| cmd).collect()
+}
+
+/// Runs the picker until a target is chosen (`Some`) or the user quits (`None`).
+fn pick<'a>(term: &Term, commands: &'a [Command]) -> io::Result<Option<&'a Command>> {
+    let mut query = String::new();
+    let mut selected = 0;
+    let mut offset = 0;
+    let height = MAX_VISIBLE.min(term.size().0.saturating_sub(3).max(1) as usize);
+    let width = commands.iter().map(|cmd| cmd.name.len()).max().unwrap_or(0) + 2;
+
+    loop {
+        let matching = filter(commands, &query);
+        selected = selected.min(matching.len().saturating_sub(1));
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+
+        let mut lines = vec![format!("{} {}", style("Filter:").bold().green(), query)];
+        for (index, cmd) in matching.iter().enumerate().skip(offset).take(height) {
+            let name = format!("{:width$}", cmd.name);
+            lines.push(if index == selected {
+                format!("{} {}{}", style(">").bold().green(), style(name).bold().cyan().reverse(), cmd.summary())
+            } else {
+                format!("  {}{}", style(name).cyan(), style(cmd.summary()).dim())
+            });
+        }
+        if matching.is_empty() {
+            lines.push(style("  (no matching target)").dim().to_string());
+        }
+        lines.push(style(format!("  {}/{} targets", matching.len(), commands.len())).dim().to_string());
+        for line in &lines {
+            term.write_line(line)?;
+        }
+
+        let key = term.read_key()?;
+        term.clear_last_lines(lines.len())?;
+        match key {
+            Key::Escape | Key::CtrlC => return Ok(None),
+            Key::Enter => return Ok(matching.get(selected).copied()),
+            Key::ArrowUp => selected = selected.saturating_sub(1),
+            Key::ArrowDown => selected += 1,
+            Key::PageUp => selected = selected.saturating_sub(height),
+            Key::PageDown => selected += height,
+            Key::Home => selected = 0,
+            Key::End => selected = matching.len(),
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn run(matches: &Matches) -> ! {
+    if !matches.positionals.is_empty() {
+        fail(Some(matches.command), "Wrong argument");
+    }
+    let dofile = matches.value("file").unwrap_or(default_dofile());
+    if dofile == STDIN_PATH {
+        fail(Some(matches.command), "Cannot read the Dofile from stdin in the interactive picker");
+    }
+    let makefile = matches.value("output").unwrap_or(DEFAULT_MAKEFILE);
+
+    let term = Term::stdout();
+    if !term.is_term() {
+        abort("`domake ui` needs an interactive terminal", 1);
+    }
+    let parsed = prepare(dofile, &load_dofile(dofile), &Overrides::default());
+    let commands = parsed.commands.iter().filter(|cmd| !cmd.is_hidden()).cloned().collect::<Vec<_>>();
+    if commands.is_empty() {
+        abort("No target found in the Dofile", 1);
+    }
+
+    let _ = term.hide_cursor();
+    let picked = pick(&term, &commands);
+    let _ = term.show_cursor();
+
+    let target = match picked {
+        Ok(Some(cmd)) => cmd.name.clone(),
+        Ok(None) => exit(0),
+        Err(err) => abort(format!("Cannot read from the terminal: {}", err).as_str(), 1),
+    };
+    if matches.flag("builtin") {
+        runner::run_targets(&parsed, &[target], false)
+    }
+    make::make(&[dofile.to_string()], makefile, &Overrides::default(), &[target])
+}
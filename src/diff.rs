@@ -0,0 +1,97 @@
+use console::style;
+
+/// A line of a unified diff.
+#[derive(Clone, Copy)]
+pub enum Line<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A group of changes with their surrounding context, `@@ -old +new @@`.
+pub struct Hunk<'a> {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<Line<'a>>,
+}
+
+/// Computes the hunks turning `old` into `new`, keeping `context` unchanged lines
+/// around each change. Lines are compared with `same`, so that volatile lines
+/// (e.g. a generation date) can be ignored.
+pub fn hunks<'a>(old: &[&'a str], new: &[&'a str], context: usize, same: impl Fn(&str, &str) -> bool) -> Vec<Hunk<'a>> {
+    // longest common subsequence table, lcs[i][j] for old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if same(old[i], new[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // the full edit script, with the position of each line in both files
+    let mut script: Vec<(Line<'a>, usize, usize)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && same(old[i], new[j]) {
+            script.push((Line::Context(new[j]), i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            script.push((Line::Removed(old[i]), i, j));
+            i += 1;
+        } else {
+            script.push((Line::Added(new[j]), i, j));
+            j += 1;
+        }
+    }
+
+    // group the changes that are close enough to share their context
+    let changes: Vec<usize> = script.iter().enumerate()
+        .filter(|(_, (line, _, _))| !matches!(line, Line::Context(_)))
+        .map(|(index, _)| index)
+        .collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for index in changes {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(script.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges.into_iter().map(|(start, end)| {
+        let (_, old_start, new_start) = script[start];
+        let mut hunk = Hunk { old_start: old_start + 1, old_len: 0, new_start: new_start + 1, new_len: 0, lines: Vec::new() };
+        for &(line, _, _) in &script[start..end] {
+            match line {
+                Line::Context(_) => { hunk.old_len += 1; hunk.new_len += 1; }
+                Line::Removed(_) => hunk.old_len += 1,
+                Line::Added(_) => hunk.new_len += 1,
+            }
+            hunk.lines.push(line);
+        }
+        hunk
+    }).collect()
+}
+
+/// Prints the hunks as a colored unified diff.
+pub fn print(old_name: &str, new_name: &str, hunks: &[Hunk]) {
+    println!("{}", style(format!("--- {}", old_name)).bold().red());
+    println!("{}", style(format!("+++ {}", new_name)).bold().green());
+    for hunk in hunks {
+        println!("{}", style(format!("@@ -{},{} +{},{} @@", hunk.old_start, hunk.old_len, hunk.new_start, hunk.new_len)).cyan());
+        for line in &hunk.lines {
+            match line {
+                Line::Context(line) => println!(" {}", line),
+                Line::Removed(line) => println!("{}", style(format!("-{}", line)).red()),
+                Line::Added(line) => println!("{}", style(format!("+{}", line)).green()),
+            }
+        }
+    }
+}
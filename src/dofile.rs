@@ -0,0 +1,1994 @@
+use std::{env, fmt, fs, io};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use log::{debug, trace};
+use crate::json::Json;
+use crate::makefile::rule_targets;
+use crate::validate;
+
+/// A parsed Dofile.
+///
+/// With the `serde` feature, it is serialized as `domake export --format json` writes it, and
+/// deserialized as `domake gen --from-json` reads it, with the same fields left optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct Dofile {
+    /// The files included by the Dofile, in order.
+    pub includes: Vec<Include>,
+    /// The `.env` files loaded by the Dofile, in order.
+    pub dotenvs: Vec<Include>,
+    /// The Dofiles whose targets are merged into this one, in order.
+    pub uses: Vec<Use>,
+    /// The blocks of literal Makefile syntax, in order.
+    pub raws: Vec<Raw>,
+    /// The target plain `make` runs, from the `default` line.
+    pub default: Option<Goal>,
+    /// The shell running the instructions, from the `shell` line, instead of `sh`.
+    pub shell: Option<Shell>,
+    /// Whether every target runs its instructions in a single shell, from the `oneshell` line.
+    pub oneshell: bool,
+    /// Whether `make -j` runs the targets one at a time anyway, from the `notparallel` line.
+    pub notparallel: bool,
+    /// The variables of the Dofile, in order.
+    pub variables: Vec<Variable>,
+    /// The targets of the Dofile, in order.
+    pub commands: Vec<Command>,
+}
+
+impl Dofile {
+    /// Sets the variable `name` to `value` like a variable given on make's command line:
+    /// it replaces every assignment of the variable in the Dofile, or comes after them if there is none.
+    pub fn define(&mut self, name: &str, value: &str) {
+        let first = self.variables.iter().position(|variable| variable.name == name);
+        let exported = self.variables.iter().any(|variable| variable.name == name && variable.exported);
+        self.variables.retain(|variable| variable.name != name);
+        let variable = Variable {
+            line: 0,
+            span: 0..0,
+            name: name.to_string(),
+            assignment: Assignment::Recursive,
+            value: value.to_string(),
+            exported,
+        };
+        match first {
+            Some(index) => self.variables.insert(index, variable),
+            None => self.variables.push(variable),
+        }
+    }
+
+    /// Keeps the instructions restricted to `platform`, an operating system or an architecture,
+    /// without their prefix for it, and leaves out those restricted to another one of its kind.
+    pub fn restrict_to(&mut self, platform: &str) {
+        let same_kind = |name: &str| OPERATING_SYSTEMS.contains(&name) == OPERATING_SYSTEMS.contains(&platform);
+        for cmd in &mut self.commands {
+            cmd.instructions = cmd.instructions.iter().filter_map(|instruction| {
+                let (names, rest) = restrictions(instruction);
+                if names.iter().any(|name| same_kind(name) && *name != platform) {
+                    debug!("target `{}`: instruction `{}` left out for `{}`", cmd.name, rest, platform);
+                    return None;
+                }
+                let prefixes = names.iter().filter(|name| !same_kind(name)).map(|name| format!("@{}: ", name)).collect::<String>();
+                Some(format!("{}{}", prefixes, rest))
+            }).collect();
+        }
+    }
+
+    /// Silences every instruction, as if they all started with `@`.
+    pub fn silence_all(&mut self) {
+        for instruction in self.commands.iter_mut().flat_map(|cmd| &mut cmd.instructions) {
+            let (names, rest) = restrictions(instruction);
+            // make reads any mix of `@`, `-` and `+` at the start of an instruction
+            if !rest.chars().take_while(|c| matches!(c, '@' | '-' | '+')).any(|c| c == '@') {
+                let prefixes = names.iter().map(|name| format!("@{}: ", name)).collect::<String>();
+                *instruction = format!("{}@{}", prefixes, rest);
+            }
+        }
+    }
+
+    /// Orders the targets by name, instead of as they are written.
+    pub fn sort_by_name(&mut self) {
+        self.commands.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// Orders the targets so that each one comes after its prerequisites, keeping the written
+    /// order otherwise.
+    pub fn sort_by_dependencies(&mut self) {
+        fn visit(index: usize, commands: &[Command], visited: &mut Vec<bool>, order: &mut Vec<usize>) {
+            if visited[index] {
+                return;
+            }
+            visited[index] = true;
+            for prior in &commands[index].prior_commands {
+                if let Some(prior) = commands.iter().position(|cmd| &cmd.name == prior) {
+                    visit(prior, commands, visited, order);
+                }
+            }
+            order.push(index);
+        }
+
+        let mut visited = vec![false; self.commands.len()];
+        let mut order = Vec::new();
+        for index in 0..self.commands.len() {
+            visit(index, &self.commands, &mut visited, &mut order);
+        }
+        let mut commands = std::mem::take(&mut self.commands).into_iter().map(Some).collect::<Vec<_>>();
+        self.commands = order.into_iter().filter_map(|index| commands[index].take()).collect();
+    }
+
+    /// Replaces each include with a glob pattern (e.g. `mk/*.mk`) by an include of every file
+    /// it matches, in order, instead of leaving the pattern to make.
+    pub fn expand_includes(&mut self) {
+        self.includes = self.includes.iter().flat_map(|include| {
+            if !is_glob(&include.path) {
+                return vec![include.clone()];
+            }
+            let paths = glob(&include.path);
+            debug!("include `{}` matches {}", include.path, if paths.is_empty() { "no file".to_string() } else { format!("`{}`", paths.join("`, `")) });
+            paths.into_iter().map(|path| Include { path, ..include.clone() }).collect()
+        }).collect();
+    }
+
+    /// Reads the Dofiles of the `use` lines for `context` and merges their targets under their
+    /// namespace: with `use frontend/Dofile as fe`, its `build` target becomes `fe/build`, and
+    /// runs in `frontend`. Its variables, includes and `.env` files are merged too, the variables
+    /// of this Dofile winning.
+    pub fn load_uses(&mut self, context: &Context) -> Result<(), String> {
+        self.load_uses_from(context, &mut Vec::new())
+    }
+
+    /// Like [`Dofile::load_uses`], `stack` holding the Dofiles being loaded, to detect circular uses.
+    fn load_uses_from(&mut self, context: &Context, stack: &mut Vec<PathBuf>) -> Result<(), String> {
+        for import in self.uses.clone() {
+            let content = fs::read_to_string(&import.path)
+                .map_err(|err| format!("Cannot read `{}`: {}", import.path, err))?;
+            let canonical = fs::canonicalize(&import.path).unwrap_or_else(|_| PathBuf::from(&import.path));
+            if stack.contains(&canonical) {
+                return Err(format!("`{}` is used by a Dofile it uses", import.path));
+            }
+            let directory = Path::new(&import.path).parent().and_then(Path::to_str).unwrap_or_default().trim_start_matches("./");
+
+            let (mut imported, errors) = parse_dofile_partial_with(&content, context);
+            // its prerequisites that are files are next to it, not here
+            let error = errors.into_iter().find(|err| match &err.kind {
+                ErrorKind::UndefinedPrerequisite(prior, _) => !fs::exists(relocate(directory, prior)).unwrap_or(false),
+                _ => true,
+            });
+            if let Some(err) = error {
+                return Err(format!("{}:{}:{}: {}", import.path, err.line, err.column, err));
+            }
+            for nested in &mut imported.uses {
+                nested.path = relocate(directory, &nested.path);
+            }
+            stack.push(canonical);
+            imported.load_uses_from(context, stack)?;
+            stack.pop();
+            debug!("use `{}`: {} target(s) merged under `{}/`", import.path, imported.commands.len(), import.namespace);
+            self.merge(imported, &import.namespace, directory);
+        }
+        Ok(())
+    }
+
+    /// Adds the targets of `imported`, a Dofile in `directory`, under `namespace`.
+    fn merge(&mut self, imported: Dofile, namespace: &str, directory: &str) {
+        let phony = imported.commands.iter().filter(|cmd| !cmd.file && !cmd.is_pattern()).map(|cmd| cmd.name.clone()).collect::<Vec<_>>();
+        // a target runs in its directory; file targets and patterns are named by their path from here
+        let rename = |name: &str| if phony.iter().any(|phony| phony == name) { format!("{}/{}", namespace, name) } else { relocate(directory, name) };
+        for mut cmd in imported.commands {
+            cmd.name = rename(&cmd.name);
+            cmd.prior_commands = cmd.prior_commands.iter().map(|prior| rename(prior)).collect();
+            cmd.group = cmd.group.or_else(|| Some(namespace.to_string()));
+            // a oneshell target only changes directory before its first command
+            let first = cmd.instructions.iter().position(|instruction| !is_comment(instruction));
+            for (index, instruction) in cmd.instructions.iter_mut().enumerate() {
+                if !directory.is_empty() && !is_comment(instruction) && (!cmd.oneshell || Some(index) == first) {
+                    *instruction = in_directory(instruction, directory);
+                }
+            }
+            self.commands.push(cmd);
+        }
+        for variable in imported.variables {
+            if !self.variables.iter().any(|own| own.name == variable.name) {
+                self.variables.push(variable);
+            }
+        }
+        for mut include in imported.includes {
+            include.path = relocate(directory, &include.path);
+            self.includes.push(include);
+        }
+        for mut dotenv in imported.dotenvs {
+            dotenv.path = relocate(directory, &dotenv.path);
+            self.dotenvs.push(dotenv);
+        }
+        self.raws.extend(imported.raws);
+    }
+
+    /// Lays `layer` over the Dofile, as with `domake -f Dofile -f Dofile.ci`: its targets replace
+    /// those of the same name, in place, and its `default` and `shell` lines win. Its variables come
+    /// after the Dofile's, so that make gives them precedence. Returns the names of the replaced targets.
+    pub fn layer(&mut self, layer: Dofile) -> Vec<String> {
+        let mut replaced = Vec::new();
+        for cmd in layer.commands {
+            match self.commands.iter_mut().find(|own| own.name == cmd.name) {
+                Some(own) => {
+                    replaced.push(cmd.name.clone());
+                    *own = cmd;
+                }
+                None => self.commands.push(cmd),
+            }
+        }
+        self.includes.extend(layer.includes);
+        self.dotenvs.extend(layer.dotenvs);
+        self.uses.extend(layer.uses);
+        self.raws.extend(layer.raws);
+        self.variables.extend(layer.variables);
+        self.default = layer.default.or(self.default.take());
+        self.shell = layer.shell.or(self.shell.take());
+        self.oneshell |= layer.oneshell;
+        self.notparallel |= layer.notparallel;
+        replaced
+    }
+
+    /// Reads the `.env` files of the Dofile and prepends their variables to the Dofile's, as `?=`
+    /// assignments so that the environment still wins, as usual with `.env` files.
+    pub fn load_dotenvs(&mut self) -> Result<(), String> {
+        let mut variables = Vec::new();
+        for dotenv in &self.dotenvs {
+            let content = match fs::read_to_string(&dotenv.path) {
+                Ok(content) => content,
+                Err(err) if dotenv.optional && err.kind() == io::ErrorKind::NotFound => {
+                    debug!("dotenv `{}` is missing, left out", dotenv.path);
+                    continue;
+                }
+                Err(err) => return Err(format!("Cannot read `{}`: {}", dotenv.path, err)),
+            };
+            for (name, value) in dotenv_variables(&content).map_err(|err| format!("{}:{}", dotenv.path, err))? {
+                debug!("dotenv `{}`: variable `{}`", dotenv.path, name);
+                variables.push(Variable {
+                    line: dotenv.line,
+                    span: dotenv.span.clone(),
+                    name,
+                    assignment: Assignment::Conditional,
+                    value,
+                    exported: false,
+                });
+            }
+        }
+        variables.append(&mut self.variables);
+        self.variables = variables;
+        Ok(())
+    }
+}
+
+/// The path `path`, relative to `directory`, relative to the current directory instead.
+fn relocate(directory: &str, path: &str) -> String {
+    if directory.is_empty() || Path::new(path).is_absolute() {
+        path.to_string()
+    } else {
+        format!("{}/{}", directory, path)
+    }
+}
+
+/// Whether the instruction is a `#` comment.
+fn is_comment(instruction: &str) -> bool {
+    restrictions(instruction).1.trim_start_matches(['@', '-', '+']).starts_with('#')
+}
+
+/// The instruction run from `directory`, keeping its prefixes first.
+fn in_directory(instruction: &str, directory: &str) -> String {
+    let (names, rest) = restrictions(instruction);
+    let command = rest.trim_start_matches(['@', '-', '+']);
+    let platforms = names.iter().map(|name| format!("@{}: ", name)).collect::<String>();
+    let directory = if directory.contains(char::is_whitespace) { format!("'{}'", directory) } else { directory.to_string() };
+    format!("{}{}cd {} && {}", platforms, &rest[..rest.len() - command.len()], directory, command)
+}
+
+/// Whether `path` is a glob pattern, with a `*` or a `?`.
+pub(crate) fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// The files matching the glob pattern `pattern`, sorted; `*` and `?` don't match a `/`,
+/// nor the `.` starting the name of a hidden file.
+fn glob(pattern: &str) -> Vec<String> {
+    // the paths matching the parts of the pattern so far, `None` standing for the current directory
+    let mut paths = vec![None];
+    for part in pattern.split('/') {
+        let join = |path: &Option<String>, name: &str| match path {
+            Some(path) => format!("{}/{}", path, name),
+            None => name.to_string(),
+        };
+        paths = paths.iter().flat_map(|path| {
+            if !is_glob(part) {
+                return vec![Some(join(path, part))];
+            }
+            let directory = match path.as_deref() {
+                Some("") => "/",
+                Some(directory) => directory,
+                None => ".",
+            };
+            fs::read_dir(directory).into_iter().flatten().flatten()
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| wildcard_match(part, name) && (part.starts_with('.') || !name.starts_with('.')))
+                .map(|name| Some(join(path, &name)))
+                .collect()
+        }).collect();
+    }
+    let mut paths = paths.into_iter().flatten().filter(|path| fs::exists(path).unwrap_or(false)).collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any text and `?` for any character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.chars().collect::<Vec<_>>(), name.chars().collect::<Vec<_>>());
+    // the positions of `name` that the start of `pattern` can reach
+    let mut reached = vec![false; name.len() + 1];
+    reached[0] = true;
+    for c in pattern {
+        reached = match c {
+            '*' => {
+                let first = reached.iter().position(|reached| *reached).unwrap_or(name.len() + 1);
+                (0..=name.len()).map(|index| index >= first).collect()
+            }
+            c => (0..=name.len()).map(|index| index > 0 && reached[index - 1] && (c == '?' || name[index - 1] == c)).collect(),
+        };
+    }
+    reached[name.len()]
+}
+
+/// The `KEY=value` lines of a `.env` file, with their value unquoted and its `$` and `#` escaped for make.
+fn dotenv_variables(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut variables = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=').filter(|(name, _)| is_variable_name(name.trim())) else {
+            return Err(format!("{}: expected a `KEY=value` line", index + 1));
+        };
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => match value[1..].find(quote) {
+                Some(end) => value[1..end + 1].to_string(),
+                None => return Err(format!("{}: missing closing {} in the value of `{}`", index + 1, quote, name.trim())),
+            },
+            // an unquoted value ends at its comment
+            _ => value.split(" #").next().unwrap_or_default().trim_end().to_string(),
+        };
+        variables.push((name.trim().to_string(), value.replace('$', "$$").replace('#', "\\#")));
+    }
+    Ok(variables)
+}
+
+/// What the Dofile is parsed for, deciding which of its `%` sections are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Context {
+    /// The selected profile, whose `%profile` sections are kept; the others are left out.
+    pub profile: Option<String>,
+    /// The operating system the Makefile is for, as named by [`std::env::consts::OS`].
+    pub os: String,
+    /// The processor architecture the Makefile is for, as named in [`ARCHITECTURES`].
+    pub arch: String,
+}
+
+impl Default for Context {
+    /// No profile, for the current operating system and architecture.
+    fn default() -> Context {
+        let arch = match env::consts::ARCH {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            arch => arch,
+        };
+        Context { profile: None, os: env::consts::OS.to_string(), arch: arch.to_string() }
+    }
+}
+
+impl Context {
+    /// Whether the condition of an `%if` line holds: `profile NAME...`, `os NAME...`, `arch NAME...`,
+    /// `env NAME` (set and not empty) or `env NAME=VALUE`, negated by a leading `!`.
+    /// `None` if the condition cannot be read.
+    pub fn holds(&self, condition: &str) -> Option<bool> {
+        let (negated, condition) = match condition.trim().strip_prefix('!') {
+            Some(condition) => (true, condition.trim()),
+            None => (false, condition.trim()),
+        };
+        let (kind, arguments) = condition.split_once(char::is_whitespace)?;
+        let mut names = arguments.split_whitespace();
+        let holds = match kind {
+            "profile" => names.any(|name| self.profile.as_deref() == Some(name)),
+            "os" => names.any(|name| name == self.os),
+            "arch" => names.any(|name| name == self.arch),
+            "env" => match (names.next()?, names.next()) {
+                (variable, None) => match variable.split_once('=') {
+                    Some((name, value)) => env::var(name).is_ok_and(|current| current == value),
+                    None => env::var(variable).is_ok_and(|current| !current.is_empty()),
+                },
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(holds != negated)
+    }
+}
+
+/// An `include` line of the Dofile, copied as is to the Makefile, or a `dotenv` line;
+/// written `include?` or `dotenv?` when the file may be missing.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Include {
+    /// The line of the `include` or `dotenv` in the Dofile, starting at 1.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: usize,
+    /// The bytes of the path in the Dofile.
+    #[cfg_attr(feature = "serde", serde(default, with = "span_pair"))]
+    pub span: Range<usize>,
+    pub path: String,
+    /// Whether a missing file is left out instead of being an error.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub optional: bool,
+}
+
+/// A `use path/to/Dofile as name` line of the Dofile, merging the targets of another Dofile
+/// under the namespace `name`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Use {
+    /// The line of the `use` in the Dofile, starting at 1.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: usize,
+    /// The bytes of the path in the Dofile.
+    #[cfg_attr(feature = "serde", serde(default, with = "span_pair"))]
+    pub span: Range<usize>,
+    pub path: String,
+    /// The prefix of the merged targets, before a `/`.
+    pub namespace: String,
+}
+
+/// A `default name` line of the Dofile, making the target `name` the one plain `make` runs.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Goal {
+    /// The line of the `default` in the Dofile, starting at 1.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: usize,
+    /// The bytes of the target name in the Dofile.
+    #[cfg_attr(feature = "serde", serde(default, with = "span_pair"))]
+    pub span: Range<usize>,
+    pub name: String,
+}
+
+/// A `shell bash` line of the Dofile, choosing the shell that runs the instructions,
+/// optionally followed by its flags (e.g. `shell bash -e`).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shell {
+    /// The line of the `shell` in the Dofile, starting at 1.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: usize,
+    /// The bytes of the shell name in the Dofile.
+    #[cfg_attr(feature = "serde", serde(default, with = "span_pair"))]
+    pub span: Range<usize>,
+    /// The name of the shell, or its path.
+    pub name: String,
+    /// The flags written after the name, if any.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flags: Vec<String>,
+}
+
+impl Shell {
+    /// The arguments given before each instruction: the written flags, or else the strict
+    /// mode of bash and zsh (stopping on any failure of a pipe), ending with `-c`.
+    pub fn arguments(&self) -> Vec<&str> {
+        let mut arguments = self.flags.iter().map(String::as_str).collect::<Vec<_>>();
+        let program = self.name.rsplit('/').next().unwrap_or_default();
+        if arguments.is_empty() && matches!(program, "bash" | "zsh") {
+            arguments.extend(["-eu", "-o", "pipefail"]);
+        }
+        if arguments.last() != Some(&"-c") {
+            arguments.push("-c");
+        }
+        arguments
+    }
+
+    /// The Makefile lines setting the shell, found through the PATH unless it is a path.
+    pub fn to_makefile(&self) -> String {
+        let program = if self.name.contains('/') { self.name.clone() } else { format!("/usr/bin/env {}", self.name) };
+        format!("SHELL := {}\n.SHELLFLAGS := {}\n", program, self.arguments().join(" "))
+    }
+}
+
+/// A block of literal Makefile syntax between a ```` ```make ```` (or ```` ```raw ````) line
+/// and a ```` ``` ```` line, copied as is to the Makefile.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Raw {
+    /// The line of the opening ```` ```make ```` in the Dofile, starting at 1.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: usize,
+    /// The bytes of the opening ```` ```make ```` in the Dofile.
+    #[cfg_attr(feature = "serde", serde(default, with = "span_pair"))]
+    pub span: Range<usize>,
+    /// The lines between the fences.
+    pub text: String,
+}
+
+impl Raw {
+    /// The targets of the rules of the block, leaving out the special and pattern ones.
+    pub fn targets(&self) -> Vec<&str> {
+        rule_targets(&self.text)
+    }
+}
+
+/// How a variable is assigned, as in make.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Assignment {
+    /// `=`: the value is expanded every time the variable is used.
+    #[cfg_attr(feature = "serde", serde(rename = "="))]
+    Recursive,
+    /// `:=`: the value is expanded once, where the variable is assigned.
+    #[cfg_attr(feature = "serde", serde(rename = ":="))]
+    Simple,
+    /// `?=`: the variable is only assigned if it isn't defined yet.
+    #[cfg_attr(feature = "serde", serde(rename = "?="))]
+    Conditional,
+    /// `+=`: the value is appended to the current one, after a space.
+    #[cfg_attr(feature = "serde", serde(rename = "+="))]
+    Append,
+}
+
+impl Assignment {
+    pub const ALL: [Assignment; 4] = [Assignment::Recursive, Assignment::Simple, Assignment::Conditional, Assignment::Append];
+
+    pub fn operator(&self) -> &'static str {
+        match self {
+            Assignment::Recursive => "=",
+            Assignment::Simple => ":=",
+            Assignment::Conditional => "?=",
+            Assignment::Append => "+=",
+        }
+    }
+}
+
+/// A `NAME = value` line of the Dofile, copied to the variables of the Makefile
+/// so that instructions can use it as `$(NAME)`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variable {
+    /// The line of the variable in the Dofile, starting at 1, or 0 if it was defined
+    /// with [`Dofile::define`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: usize,
+    /// The bytes of the name in the Dofile.
+    #[cfg_attr(feature = "serde", serde(default, with = "span_pair"))]
+    pub span: Range<usize>,
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "operator"))]
+    pub assignment: Assignment,
+    pub value: String,
+    /// Whether the instructions get it in their environment, from an `export NAME = value` line.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exported: bool,
+}
+
+impl Variable {
+    /// The Makefile assignment of the variable.
+    pub fn to_makefile(&self) -> String {
+        let export = if self.exported { "export " } else { "" };
+        format!("{}{} {} {}", export, self.name, self.assignment.operator(), self.value).trim_end().to_string()
+    }
+}
+
+/// A target of the Dofile.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Command {
+    /// The line of the `[name]` header in the Dofile, starting at 1.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: usize,
+    /// The bytes of the `[name]` in the Dofile.
+    #[cfg_attr(feature = "serde", serde(default, with = "span_pair"))]
+    pub span: Range<usize>,
+    pub name: String,
+    /// Whether it makes the file named after it, from a `[file: path]` header, instead of being `.PHONY`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file: bool,
+    /// The text of the `#` description lines, without their `#`, one per line.
+    pub description: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub prior_commands: Vec<String>,
+    /// The tools of its `requires:` line, that must be on the PATH to run it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub requires: Vec<String>,
+    /// The section of the Makefile and of `make help` it is listed in, from its `#@ group:` line.
+    pub group: Option<String>,
+    /// What to use instead, from its `deprecated:` line, if the target is deprecated.
+    pub deprecated: Option<String>,
+    /// The variables of its `vars:` lines, set only while it runs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub variables: Vec<Variable>,
+    /// Whether its instructions run in a single shell, sharing `cd` and variables, from its
+    /// `#@ oneshell` line or the `oneshell` line of the Dofile.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub oneshell: bool,
+    /// Whether its prerequisites are made one at a time, even with `make -j`, from its `#@ notparallel` line.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub notparallel: bool,
+    /// The instructions, one per line, except for the lines carried on by a final `\\`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub instructions: Vec<String>,
+}
+
+/// Whether the shell command `command` goes on after its end, like `if ...; then` or `a &&`,
+/// so that a `;` cannot follow it.
+fn continues(command: &str) -> bool {
+    let command = command.trim_end();
+    ["then", "do", "else", "{", "(", "&&", "||", "|", ";", "\\"].iter().any(|end| command.ends_with(end))
+}
+
+/// The operating systems an instruction can be restricted to, with an `@os:` prefix.
+pub const OPERATING_SYSTEMS: &[&str] = &["linux", "macos", "windows"];
+
+/// The processor architectures an instruction can be restricted to, with an `@arch:` prefix.
+pub const ARCHITECTURES: &[&str] = &["amd64", "arm64"];
+
+/// The platforms `instruction` is restricted to with its `@os:` and `@arch:` prefixes,
+/// and the instruction without them.
+pub(crate) fn restrictions(instruction: &str) -> (Vec<&str>, &str) {
+    let mut names = Vec::new();
+    let mut rest = instruction;
+    while let Some((name, after)) = rest.strip_prefix('@').and_then(|rest| rest.split_once(':')) {
+        if !OPERATING_SYSTEMS.contains(&name) && !ARCHITECTURES.contains(&name) {
+            break;
+        }
+        names.push(name);
+        rest = after.trim_start();
+    }
+    (names, rest)
+}
+
+/// The Makefile variable set to the current platform of the kind of `name`.
+pub(crate) fn platform_variable(name: &str) -> &'static str {
+    if OPERATING_SYSTEMS.contains(&name) { "DOMAKE_OS" } else { "DOMAKE_ARCH" }
+}
+
+impl Command {
+    /// The first line of the description, as shown by `make help`.
+    pub fn summary(&self) -> &str {
+        self.description.lines().next().unwrap_or_default()
+    }
+
+    /// Whether the target is left out of `make help`, as its name (after any namespace)
+    /// starts with `_`, or it is a pattern rule.
+    pub fn is_hidden(&self) -> bool {
+        self.name.rsplit('/').next().is_some_and(|name| name.starts_with('_')) || self.is_pattern()
+    }
+
+    /// Whether it is a pattern rule like `[%.o] %.c`, making any file its `%` matches.
+    pub fn is_pattern(&self) -> bool {
+        self.name.contains('%')
+    }
+
+    /// The instructions of a oneshell target as the commands of a single script, starting
+    /// with `set -e` to stop on the first failure like separate lines would, and the `@`
+    /// and `-` of its first instruction, which apply to the whole script.
+    pub fn oneshell_script(&self) -> (&str, Vec<String>) {
+        let mut prefix = "";
+        let mut commands = vec!["set -e".to_string()];
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let (names, instruction) = restrictions(instruction);
+            let command = instruction.trim_start_matches(['@', '-', '+']);
+            if index == 0 {
+                prefix = &instruction[..instruction.len() - command.len()];
+            }
+            // a comment would hide the commands after it
+            if command.starts_with('#') {
+                continue;
+            }
+            let mut command = command.to_string();
+            if index > 0 && instruction[..instruction.len() - command.len()].contains('-') && !continues(&command) {
+                command.push_str(" || true");
+            }
+            // the platform is chosen when make runs, see `DOMAKE_OS` and `DOMAKE_ARCH` in the Makefile
+            for name in names.iter().rev() {
+                command = format!("if [ \"$({})\" = {} ]; then {}; fi", platform_variable(name), name, command);
+            }
+            commands.push(command);
+        }
+        (prefix, commands)
+    }
+
+    /// The Makefile rule of the target.
+    pub fn to_makefile(&self) -> String {
+        // `make help` only lists the `##` comments
+        let marker = if self.is_hidden() { "#" } else { "##" };
+        let mut buffer = format!("{} {}: {}\n", marker, self.name, self.summary());
+        for line in self.description.lines().skip(1) {
+            buffer.push_str(format!("# {}\n", line).trim_end());
+            buffer.push('\n');
+        }
+        // make only runs a file target when a prerequisite is newer than the file
+        if !self.file && !self.is_pattern() {
+            buffer.push_str(format!(".PHONY: {}\n", self.name).as_str());
+        }
+        if self.notparallel {
+            // before make 4.4, it would make the whole Makefile run one target at a time, so the
+            // Makefile leaves it out for these versions
+            buffer.push_str(format!(".NOTPARALLEL: {}\n", self.name).as_str());
+        }
+        for variable in &self.variables {
+            buffer.push_str(format!("{}: {}\n", self.name, variable.to_makefile()).as_str());
+        }
+        buffer.push_str(format!("{}:{}\n", self.name, self.prior_commands.iter().map(|prior| format!(" {}", prior)).collect::<String>()).as_str());
+
+        if let Some(advice) = &self.deprecated {
+            let warning = format!("Warning: target `{}` is deprecated{}", self.name, if advice.is_empty() { String::new() } else { format!(", {}", advice) });
+            // quoted for the shell, with the `$` escaped for make
+            let warning = warning.replace('\'', "'\\''").replace('$', "$$");
+            buffer.push_str(format!("\t@printf '\\033[1;33m%s\\033[0m\\n' '{}' >&2\n", warning).as_str());
+        }
+        for tool in &self.requires {
+            buffer.push_str(format!("\t@command -v {} >/dev/null 2>&1 || {{ echo '{}: `{}` is required but was not found in the PATH' >&2; exit 1; }}\n",
+                                    tool, self.name, tool).as_str());
+        }
+        if self.oneshell {
+            // a single recipe line, carried on over the next lines with a final `\`
+            let (prefix, commands) = self.oneshell_script();
+            let mut script = String::new();
+            for command in &commands {
+                if !script.is_empty() {
+                    script.push_str(if continues(&script) { " \\\n" } else { "; \\\n" });
+                }
+                script.push_str(command);
+            }
+            buffer.push_str(format!("\t{}{}\n", prefix, script.replace('\n', "\n\t")).as_str());
+            return buffer;
+        }
+        for instruction in &self.instructions {
+            // the platform is chosen when make runs, see `DOMAKE_OS` and `DOMAKE_ARCH` in the Makefile
+            let (names, instruction) = restrictions(instruction);
+            for name in &names {
+                buffer.push_str(format!("ifeq ($({}),{})\n", platform_variable(name), name).as_str());
+            }
+            buffer.push_str(format!("\t{}\n", instruction.replace('\n', "\n\t")).as_str());
+            buffer.push_str(&"endif\n".repeat(names.len()));
+        }
+        buffer
+    }
+}
+
+/// What is wrong in a Dofile.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// The target has no `#` description line.
+    MissingDescription(String),
+    /// The target has no instructions.
+    EmptyRecipe(String),
+    /// The target is already defined, at the given line.
+    DuplicateTarget(String, usize),
+    /// The targets require each other, from the first one back to it.
+    CircularDependency(Vec<String>),
+    /// The prerequisite is neither a target nor an existing file, maybe a typo of the suggested target.
+    UndefinedPrerequisite(String, Option<String>),
+    /// The `[` of a target header is never closed.
+    UnclosedHeader,
+    /// A target header has nothing between its brackets.
+    EmptyName,
+    /// The target name contains a character that make reads as syntax.
+    InvalidName(String, char),
+    /// An `include` line has no path.
+    MissingIncludePath,
+    /// A `dotenv` line has no path.
+    MissingDotenvPath,
+    /// A `use` line isn't `use PATH as NAME`, with what follows `use`.
+    InvalidUse(String),
+    /// Two `use` lines have the same namespace, with the line of the first one.
+    DuplicateNamespace(String, usize),
+    /// A `default` line has no target name.
+    MissingDefaultName,
+    /// A `shell` line has no shell name.
+    MissingShellName,
+    /// A second `shell` line, with the line of the first one.
+    DuplicateShell(usize),
+    /// A second `default` line, with the line of the first one.
+    DuplicateDefault(usize),
+    /// The `default` target isn't defined, maybe a typo of the suggested target.
+    UndefinedDefault(String, Option<String>),
+    /// A `comments:` line is neither `keep` nor `strip`.
+    InvalidComments(String),
+    /// A `dollars:` line is neither `shell` nor `make`, with what it says.
+    InvalidDollars(String),
+    /// A `vars:` line isn't a `NAME = value` assignment, with what it says.
+    InvalidTargetVariable(String),
+    /// A ```` ```make ```` block is never closed by a ```` ``` ```` line.
+    UnclosedRaw,
+    /// A `%profile` line has no profile name.
+    MissingProfileName,
+    /// A `%` line is not a known directive.
+    UnknownDirective(String),
+    /// The condition of an `%if` line cannot be read.
+    InvalidCondition(String),
+    /// An `%else` line isn't in a section, or its section already had one.
+    UnmatchedElse,
+    /// An `%end` line doesn't close any section.
+    UnmatchedEnd,
+    /// A section is never closed by an `%end` line.
+    UnclosedSection,
+    /// A line outside of any target is neither a comment, an `include`, a `dotenv` nor a variable.
+    UnexpectedLine,
+}
+
+/// A mistake in a Dofile, at a line and column both starting at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    /// The bytes of the Dofile the mistake is about.
+    pub span: Range<usize>,
+    pub kind: ErrorKind,
+    /// Other parts of the Dofile involved in the mistake, with what they are.
+    pub related: Vec<(Range<usize>, String)>,
+}
+
+impl ParseError {
+    pub(crate) fn new(content: &str, span: Range<usize>, kind: ErrorKind, related: Vec<(Range<usize>, String)>) -> ParseError {
+        let before = &content[..span.start];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        ParseError { line, column, span, kind, related }
+    }
+
+    /// How to fix the mistake.
+    pub fn hint(&self) -> String {
+        let hint = match &self.kind {
+            ErrorKind::MissingDescription(_) => "add a `# description` line right after the header",
+            ErrorKind::EmptyRecipe(_) => "add the instructions after the description, one per line",
+            ErrorKind::DuplicateTarget(..) => "rename one of the targets, or merge their instructions",
+            ErrorKind::CircularDependency(_) => "remove one of the prerequisites of the cycle",
+            ErrorKind::UndefinedPrerequisite(_, Some(suggestion)) => return format!("did you mean `{}`?", suggestion),
+            ErrorKind::UndefinedPrerequisite(_, None) => "define the target, or create the file before generating the Makefile",
+            ErrorKind::UnclosedHeader => "target names are written between brackets, e.g. `[build]`",
+            ErrorKind::EmptyName => "write the name of the target between the brackets, e.g. `[build]`",
+            ErrorKind::InvalidName(..) => "use letters, digits, `-`, `_`, `.` or `/` in target names",
+            ErrorKind::MissingIncludePath => "write the file to include after `include`, e.g. `include .env`",
+            ErrorKind::MissingDotenvPath => "write the `.env` file to load after `dotenv`, e.g. `dotenv .env`",
+            ErrorKind::InvalidUse(_) => "write the Dofile to use and the prefix of its targets, e.g. `use frontend/Dofile as fe`",
+            ErrorKind::DuplicateNamespace(..) => "give each used Dofile its own name after `as`",
+            ErrorKind::MissingDefaultName => "write the target plain `make` runs after `default`, e.g. `default build`",
+            ErrorKind::DuplicateDefault(_) => "keep a single `default` line",
+            ErrorKind::MissingShellName => "write the shell running the instructions after `shell`, e.g. `shell bash`",
+            ErrorKind::DuplicateShell(_) => "keep a single `shell` line",
+            ErrorKind::UndefinedDefault(_, Some(suggestion)) => return format!("did you mean `{}`?", suggestion),
+            ErrorKind::UndefinedDefault(_, None) => "define the target, or make another one the default",
+            ErrorKind::InvalidComments(_) => "write `comments: strip` to leave the `#` lines of the instructions out of the Makefile",
+            ErrorKind::InvalidDollars(_) => "write `dollars: make` to leave every `$` of the instructions to make",
+            ErrorKind::InvalidTargetVariable(_) => "write one assignment per `vars:` line, e.g. `vars: CFLAGS = -O2`",
+            ErrorKind::UnclosedRaw => "close the block with a line of three backquotes",
+            ErrorKind::MissingProfileName => "write the profiles of the section after `%profile`, e.g. `%profile ci release`",
+            ErrorKind::InvalidCondition(_) => "conditions are `profile NAME...`, `os NAME...`, `arch NAME...`, `env NAME` or `env NAME=VALUE`, negated by a leading `!`",
+            ErrorKind::UnknownDirective(_) => "the directives are `%profile`, `%if`, `%else` and `%end`",
+            ErrorKind::UnmatchedElse => "a section has at most one `%else`, before its `%end`",
+            ErrorKind::UnmatchedEnd => "remove the `%end`, or open its section before it",
+            ErrorKind::UnclosedSection => "close the section with an `%end` line",
+            ErrorKind::UnexpectedLine => "start a target with a `[name]` header, define a `NAME = value` variable, or make the line a `#` comment",
+        };
+        hint.to_string()
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::MissingDescription(name) => write!(f, "target `{}` has no `#` description line", name),
+            ErrorKind::EmptyRecipe(name) => write!(f, "target `{}` has no instructions", name),
+            ErrorKind::DuplicateTarget(name, line) => write!(f, "target `{}` is already defined at line {}", name, line),
+            ErrorKind::CircularDependency(cycle) => write!(f, "circular dependency: {}", cycle.join(" -> ")),
+            ErrorKind::UndefinedPrerequisite(prior, _) => write!(f, "prerequisite `{}` is neither a target nor an existing file", prior),
+            ErrorKind::UnclosedHeader => write!(f, "missing `]` after the target name"),
+            ErrorKind::EmptyName => write!(f, "missing target name between the brackets"),
+            ErrorKind::InvalidName(name, ' ') => write!(f, "target name `{}` contains a space", name),
+            ErrorKind::InvalidName(name, c) if c.is_whitespace() => write!(f, "target name `{}` contains whitespace", name),
+            ErrorKind::InvalidName(name, c) => write!(f, "target name `{}` contains `{}`, which make reads as syntax", name, c),
+            ErrorKind::MissingIncludePath => write!(f, "missing path after `include`"),
+            ErrorKind::MissingDotenvPath => write!(f, "missing path after `dotenv`"),
+            ErrorKind::InvalidUse(text) => write!(f, "`use` expects `use PATH as NAME`, not `use {}`", text),
+            ErrorKind::DuplicateNamespace(namespace, line) => write!(f, "namespace `{}` is already used at line {}", namespace, line),
+            ErrorKind::MissingDefaultName => write!(f, "missing target name after `default`"),
+            ErrorKind::DuplicateDefault(line) => write!(f, "the default target is already set at line {}", line),
+            ErrorKind::MissingShellName => write!(f, "missing shell name after `shell`"),
+            ErrorKind::DuplicateShell(line) => write!(f, "the shell is already set at line {}", line),
+            ErrorKind::UndefinedDefault(name, _) => write!(f, "default target `{}` isn't defined", name),
+            ErrorKind::InvalidComments(choice) => write!(f, "`comments:` expects `keep` or `strip`, not `{}`", choice),
+            ErrorKind::InvalidDollars(choice) => write!(f, "`dollars:` expects `shell` or `make`, not `{}`", choice),
+            ErrorKind::InvalidTargetVariable(text) => write!(f, "`vars:` expects a variable assignment, not `{}`", text),
+            ErrorKind::UnclosedRaw => write!(f, "raw block never closed by ```"),
+            ErrorKind::MissingProfileName => write!(f, "missing profile name after `%profile`"),
+            ErrorKind::UnknownDirective(directive) => write!(f, "unknown directive `%{}`", directive),
+            ErrorKind::InvalidCondition(condition) if condition.is_empty() => write!(f, "missing condition after `%if`"),
+            ErrorKind::InvalidCondition(condition) => write!(f, "invalid condition `{}`", condition),
+            ErrorKind::UnmatchedElse => write!(f, "`%else` without a section to switch"),
+            ErrorKind::UnmatchedEnd => write!(f, "`%end` without a section to close"),
+            ErrorKind::UnclosedSection => write!(f, "section never closed by `%end`"),
+            ErrorKind::UnexpectedLine => write!(f, "unexpected line outside of a target, targets start with `[name]`"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The characters of a target name that would break the Makefile rule.
+const RESERVED_CHARACTERS: &[char] = &[':', ';', '%', '$', '#', '='];
+
+/// The first character of `name` that isn't allowed in a target name, with its index.
+fn invalid_character(name: &str) -> Option<(usize, char)> {
+    name.char_indices().find(|(_, c)| c.is_whitespace() || RESERVED_CHARACTERS.contains(c) || *c == '[' || *c == ']')
+}
+
+/// Whether `name` can be the name of a target.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && invalid_character(name).is_none()
+}
+
+/// The variables of make itself, or of the Makefile domake writes, that the instructions refer to
+/// without declaring them.
+const MAKE_VARIABLES: [&str; 6] = ["MAKE", "MAKEFLAGS", "MAKECMDGOALS", "CURDIR", "DOMAKE_OS", "DOMAKE_ARCH"];
+
+/// Escapes the `$` of an instruction that make would read, so that `$HOME`, `$1`, `$(date)` or
+/// `${HOME}` reach the shell; `$(NAME)` and `${NAME}` stay make references when `NAME` is among
+/// the `declared` variables, and `$$` is already escaped.
+pub(crate) fn escape_dollars(instruction: &str, declared: &[String]) -> String {
+    let is_declared = |name: &str| is_variable_name(name) && (declared.iter().any(|declared| declared == name) || MAKE_VARIABLES.contains(&name));
+    let mut buffer = String::new();
+    let mut rest = instruction;
+    while let Some(start) = rest.find('$') {
+        buffer.push_str(&rest[..=start]);
+        rest = &rest[start + 1..];
+        let close = match rest.chars().next() {
+            Some('$') => {
+                buffer.push('$');
+                rest = &rest[1..];
+                continue;
+            }
+            Some('(') => ')',
+            Some('{') => '}',
+            _ => {
+                buffer.push('$');
+                continue;
+            }
+        };
+        // `$(NAME:.c=.o)` substitutes in the variable `NAME`
+        let reference = rest[1..].split_once(close).map(|(inside, _)| inside.split_once(':').map_or(inside, |(name, _)| name));
+        if !reference.is_some_and(|name| is_declared(name.trim())) {
+            buffer.push('$');
+        }
+    }
+    buffer.push_str(rest);
+    buffer
+}
+
+/// The names of the variables declared by the lines of a Dofile outside of its targets, wherever
+/// they are, the raw blocks included.
+pub(crate) fn declared_variables(lines: &[&str]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        match lex(lines[index]) {
+            // a `NAME=value` instruction sets a shell variable
+            Token::Header => index = block_end(lines, index),
+            Token::Variable(name, ..) => {
+                names.push(name.to_string());
+                index += 1;
+            }
+            _ => index += 1,
+        }
+    }
+    names
+}
+
+/// Translates the `{{NAME}}` references to variables into make's `$(NAME)`; other
+/// `{{...}}` (e.g. the `{{.Names}}` of a Go template) are left as they are.
+pub(crate) fn interpolate(text: &str) -> String {
+    let mut buffer = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        buffer.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest[2..].split_once("}}").map(|(name, _)| name).filter(|name| is_variable_name(name.trim())) {
+            Some(name) => {
+                buffer.push_str(format!("$({})", name.trim()).as_str());
+                rest = &rest[name.len() + 4..];
+            }
+            None => {
+                buffer.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    buffer.push_str(rest);
+    buffer
+}
+
+/// A line of the Dofile outside of the targets, classified by how it starts.
+enum Token<'a> {
+    Blank,
+    /// A `#` line.
+    Comment,
+    /// An `include` line, with its path and whether it is an `include?`.
+    Include(&'a str, bool),
+    /// A `dotenv` line, with its path and whether it is a `dotenv?`.
+    Dotenv(&'a str, bool),
+    /// A `use` line, with what follows `use`.
+    Use(&'a str),
+    /// A `default` line, with its target name.
+    Default(&'a str),
+    /// A `shell` line, with its shell name and flags.
+    Shell(&'a str),
+    /// A `oneshell` line.
+    Oneshell,
+    /// A `notparallel` line.
+    Notparallel,
+    /// A `NAME = value` line, with its name, assignment and value, and whether it starts with `export`.
+    Variable(&'a str, Assignment, &'a str, bool),
+    /// A `[name] prerequisites` line.
+    Header,
+    /// A ```` ```make ```` or ```` ```raw ```` line opening a raw block.
+    Fence,
+    Text,
+}
+
+fn lex(line: &str) -> Token<'_> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Token::Blank;
+    }
+    if line.starts_with('#') {
+        return Token::Comment;
+    }
+    if line.starts_with('[') {
+        return Token::Header;
+    }
+    if line == "```make" || line == "```raw" {
+        return Token::Fence;
+    }
+    let directive = |keyword: &str| line.strip_prefix(keyword)
+        .filter(|path| path.is_empty() || path.starts_with(char::is_whitespace))
+        .map(str::trim);
+    if let Some(path) = directive("include") {
+        return Token::Include(path, false);
+    }
+    if let Some(path) = directive("include?") {
+        return Token::Include(path, true);
+    }
+    if let Some(path) = directive("dotenv") {
+        return Token::Dotenv(path, false);
+    }
+    if let Some(path) = directive("dotenv?") {
+        return Token::Dotenv(path, true);
+    }
+    if let Some(import) = directive("use").filter(|import| !import.starts_with(['=', ':', '?', '+'])) {
+        return Token::Use(import);
+    }
+    // unlike `default = value`, which is a variable
+    if let Some(name) = directive("default").filter(|name| !name.contains('=')) {
+        return Token::Default(name);
+    }
+    if line == "oneshell" {
+        return Token::Oneshell;
+    }
+    if line == "notparallel" {
+        return Token::Notparallel;
+    }
+    if let Some(shell) = directive("shell").filter(|shell| !shell.starts_with(['=', ':', '?', '+'])) {
+        return Token::Shell(shell);
+    }
+    let (exported, line) = match directive("export") {
+        // unlike `export = value`, which is a variable
+        Some(assignment) if assignment.contains('=') && !assignment.starts_with(['=', ':', '?', '+']) => (true, assignment),
+        _ => (false, line),
+    };
+    let Some((name, value)) = line.split_once('=') else {
+        return Token::Text;
+    };
+    // the first character of the operator, if any, ends the name
+    let assignment = match name.chars().last() {
+        Some(':') => Assignment::Simple,
+        Some('?') => Assignment::Conditional,
+        Some('+') => Assignment::Append,
+        _ => Assignment::Recursive,
+    };
+    let name = if assignment == Assignment::Recursive { name } else { &name[..name.len() - 1] }.trim();
+    if is_variable_name(name) {
+        Token::Variable(name, assignment, value.trim(), exported)
+    } else {
+        Token::Text
+    }
+}
+
+/// Whether `name` can be the name of a variable: letters, digits and `_`, not starting with a digit.
+pub fn is_variable_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The index of the line after the block of a target whose header is the line `start`, as the
+/// parser reads it: up to a blank line, unless the line after the blank lines is indented. A `%`
+/// line ends it as well, as it belongs to the sections around the target.
+pub fn block_end(lines: &[&str], start: usize) -> usize {
+    let mut end = start;
+    loop {
+        while lines.get(end).is_some_and(|line| !line.trim().is_empty() && !line.trim().starts_with('%')) {
+            end += 1;
+        }
+        if !is_indented_after_blanks(&lines[end.min(lines.len())..]) || lines.get(end).is_some_and(|line| line.trim().starts_with('%')) {
+            return end;
+        }
+        while lines.get(end).is_some_and(|line| line.trim().is_empty()) {
+            end += 1;
+        }
+    }
+}
+
+/// Whether the first line after the blank lines at the start of `lines` is indented.
+fn is_indented_after_blanks(lines: &[&str]) -> bool {
+    lines.iter()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.starts_with(char::is_whitespace))
+}
+
+struct Parser<'a> {
+    content: &'a str,
+    lines: Vec<&'a str>,
+    /// The index of the next line to read.
+    next: usize,
+    context: &'a Context,
+    /// The sections the next line is in, innermost last.
+    sections: Vec<Section<'a>>,
+    /// The variables declared anywhere in the Dofile, which the instructions refer to as `$(NAME)`.
+    declared: Vec<String>,
+    dofile: Dofile,
+    errors: Vec<ParseError>,
+}
+
+/// A `%profile` or `%if` section of the Dofile.
+struct Section<'a> {
+    /// The `%` line opening the section.
+    line: &'a str,
+    /// Whether the lines of the section are kept.
+    kept: bool,
+    /// Whether the lines after an `%else` would be kept, `None` once it is read.
+    otherwise: Option<bool>,
+}
+
+impl<'a> Parser<'a> {
+    /// Whether the lines of the current section are kept.
+    fn is_kept(&self) -> bool {
+        self.sections.last().is_none_or(|section| section.kept)
+    }
+
+    /// Opens a section whose lines are kept if `selected` and the current ones are.
+    fn open(&mut self, line: &'a str, selected: bool) {
+        let kept = self.is_kept();
+        trace!("line {}: `{}` {} its lines", self.next, line, if kept && selected { "keeps" } else { "leaves out" });
+        self.sections.push(Section { line, kept: kept && selected, otherwise: Some(kept && !selected) });
+    }
+
+    /// The next line to parse, after handling the `%` directives before it
+    /// and skipping the sections that are left out.
+    fn peek(&mut self) -> Option<&'a str> {
+        loop {
+            let line = *self.lines.get(self.next)?;
+            if line.trim().starts_with('%') {
+                self.next += 1;
+                self.directive(line.trim());
+            } else if !self.is_kept() {
+                self.next += 1;
+                trace!("line {}: left out by `{}`", self.next, self.sections.last().map_or("", |section| section.line));
+                // a raw block is left out whole, whatever its lines look like
+                if matches!(lex(line), Token::Fence) {
+                    self.raw_text();
+                }
+            } else {
+                return Some(line);
+            }
+        }
+    }
+
+    /// The next line and its number, starting at 1.
+    fn line(&mut self) -> Option<(usize, &'a str)> {
+        let line = self.peek()?;
+        self.next += 1;
+        Some((self.next, line))
+    }
+
+    /// The next line if it belongs to the current block, i.e. isn't blank.
+    fn block_line(&mut self) -> Option<(usize, &'a str)> {
+        match self.peek() {
+            Some(line) if !line.trim().is_empty() => self.line(),
+            _ => None,
+        }
+    }
+
+    /// Opens, switches or closes a section with the `%` line `line`.
+    fn directive(&mut self, line: &'a str) {
+        let (keyword, rest) = line[1..].split_once(char::is_whitespace).unwrap_or((&line[1..], ""));
+        match keyword {
+            "profile" if rest.trim().is_empty() => self.record(line, ErrorKind::MissingProfileName),
+            "profile" => {
+                let selected = self.context.profile.as_deref().is_some_and(|profile| rest.split_whitespace().any(|name| name == profile));
+                self.open(line, selected);
+            }
+            "if" => match self.context.holds(rest) {
+                Some(holds) => self.open(line, holds),
+                None => {
+                    self.record(line, ErrorKind::InvalidCondition(rest.trim().to_string()));
+                    // the whole section is left out, up to its `%end`
+                    self.sections.push(Section { line, kept: false, otherwise: Some(false) });
+                }
+            },
+            "else" => {
+                if let Some(section) = self.sections.last_mut() {
+                    if let Some(otherwise) = section.otherwise.take() {
+                        section.kept = otherwise;
+                        trace!("line {}: `%else` {} its lines", self.next, if otherwise { "keeps" } else { "leaves out" });
+                        return;
+                    }
+                }
+                self.record(line, ErrorKind::UnmatchedElse);
+            }
+            "end" => {
+                if self.sections.pop().is_none() {
+                    self.record(line, ErrorKind::UnmatchedEnd);
+                }
+            }
+            _ => self.record(line, ErrorKind::UnknownDirective(keyword.to_string())),
+        }
+    }
+
+    /// The lines of a raw block up to its closing fence, read as they are, or `None` if it is never closed.
+    fn raw_text(&mut self) -> Option<String> {
+        let start = self.next;
+        while let Some(line) = self.lines.get(self.next) {
+            self.next += 1;
+            if line.trim() == "```" {
+                return Some(self.lines[start..self.next - 1].join("\n"));
+            }
+        }
+        None
+    }
+
+    /// Whether the first line after the next blank lines is indented.
+    fn is_indented_after_blanks(&self) -> bool {
+        is_indented_after_blanks(&self.lines[self.next..])
+    }
+
+    /// Skips the rest of the current block, up to the next blank line.
+    fn skip_block(&mut self) {
+        while self.block_line().is_some() {}
+    }
+
+    /// The bytes of `part`, a slice of the content, in the content.
+    fn span(&self, part: &str) -> Range<usize> {
+        let start = part.as_ptr() as usize - self.content.as_ptr() as usize;
+        start..start + part.len()
+    }
+
+    /// Records a mistake about `part`, a slice of the content, and skips the rest of its block.
+    fn error(&mut self, part: &str, kind: ErrorKind) {
+        self.error_with(part, kind, Vec::new());
+    }
+
+    fn error_with(&mut self, part: &str, kind: ErrorKind, related: Vec<(Range<usize>, String)>) {
+        self.errors.push(ParseError::new(self.content, self.span(part), kind, related));
+        self.skip_block();
+    }
+
+    /// Records a mistake about `part` without skipping anything.
+    fn record(&mut self, part: &str, kind: ErrorKind) {
+        self.errors.push(ParseError::new(self.content, self.span(part), kind, Vec::new()));
+    }
+
+    fn parse(&mut self) {
+        while let Some((number, line)) = self.line() {
+            match lex(line) {
+                Token::Blank | Token::Comment => {}
+                Token::Include("", _) => self.error(line.trim(), ErrorKind::MissingIncludePath),
+                Token::Include(path, optional) => {
+                    debug!("line {}: include `{}`{}", number, path, if optional { " (optional)" } else { "" });
+                    let span = self.span(path);
+                    self.dofile.includes.push(Include { line: number, span, path: path.to_string(), optional });
+                }
+                Token::Dotenv("", _) => self.error(line.trim(), ErrorKind::MissingDotenvPath),
+                Token::Dotenv(path, optional) => {
+                    debug!("line {}: dotenv `{}`{}", number, path, if optional { " (optional)" } else { "" });
+                    let span = self.span(path);
+                    self.dofile.dotenvs.push(Include { line: number, span, path: path.to_string(), optional });
+                }
+                Token::Use(import) => {
+                    let parts = import.rsplit_once(" as ").map(|(path, namespace)| (path.trim(), namespace.trim()));
+                    let Some((path, namespace)) = parts.filter(|(path, namespace)| !path.is_empty() && is_valid_name(namespace) && !namespace.contains('/')) else {
+                        self.error(line.trim(), ErrorKind::InvalidUse(import.to_string()));
+                        continue;
+                    };
+                    if let Some(first) = self.dofile.uses.iter().find(|first| first.namespace == namespace) {
+                        let (kind, related) = (ErrorKind::DuplicateNamespace(namespace.to_string(), first.line), vec![(first.span.clone(), "first used here".to_string())]);
+                        self.error_with(namespace, kind, related);
+                        continue;
+                    }
+                    debug!("line {}: use `{}` as `{}`", number, path, namespace);
+                    let span = self.span(path);
+                    self.dofile.uses.push(Use { line: number, span, path: path.to_string(), namespace: namespace.to_string() });
+                }
+                Token::Default("") => self.error(&line.trim()[.."default".len()], ErrorKind::MissingDefaultName),
+                Token::Default(name) => match &self.dofile.default {
+                    Some(first) => {
+                        let related = vec![(first.span.clone(), "first set here".to_string())];
+                        self.error_with(name, ErrorKind::DuplicateDefault(first.line), related);
+                    }
+                    None => {
+                        debug!("line {}: default target `{}`", number, name);
+                        let span = self.span(name);
+                        self.dofile.default = Some(Goal { line: number, span, name: name.to_string() });
+                    }
+                },
+                Token::Oneshell => {
+                    debug!("line {}: every target runs in a single shell", number);
+                    self.dofile.oneshell = true;
+                }
+                Token::Notparallel => {
+                    debug!("line {}: the targets run one at a time", number);
+                    self.dofile.notparallel = true;
+                }
+                Token::Shell("") => self.error(&line.trim()[.."shell".len()], ErrorKind::MissingShellName),
+                Token::Shell(shell) => {
+                    let mut words = shell.split_whitespace();
+                    let name = words.next().unwrap_or_default();
+                    match &self.dofile.shell {
+                        Some(first) => {
+                            let related = vec![(first.span.clone(), "first set here".to_string())];
+                            self.error_with(name, ErrorKind::DuplicateShell(first.line), related);
+                        }
+                        None => {
+                            let span = self.span(name);
+                            let flags = words.map(str::to_string).collect::<Vec<_>>();
+                            debug!("line {}: shell `{}` {}", number, name, flags.join(" "));
+                            self.dofile.shell = Some(Shell { line: number, span, name: name.to_string(), flags });
+                        }
+                    }
+                }
+                Token::Variable(name, assignment, value, exported) => {
+                    debug!("line {}: {}variable `{}` {} `{}`", number, if exported { "exported " } else { "" }, name, assignment.operator(), value);
+                    let span = self.span(name);
+                    self.dofile.variables.push(Variable { line: number, span, name: name.to_string(), assignment, value: interpolate(value), exported });
+                }
+                Token::Header => self.command(number, line.trim()),
+                Token::Fence => match self.raw_text() {
+                    Some(text) => {
+                        debug!("line {}: raw block of {} line(s)", number, text.lines().count());
+                        let span = self.span(line.trim());
+                        self.dofile.raws.push(Raw { line: number, span, text });
+                    }
+                    None => self.record(line.trim(), ErrorKind::UnclosedRaw),
+                },
+                Token::Text => self.error(line.trim(), ErrorKind::UnexpectedLine),
+            }
+        }
+        for section in std::mem::take(&mut self.sections) {
+            self.record(section.line, ErrorKind::UnclosedSection);
+        }
+        if self.dofile.oneshell {
+            for cmd in &mut self.dofile.commands {
+                cmd.oneshell = true;
+            }
+        }
+    }
+
+    /// Parses the block of the target whose header is `header`:
+    /// its prerequisites, `#` description and instructions, up to the next blank line.
+    fn command(&mut self, line: usize, header: &'a str) {
+        let Some(close) = header.find(']') else {
+            return self.error(header, ErrorKind::UnclosedHeader);
+        };
+        let (bracketed, rest) = header.split_at(close + 1);
+        let name = bracketed[1..close].trim();
+        let (name, file) = match name.strip_prefix("file:") {
+            Some(path) => (path.trim(), true),
+            None => (name, false),
+        };
+        if name.is_empty() {
+            return self.error(bracketed, ErrorKind::EmptyName);
+        }
+        // a single `%` makes a pattern rule
+        let checked = if name.matches('%').count() == 1 { name.replace('%', "_") } else { name.to_string() };
+        if let Some((index, c)) = invalid_character(&checked) {
+            return self.error(&name[index..index + c.len_utf8()], ErrorKind::InvalidName(name.to_string(), c));
+        }
+
+        let mut prior_commands = rest.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+        // the prerequisites may also be on their own line
+        if prior_commands.is_empty() && self.peek().is_some_and(|next| !next.trim().starts_with('#')) {
+            if let Some((_, next)) = self.block_line() {
+                prior_commands = next.split_whitespace().map(str::to_string).collect();
+            }
+        }
+
+        let mut description = match self.block_line() {
+            Some((_, next)) if next.trim().starts_with('#') => next.trim()[1..].trim().to_string(),
+            _ => return self.error(bracketed, ErrorKind::MissingDescription(name.to_string())),
+        };
+        // the next `#` lines carry on the description, except for the `#@` tags
+        while let Some(next) = self.peek().map(str::trim).filter(|next| next.starts_with('#') && !next.starts_with("#@")) {
+            self.line();
+            description.push('\n');
+            description.push_str(next[1..].trim());
+        }
+
+        // the attributes of the target, before its instructions
+        // the automatic variables of make (e.g. `$<`, `$@`) are what pattern rules are written with
+        let (mut requires, mut group, mut deprecated, mut strip_comments, mut make_dollars) = (Vec::new(), None, None, false, name.contains('%'));
+        let (mut variables, mut oneshell, mut notparallel) = (Vec::new(), false, false);
+        while let Some(next) = self.peek().map(str::trim) {
+            if let Some(tools) = next.strip_prefix("requires:") {
+                requires.extend(tools.split([',', ' ', '\t']).filter(|tool| !tool.is_empty()).map(str::to_string));
+            } else if let Some(advice) = next.strip_prefix("deprecated:") {
+                deprecated = Some(advice.trim().to_string());
+            } else if let Some(choice) = next.strip_prefix("comments:").map(str::trim) {
+                strip_comments = match choice {
+                    "keep" => false,
+                    "strip" => true,
+                    _ => return self.error(next, ErrorKind::InvalidComments(choice.to_string())),
+                };
+            } else if let Some(assignment) = next.strip_prefix("vars:") {
+                let Token::Variable(variable, assignment, value, exported) = lex(assignment) else {
+                    return self.error(next, ErrorKind::InvalidTargetVariable(assignment.trim().to_string()));
+                };
+                let (line, span) = (self.next + 1, self.span(variable));
+                variables.push(Variable { line, span, name: variable.to_string(), assignment, value: interpolate(value), exported });
+            } else if let Some(choice) = next.strip_prefix("dollars:").map(str::trim) {
+                make_dollars = match choice {
+                    "shell" => false,
+                    "make" => true,
+                    _ => return self.error(next, ErrorKind::InvalidDollars(choice.to_string())),
+                };
+            } else if let Some(name) = next.strip_prefix("#@").and_then(|tag| tag.trim().strip_prefix("group:")) {
+                group = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+            } else if next.strip_prefix("#@").is_some_and(|tag| tag.trim() == "oneshell") {
+                oneshell = true;
+            } else if next.strip_prefix("#@").is_some_and(|tag| tag.trim() == "notparallel") {
+                notparallel = true;
+            } else {
+                break;
+            }
+            trace!("line {}: attribute `{}` of `{}`", self.next + 1, next, name);
+            self.line();
+        }
+
+        let declared = self.declared.iter().chain(variables.iter().map(|variable| &variable.name)).cloned().collect::<Vec<_>>();
+        let mut instructions = Vec::new();
+        loop {
+            while let Some((_, next)) = self.block_line() {
+                let mut instruction = next.trim().to_string();
+                // a `\` at the end carries the instruction on over the next line, kept as is
+                while instruction.ends_with('\\') {
+                    let Some((_, more)) = self.block_line() else { break };
+                    instruction.push('\n');
+                    instruction.push_str(more.trim_end());
+                }
+                // the `#` lines are shell comments, which make echoes like the instructions
+                if strip_comments && instruction.starts_with('#') {
+                    trace!("line {}: comment of `{}` stripped", self.next, name);
+                } else {
+                    let instruction = if make_dollars { instruction } else { escape_dollars(&instruction, &declared) };
+                    instructions.push(interpolate(&instruction));
+                }
+            }
+            // blank lines followed by an indented line are spacing inside the instructions
+            if !self.is_indented_after_blanks() {
+                break;
+            }
+            while self.peek().is_some_and(|line| line.trim().is_empty()) {
+                self.line();
+            }
+        }
+        if instructions.is_empty() {
+            return self.error(bracketed, ErrorKind::EmptyRecipe(name.to_string()));
+        }
+        debug!("line {}: target `{}`{}, {} instruction(s){}", line, name, if prior_commands.is_empty() { String::new() } else { format!(" after `{}`", prior_commands.join("`, `")) }, instructions.len(), if make_dollars { ", with make dollars" } else { "" });
+        if let Some(first) = self.dofile.commands.iter().find(|cmd| cmd.name == name) {
+            let (kind, related) = (ErrorKind::DuplicateTarget(name.to_string(), first.line), vec![(first.span.clone(), "first defined here".to_string())]);
+            return self.error_with(bracketed, kind, related);
+        }
+
+        self.dofile.commands.push(Command {
+            line,
+            span: self.span(bracketed),
+            name: name.to_string(),
+            file,
+            description,
+            prior_commands,
+            requires,
+            group,
+            deprecated,
+            variables,
+            oneshell,
+            notparallel,
+            instructions,
+        });
+    }
+}
+
+/// Parses the content of a Dofile and checks its targets, failing on its first mistake.
+pub fn parse_dofile(content: &str) -> Result<Dofile, ParseError> {
+    parse_dofile_with(content, &Context::default())
+}
+
+/// Parses the content of a Dofile for `context` and checks its targets, failing on its first mistake.
+pub fn parse_dofile_with(content: &str, context: &Context) -> Result<Dofile, ParseError> {
+    let (dofile, mut errors) = parse_dofile_partial_with(content, context);
+    if errors.is_empty() {
+        Ok(dofile)
+    } else {
+        Err(errors.remove(0))
+    }
+}
+
+/// Parses every well-formed part of a Dofile, leaving out the targets with
+/// mistakes, then checks the targets together; the mistakes are returned in
+/// the order of the file.
+pub fn parse_dofile_partial(content: &str) -> (Dofile, Vec<ParseError>) {
+    parse_dofile_partial_with(content, &Context::default())
+}
+
+/// Like [`parse_dofile_partial`], keeping the sections selected by `context`.
+pub fn parse_dofile_partial_with(content: &str, context: &Context) -> (Dofile, Vec<ParseError>) {
+    let lines = content.lines().collect::<Vec<_>>();
+    let mut parser = Parser {
+        content,
+        declared: declared_variables(&lines),
+        lines,
+        next: 0,
+        context,
+        sections: Vec::new(),
+        dofile: Dofile::default(),
+        errors: Vec::new(),
+    };
+    parser.parse();
+    let mut errors = parser.errors;
+    errors.extend(validate::validate(content, &parser.dofile));
+    errors.sort_by_key(|err| err.span.start);
+    (parser.dofile, errors)
+}
+
+fn span_to_json(span: &Range<usize>) -> Json {
+    vec![span.start, span.end].into()
+}
+
+impl From<&Include> for Json {
+    fn from(include: &Include) -> Json {
+        Json::object([
+            ("line", include.line.into()),
+            ("span", span_to_json(&include.span)),
+            ("path", include.path.as_str().into()),
+            ("optional", include.optional.into()),
+        ])
+    }
+}
+
+impl From<&Use> for Json {
+    fn from(import: &Use) -> Json {
+        Json::object([
+            ("line", import.line.into()),
+            ("span", span_to_json(&import.span)),
+            ("path", import.path.as_str().into()),
+            ("namespace", import.namespace.as_str().into()),
+        ])
+    }
+}
+
+impl From<&Goal> for Json {
+    fn from(goal: &Goal) -> Json {
+        Json::object([
+            ("line", goal.line.into()),
+            ("span", span_to_json(&goal.span)),
+            ("name", goal.name.as_str().into()),
+        ])
+    }
+}
+
+impl From<&Shell> for Json {
+    fn from(shell: &Shell) -> Json {
+        Json::object([
+            ("line", shell.line.into()),
+            ("span", span_to_json(&shell.span)),
+            ("name", shell.name.as_str().into()),
+            ("flags", shell.flags.iter().map(String::as_str).collect::<Vec<_>>().into()),
+        ])
+    }
+}
+
+impl From<&Raw> for Json {
+    fn from(raw: &Raw) -> Json {
+        Json::object([
+            ("line", raw.line.into()),
+            ("span", span_to_json(&raw.span)),
+            ("text", raw.text.as_str().into()),
+        ])
+    }
+}
+
+impl From<&Variable> for Json {
+    fn from(variable: &Variable) -> Json {
+        Json::object([
+            ("line", variable.line.into()),
+            ("span", span_to_json(&variable.span)),
+            ("name", variable.name.as_str().into()),
+            ("operator", variable.assignment.operator().into()),
+            ("value", variable.value.as_str().into()),
+            ("exported", variable.exported.into()),
+        ])
+    }
+}
+
+impl From<&Command> for Json {
+    fn from(cmd: &Command) -> Json {
+        Json::object([
+            ("line", cmd.line.into()),
+            ("span", span_to_json(&cmd.span)),
+            ("name", cmd.name.as_str().into()),
+            ("file", cmd.file.into()),
+            ("description", cmd.description.as_str().into()),
+            ("prior_commands", cmd.prior_commands.iter().map(String::as_str).collect::<Vec<_>>().into()),
+            ("requires", cmd.requires.iter().map(String::as_str).collect::<Vec<_>>().into()),
+            ("group", cmd.group.as_deref().into()),
+            ("deprecated", cmd.deprecated.as_deref().into()),
+            ("variables", Json::Array(cmd.variables.iter().map(Json::from).collect())),
+            ("oneshell", cmd.oneshell.into()),
+            ("notparallel", cmd.notparallel.into()),
+            ("instructions", cmd.instructions.iter().map(String::as_str).collect::<Vec<_>>().into()),
+        ])
+    }
+}
+
+impl From<&Dofile> for Json {
+    fn from(dofile: &Dofile) -> Json {
+        Json::object([
+            ("includes", Json::Array(dofile.includes.iter().map(Json::from).collect())),
+            ("dotenvs", Json::Array(dofile.dotenvs.iter().map(Json::from).collect())),
+            ("uses", Json::Array(dofile.uses.iter().map(Json::from).collect())),
+            ("raws", Json::Array(dofile.raws.iter().map(Json::from).collect())),
+            ("default", dofile.default.as_ref().map_or(Json::Null, Json::from)),
+            ("shell", dofile.shell.as_ref().map_or(Json::Null, Json::from)),
+            ("oneshell", dofile.oneshell.into()),
+            ("notparallel", dofile.notparallel.into()),
+            ("variables", Json::Array(dofile.variables.iter().map(Json::from).collect())),
+            ("commands", Json::Array(dofile.commands.iter().map(Json::from).collect())),
+        ])
+    }
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, String> {
+    json.get(key).ok_or_else(|| format!("missing field `{}`", key))
+}
+
+fn string(json: &Json, key: &str) -> Result<String, String> {
+    field(json, key)?.as_str().map(str::to_string).ok_or_else(|| format!("`{}` must be a string", key))
+}
+
+/// The boolean `key`, false when it is left out.
+fn boolean(json: &Json, key: &str) -> Result<bool, String> {
+    match json.get(key) {
+        None => Ok(false),
+        Some(value) => value.as_bool().ok_or_else(|| format!("`{}` must be a boolean", key)),
+    }
+}
+
+fn optional_string(json: &Json, key: &str) -> Result<Option<String>, String> {
+    match json.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(_) => string(json, key).map(Some),
+    }
+}
+
+/// The line of the construct in the Dofile, 0 when it is left out, as for a model built by another program.
+fn line(json: &Json) -> Result<usize, String> {
+    match json.get("line") {
+        None => Ok(0),
+        Some(line) => line.as_usize().ok_or_else(|| "`line` must be a positive integer".to_string()),
+    }
+}
+
+/// The `span` of the parts, serialized as a `[start, end]` pair like in the JSON model.
+#[cfg(feature = "serde")]
+mod span_pair {
+    use std::ops::Range;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(span: &Range<usize>, serializer: S) -> Result<S::Ok, S::Error> {
+        [span.start, span.end].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Range<usize>, D::Error> {
+        match <[usize; 2]>::deserialize(deserializer)? {
+            [start, end] if start <= end => Ok(start..end),
+            _ => Err(de::Error::custom("`span` must be a range of bytes")),
+        }
+    }
+}
+
+/// The bytes of the construct in the Dofile, none when they are left out.
+fn span(json: &Json) -> Result<Range<usize>, String> {
+    match array(json, "span")? {
+        [] if json.get("span").is_none() => Ok(0..0),
+        [start, end] => match (start.as_usize(), end.as_usize()) {
+            (Some(start), Some(end)) if start <= end => Ok(start..end),
+            _ => Err("`span` must be a range of bytes".to_string()),
+        },
+        _ => Err("`span` must be a `[start, end]` pair".to_string()),
+    }
+}
+
+fn strings(json: &Json, key: &str) -> Result<Vec<String>, String> {
+    array(json, key)?.iter()
+        .map(|value| value.as_str().map(str::to_string).ok_or_else(|| format!("`{}` must only contain strings", key)))
+        .collect()
+}
+
+/// The array `key`, empty when it is left out.
+fn array<'a>(json: &'a Json, key: &str) -> Result<&'a [Json], String> {
+    match json.get(key) {
+        None => Ok(&[]),
+        Some(value) => value.as_array().ok_or_else(|| format!("`{}` must be an array", key)),
+    }
+}
+
+/// The items of the array `key`, each converted, an error naming the one that isn't valid.
+fn items<'a, T: TryFrom<&'a Json, Error = String>>(json: &'a Json, key: &str) -> Result<Vec<T>, String> {
+    array(json, key)?.iter()
+        .enumerate()
+        .map(|(index, item)| T::try_from(item).map_err(|err| format!("{}[{}]: {}", key, index, err)))
+        .collect()
+}
+
+impl TryFrom<&Json> for Include {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Include, String> {
+        Ok(Include {
+            line: line(json)?,
+            span: span(json)?,
+            path: string(json, "path")?,
+            optional: boolean(json, "optional")?,
+        })
+    }
+}
+
+impl TryFrom<&Json> for Use {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Use, String> {
+        Ok(Use {
+            line: line(json)?,
+            span: span(json)?,
+            path: string(json, "path")?,
+            namespace: string(json, "namespace")?,
+        })
+    }
+}
+
+impl TryFrom<&Json> for Goal {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Goal, String> {
+        Ok(Goal {
+            line: line(json)?,
+            span: span(json)?,
+            name: string(json, "name")?,
+        })
+    }
+}
+
+impl TryFrom<&Json> for Shell {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Shell, String> {
+        Ok(Shell {
+            line: line(json)?,
+            span: span(json)?,
+            name: string(json, "name")?,
+            flags: strings(json, "flags")?,
+        })
+    }
+}
+
+impl TryFrom<&Json> for Raw {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Raw, String> {
+        Ok(Raw {
+            line: line(json)?,
+            span: span(json)?,
+            text: string(json, "text")?,
+        })
+    }
+}
+
+impl TryFrom<&Json> for Variable {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Variable, String> {
+        let operator = string(json, "operator")?;
+        let assignment = Assignment::ALL.into_iter().find(|assignment| assignment.operator() == operator)
+            .ok_or_else(|| format!("unknown operator `{}`", operator))?;
+        Ok(Variable {
+            line: line(json)?,
+            span: span(json)?,
+            name: string(json, "name")?,
+            assignment,
+            value: string(json, "value")?,
+            exported: boolean(json, "exported")?,
+        })
+    }
+}
+
+impl TryFrom<&Json> for Command {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Command, String> {
+        Ok(Command {
+            line: line(json)?,
+            span: span(json)?,
+            name: string(json, "name")?,
+            file: boolean(json, "file")?,
+            description: string(json, "description")?,
+            prior_commands: strings(json, "prior_commands")?,
+            requires: strings(json, "requires")?,
+            group: optional_string(json, "group")?,
+            deprecated: optional_string(json, "deprecated")?,
+            variables: items(json, "variables")?,
+            oneshell: boolean(json, "oneshell")?,
+            notparallel: boolean(json, "notparallel")?,
+            instructions: strings(json, "instructions")?,
+        })
+    }
+}
+
+impl TryFrom<&Json> for Dofile {
+    type Error = String;
+
+    fn try_from(json: &Json) -> Result<Dofile, String> {
+        Ok(Dofile {
+            includes: items(json, "includes")?,
+            dotenvs: items(json, "dotenvs")?,
+            uses: items(json, "uses")?,
+            raws: items(json, "raws")?,
+            default: match json.get("default") {
+                None | Some(Json::Null) => None,
+                Some(goal) => Some(Goal::try_from(goal)?),
+            },
+            shell: match json.get("shell") {
+                None | Some(Json::Null) => None,
+                Some(shell) => Some(Shell::try_from(shell)?),
+            },
+            oneshell: boolean(json, "oneshell")?,
+            notparallel: boolean(json, "notparallel")?,
+            variables: items(json, "variables")?,
+            commands: items(json, "commands")?,
+        })
+    }
+}
+
+impl Dofile {
+    /// Checks a model that wasn't parsed from a Dofile, such as one read from JSON, as the parser
+    /// would: the names of its targets and variables, its targets defined twice or without
+    /// instructions, then its prerequisites, cycles and default target.
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(err) = self.target_problems().first() {
+            return Err(err.to_string());
+        }
+        let variables = self.variables.iter().chain(self.commands.iter().flat_map(|cmd| &cmd.variables));
+        if let Some(variable) = variables.into_iter().find(|variable| !is_variable_name(&variable.name)) {
+            return Err(format!("variable name `{}` is not valid", variable.name));
+        }
+        match self.relation_problems().first() {
+            Some(err) => Err(err.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Every error [`Dofile::check`] would stop at among the targets and their relations, e.g. to
+    /// lint a structured Dofile. Their spans point into no content.
+    pub fn problems(&self) -> Vec<ParseError> {
+        let mut problems = self.target_problems();
+        problems.extend(self.relation_problems());
+        problems
+    }
+
+    fn target_problems(&self) -> Vec<ParseError> {
+        let error = |kind: ErrorKind| ParseError::new("", 0..0, kind, Vec::new());
+        let mut problems = Vec::new();
+        for (index, cmd) in self.commands.iter().enumerate() {
+            // a single `%` makes a pattern rule
+            let checked = if cmd.name.matches('%').count() == 1 { cmd.name.replace('%', "_") } else { cmd.name.clone() };
+            if cmd.name.is_empty() {
+                problems.push(error(ErrorKind::EmptyName));
+            } else if let Some((_, c)) = invalid_character(&checked) {
+                problems.push(error(ErrorKind::InvalidName(cmd.name.clone(), c)));
+            }
+            if let Some(first) = self.commands[..index].iter().find(|first| first.name == cmd.name) {
+                problems.push(error(ErrorKind::DuplicateTarget(cmd.name.clone(), first.line)));
+            }
+            if cmd.instructions.is_empty() {
+                problems.push(error(ErrorKind::EmptyRecipe(cmd.name.clone())));
+            }
+        }
+        problems
+    }
+
+    /// The prerequisites, cycles and default target, checked as the parser would.
+    fn relation_problems(&self) -> Vec<ParseError> {
+        // the spans point into no content
+        let mut model = self.clone();
+        for cmd in &mut model.commands {
+            cmd.span = 0..0;
+        }
+        if let Some(goal) = &mut model.default {
+            goal.span = 0..0;
+        }
+        validate::validate("", &model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instructions(content: &str) -> Vec<String> {
+        parse_dofile(content).unwrap().commands.remove(0).instructions
+    }
+
+    const DOFILE: &str = "shell bash -eu\ndefault build\ninclude? local.mk\nexport PROFILE ?= debug\n\n```make\nVERSION := 1\n```\n\n[build] lib\n# Builds\n# the project\n#@ group: dev\nvars: MODE = fast\n@echo $(PROFILE) $(MODE)\n\n[file: lib]\n# Lib\nrequires: cc\ncc -o lib lib.c\n";
+
+    #[test]
+    fn the_model_reads_back_from_json_and_yaml() {
+        let dofile = parse_dofile(DOFILE).unwrap();
+        let json = Json::from(&dofile);
+        assert_eq!(Dofile::try_from(&Json::parse(&json.pretty()).unwrap()).unwrap(), dofile);
+        // a JSON document is YAML as well
+        let yaml = crate::yaml::parse(&json.to_string()).unwrap();
+        assert_eq!(yaml, json);
+        assert_eq!(Dofile::try_from(&yaml).unwrap(), dofile);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_reads_the_model_like_the_json_one() {
+        use serde::de::value::{Error, MapDeserializer, SeqDeserializer, StrDeserializer};
+        use serde::Deserialize;
+        // the line and span left out, as the programs building the model may
+        let goal = Goal::deserialize(MapDeserializer::<_, Error>::new([("name", "build")].into_iter())).unwrap();
+        assert_eq!(goal, Goal { line: 0, span: 0..0, name: "build".to_string() });
+        assert_eq!(Assignment::deserialize(StrDeserializer::<Error>::new("?=")), Ok(Assignment::Conditional));
+        assert!(Assignment::deserialize(StrDeserializer::<Error>::new("Conditional")).is_err());
+        assert_eq!(span_pair::deserialize(SeqDeserializer::<_, Error>::new([3usize, 7].into_iter())), Ok(3..7));
+        assert!(span_pair::deserialize(SeqDeserializer::<_, Error>::new([7usize, 3].into_iter())).is_err());
+    }
+
+    #[test]
+    fn only_the_declared_variables_stay_make_references() {
+        let content = "[build]\n# Builds\necho $(PROFILE) ${PROFILE} $(date) ${HOME} $HOME $$1 $(MAKE)\n\nPROFILE = debug\n";
+        assert_eq!(instructions(content), ["echo $(PROFILE) ${PROFILE} $$(date) $${HOME} $$HOME $$1 $(MAKE)"]);
+    }
+
+    #[test]
+    fn a_shell_assignment_in_an_instruction_declares_nothing() {
+        let content = "[build]\n# Builds\nDIR=$(mktemp -d) && cd ${DIR}\n";
+        assert_eq!(instructions(content), ["DIR=$$(mktemp -d) && cd $${DIR}"]);
+    }
+
+    #[test]
+    fn the_variables_of_the_target_are_declared() {
+        let content = "[build]\n# Builds\nvars: MODE = fast\necho $(MODE) $(OTHER:.c=.o) $(MODE:.c=.o)\n";
+        assert_eq!(instructions(content), ["echo $(MODE) $$(OTHER:.c=.o) $(MODE:.c=.o)"]);
+    }
+}
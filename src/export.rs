@@ -0,0 +1,1114 @@
+//! Renders the Dofile for other task runners than make, for teams moving between them.
+//!
+//! The instructions are written for make, so `$(NAME)` references and `$$` escapes are
+//! translated for each format, as are the `@os:` and `@arch:` prefixes.
+
+use crate::dofile::{platform_variable, restrictions};
+use crate::json::Json;
+use crate::{Assignment, Command, Dofile};
+
+/// Whether the Dofile restricts some instructions to an operating system, or to an architecture.
+fn uses_platforms(dofile: &Dofile) -> (bool, bool) {
+    let names = dofile.commands.iter().flat_map(|cmd| &cmd.instructions).flat_map(|instruction| restrictions(instruction).0).collect::<Vec<_>>();
+    (names.iter().any(|name| platform_variable(name) == "DOMAKE_OS"), names.iter().any(|name| platform_variable(name) == "DOMAKE_ARCH"))
+}
+
+/// The silent commands checking the `requires:` and warning of the `deprecated:` of the target,
+/// run before its instructions.
+fn checks(cmd: &Command) -> Vec<String> {
+    let mut commands = Vec::new();
+    if let Some(advice) = &cmd.deprecated {
+        let advice = if advice.is_empty() { String::new() } else { format!(", {}", advice) };
+        let warning = format!("Warning: target `{}` is deprecated{}", cmd.name, advice).replace('\'', "'\\''").replace('$', "$$");
+        commands.push(format!("@echo '{}' >&2", warning));
+    }
+    for tool in &cmd.requires {
+        commands.push(format!("@command -v {} >/dev/null 2>&1 || {{ echo '{}: `{}` is required but was not found in the PATH' >&2; exit 1; }}", tool, cmd.name, tool));
+    }
+    commands
+}
+
+/// The instructions of the target as make would run them, one shell command each, with their
+/// `@` and `-` prefixes first, after its [`checks`]; the platform restrictions become tests of
+/// `$(DOMAKE_OS)` and `$(DOMAKE_ARCH)`. The comment instructions are left out, as joined to
+/// the other commands they would hide them.
+fn commands(cmd: &Command) -> Vec<String> {
+    let mut commands = checks(cmd);
+    for instruction in &cmd.instructions {
+        let (names, instruction) = restrictions(instruction);
+        let command = instruction.trim_start_matches(['@', '-', '+']);
+        if command.starts_with('#') {
+            continue;
+        }
+        // `+` only matters to `make -n`
+        let prefix = instruction[..instruction.len() - command.len()].replace('+', "");
+        let mut command = command.to_string();
+        for name in names.iter().rev() {
+            command = format!("if [ \"$({})\" = {} ]; then {}; fi", platform_variable(name), name, command);
+        }
+        commands.push(format!("{}{}", prefix, command));
+    }
+    commands
+}
+
+/// Translates what make would expand in `text`: `$$` becomes `$`, `$(NAME)` and `${NAME}` become
+/// `reference(NAME)`, and `$@`, `$<` and `$^` become the target and its prerequisites. The make
+/// functions, like `$(shell ...)`, are left as they are.
+fn unmake(text: &str, cmd: Option<&Command>, reference: &dyn Fn(&str) -> String) -> String {
+    let mut buffer = String::new();
+    let mut rest = text;
+    while let Some(index) = rest.find('$') {
+        buffer.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        let mut chars = rest.chars();
+        match (chars.next(), cmd) {
+            (Some('$'), _) => {
+                buffer.push('$');
+                rest = &rest[1..];
+            }
+            (Some('@'), Some(cmd)) => {
+                buffer.push_str(&cmd.name);
+                rest = &rest[1..];
+            }
+            (Some('<'), Some(cmd)) => {
+                buffer.push_str(cmd.prior_commands.first().map(String::as_str).unwrap_or_default());
+                rest = &rest[1..];
+            }
+            (Some('^'), Some(cmd)) => {
+                buffer.push_str(&cmd.prior_commands.join(" "));
+                rest = &rest[1..];
+            }
+            (Some(open @ ('(' | '{')), _) => {
+                let close = if open == '(' { ')' } else { '}' };
+                let name = rest[1..].split_once(close).map(|(name, _)| name)
+                    .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+                match name {
+                    Some(name) => {
+                        buffer.push_str(&reference(name));
+                        rest = &rest[name.len() + 2..];
+                    }
+                    None => buffer.push('$'),
+                }
+            }
+            _ => buffer.push('$'),
+        }
+    }
+    buffer.push_str(rest);
+    buffer
+}
+
+/// The parts of a make variable value: its text and the variables it references, in order.
+enum Part {
+    Text(String),
+    Reference(String),
+}
+
+fn parts(value: &str) -> Vec<Part> {
+    // the references are marked with a character that cannot be in the value
+    let marked = unmake(value, None, &|name| format!("\u{0}{}\u{0}", name));
+    marked.split('\u{0}').enumerate().filter(|(_, part)| !part.is_empty())
+        .map(|(index, part)| if index % 2 == 1 { Part::Reference(part.to_string()) } else { Part::Text(part.to_string()) })
+        .collect()
+}
+
+/// The name of a target as a just recipe, which only allows letters, digits, `-` and `_`.
+fn just_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' }).collect()
+}
+
+/// A just string literal.
+fn just_string(text: &str) -> String {
+    if text.contains(['\'', '\n']) {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+    } else {
+        format!("'{}'", text)
+    }
+}
+
+/// A make variable value as a just expression, concatenating its text and the variables it references.
+fn just_expression(value: &str) -> String {
+    let parts = parts(value).into_iter().map(|part| match part {
+        Part::Text(text) => just_string(&text),
+        Part::Reference(name) => name,
+    }).collect::<Vec<_>>();
+    if parts.is_empty() { "''".to_string() } else { parts.join(" + ") }
+}
+
+/// Renders the Dofile as a `justfile`: each target becomes a recipe with its prerequisites as
+/// dependencies and its description as doc comment, the variables become just variables, and the
+/// `vars:` of a target become parameters of its recipe with their value as default.
+///
+/// File targets and pattern rules have no equivalent in just and are left out, as are the
+/// includes and raw blocks, which are Makefile syntax.
+pub fn justfile(dofile: &Dofile) -> String {
+    let mut buffer = String::from("# This justfile was done using 'domake'\n\n");
+    if let Some(shell) = &dofile.shell {
+        let words = [shell.name.as_str()].into_iter().chain(shell.arguments()).map(|word| format!("\"{}\"", word)).collect::<Vec<_>>();
+        buffer.push_str(format!("set shell := [{}]\n\n", words.join(", ")).as_str());
+    }
+
+    let (os, arch) = uses_platforms(dofile);
+    if os {
+        buffer.push_str("DOMAKE_OS := if os() == \"macos\" { \"macos\" } else if os() == \"windows\" { \"windows\" } else { \"linux\" }\n");
+    }
+    if arch {
+        buffer.push_str("DOMAKE_ARCH := if arch() == \"aarch64\" { \"arm64\" } else { \"amd64\" }\n");
+    }
+
+    // just has a single assignment per variable, so those of make are folded into it
+    let mut variables: Vec<(&str, String, bool)> = Vec::new();
+    for variable in &dofile.variables {
+        let position = variables.iter().position(|(name, _, _)| *name == variable.name);
+        let expression = just_expression(&variable.value);
+        match (variable.assignment, position) {
+            (Assignment::Conditional, Some(_)) => {}
+            (Assignment::Conditional, None) if parts(&variable.value).iter().all(|part| matches!(part, Part::Text(_))) => {
+                let expression = format!("env_var_or_default('{}', {})", variable.name, expression);
+                variables.push((&variable.name, expression, variable.exported));
+            }
+            (Assignment::Append, Some(index)) => variables[index].1 = format!("{} + ' ' + {}", variables[index].1, expression),
+            (_, Some(index)) => variables[index].1 = expression,
+            (_, None) => variables.push((&variable.name, expression, variable.exported)),
+        }
+        if let Some(index) = position {
+            variables[index].2 |= variable.exported;
+        }
+    }
+    for (name, expression, exported) in &variables {
+        buffer.push_str(format!("{}{} := {}\n", if *exported { "export " } else { "" }, name, expression).as_str());
+    }
+    if os || arch || !variables.is_empty() {
+        buffer.push('\n');
+    }
+
+    for include in &dofile.includes {
+        buffer.push_str(format!("# left out, as it is a Makefile: include {}\n", include.path).as_str());
+    }
+    if !dofile.raws.is_empty() {
+        buffer.push_str(format!("# left out, as they are Makefile syntax: {} raw block(s)\n", dofile.raws.len()).as_str());
+    }
+
+    let exported = dofile.commands.iter().filter(|cmd| !cmd.file && !cmd.is_pattern()).collect::<Vec<_>>();
+    for cmd in dofile.commands.iter().filter(|cmd| cmd.file || cmd.is_pattern()) {
+        buffer.push_str(format!("# left out, as just recipes cannot make files: {}\n", cmd.name).as_str());
+    }
+    if dofile.includes.len() + dofile.raws.len() + dofile.commands.len() > exported.len() {
+        buffer.push('\n');
+    }
+
+    // the first recipe is the default one, like the first target of a Makefile
+    match &dofile.default {
+        Some(goal) => buffer.push_str(format!("# runs `{}`\n_default: {}\n\n", goal.name, just_name(&goal.name)).as_str()),
+        None => buffer.push_str("# lists the recipes\n_default:\n    @just --list\n\n"),
+    }
+
+    for cmd in exported {
+        if !cmd.summary().is_empty() {
+            buffer.push_str(format!("# {}\n", cmd.summary()).as_str());
+        }
+        if let Some(group) = &cmd.group {
+            buffer.push_str(format!("[group({})]\n", just_string(group)).as_str());
+        }
+        let parameters = cmd.variables.iter()
+            .map(|variable| format!(" {}{}={}", if variable.exported { "$" } else { "" }, variable.name, just_expression(&variable.value)))
+            .collect::<String>();
+        let dependencies = cmd.prior_commands.iter()
+            .filter(|prior| dofile.commands.iter().any(|other| &other.name == *prior && !other.file && !other.is_pattern()))
+            .map(|prior| format!(" {}", just_name(prior)))
+            .collect::<String>();
+        buffer.push_str(format!("{}{}:{}\n", just_name(&cmd.name), parameters, dependencies).as_str());
+
+        let reference = |name: &str| format!("{{{{{}}}}}", name);
+        // a literal `{{` is written `{{{{` in just
+        let unmake = |command: &str| unmake(&command.replace("{{", "{{{{"), Some(cmd), &reference).replace('\n', "\n    ");
+        if cmd.oneshell {
+            // a script recipe runs its lines in a single shell
+            let program = dofile.shell.as_ref().map(|shell| shell.name.as_str()).unwrap_or("sh");
+            let (_, commands) = cmd.oneshell_script();
+            buffer.push_str(format!("    #!/usr/bin/env {}\n", program).as_str());
+            let checks = checks(cmd).into_iter().map(|check| check.trim_start_matches('@').to_string());
+            for command in checks.chain(commands) {
+                buffer.push_str(format!("    {}\n", unmake(&command)).as_str());
+            }
+        } else {
+            for command in commands(cmd) {
+                buffer.push_str(format!("    {}\n", unmake(&command)).as_str());
+            }
+        }
+        buffer.push('\n');
+    }
+    buffer.truncate(buffer.trim_end().len());
+    buffer.push('\n');
+    buffer
+}
+
+/// A YAML string, quoted so that it is never read as another type.
+fn yaml_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// A YAML scalar for a command, as a literal block when it has several lines.
+fn yaml_block(text: &str, indent: usize) -> String {
+    if !text.contains('\n') {
+        return yaml_string(text);
+    }
+    let padding = " ".repeat(indent);
+    format!("|\n{}{}", padding, text.replace('\n', &format!("\n{}", padding)))
+}
+
+/// The operating system or architecture of an `@os:` or `@arch:` prefix, as named by Task.
+fn task_platform(name: &str) -> &str {
+    if name == "macos" { "darwin" } else { name }
+}
+
+/// Renders the Dofile as a `Taskfile.yml` for Task (version 3): each target becomes a task with
+/// its description as `desc`, its prerequisites as `deps` and its `vars:` as `vars`; the platform
+/// prefixes become `platforms`, and `@` and `-` become `silent` and `ignore_error`.
+///
+/// A file target becomes a task that `generates` its file from its `sources`, like make; the
+/// pattern rules, includes and raw blocks are Makefile syntax and are left out.
+pub fn taskfile(dofile: &Dofile) -> String {
+    let mut buffer = String::from("# This Taskfile was done using 'domake'\nversion: '3'\n");
+    if let Some(shell) = &dofile.shell {
+        buffer.push_str(format!("# Task runs the commands with its own shell instead of `{}`\n", shell.name).as_str());
+    }
+    for include in &dofile.includes {
+        buffer.push_str(format!("# left out, as it is a Makefile: include {}\n", include.path).as_str());
+    }
+    if !dofile.raws.is_empty() {
+        buffer.push_str(format!("# left out, as they are Makefile syntax: {} raw block(s)\n", dofile.raws.len()).as_str());
+    }
+    for cmd in dofile.commands.iter().filter(|cmd| cmd.is_pattern()) {
+        buffer.push_str(format!("# left out, as Task has no pattern rules: {}\n", cmd.name).as_str());
+    }
+
+    // a literal `{{` would start a template
+    let reference = |name: &str| format!("{{{{.{}}}}}", name);
+    let unmake = |text: &str, cmd: Option<&Command>| unmake(&text.replace("{{", "{{\"{{\"}}"), cmd, &reference);
+
+    let (os, arch) = uses_platforms(dofile);
+    // a key is defined once in YAML, so the assignments of make are folded into it
+    let mut folded: Vec<(&str, String, bool)> = Vec::new();
+    for variable in &dofile.variables {
+        let position = folded.iter().position(|(name, _, _)| *name == variable.name);
+        let value = unmake(&variable.value, None);
+        match (variable.assignment, position) {
+            (Assignment::Conditional, Some(_)) => {}
+            // the environment is the only value it may already have
+            (Assignment::Conditional, None) => {
+                let value = format!("{{{{.{} | default {:?}}}}}", variable.name, value);
+                folded.push((&variable.name, value, variable.exported));
+            }
+            (Assignment::Append, Some(index)) => folded[index].1 = format!("{} {}", folded[index].1, value),
+            (_, Some(index)) => folded[index].1 = value,
+            (_, None) => folded.push((&variable.name, value, variable.exported)),
+        }
+        if let Some(index) = position {
+            folded[index].2 |= variable.exported;
+        }
+    }
+    let (variables, env) = folded.into_iter().partition::<Vec<_>, _>(|(_, _, exported)| !exported);
+    for (key, variables) in [("vars", variables), ("env", env)] {
+        if variables.is_empty() && !(key == "vars" && (os || arch)) {
+            continue;
+        }
+        buffer.push_str(format!("\n{}:\n", key).as_str());
+        if key == "vars" && os {
+            buffer.push_str("  DOMAKE_OS: '{{if eq OS \"darwin\"}}macos{{else}}{{OS}}{{end}}'\n");
+        }
+        if key == "vars" && arch {
+            buffer.push_str("  DOMAKE_ARCH: '{{if eq ARCH \"arm64\"}}arm64{{else}}amd64{{end}}'\n");
+        }
+        for (name, value, _) in variables {
+            buffer.push_str(format!("  {}: {}\n", name, yaml_string(&value)).as_str());
+        }
+    }
+
+    buffer.push_str("\ntasks:\n");
+    if !dofile.commands.iter().any(|cmd| cmd.name == "default") {
+        match &dofile.default {
+            Some(goal) => buffer.push_str(format!("  default:\n    cmds:\n      - task: {}\n", yaml_string(&goal.name)).as_str()),
+            None => buffer.push_str("  default:\n    cmds:\n      - task --list\n    silent: true\n"),
+        }
+    }
+    let is_task = |name: &str| dofile.commands.iter().any(|cmd| cmd.name == name && !cmd.is_pattern());
+    for cmd in dofile.commands.iter().filter(|cmd| !cmd.is_pattern()) {
+        buffer.push_str(format!("\n  {}:\n", yaml_string(&cmd.name)).as_str());
+        if !cmd.summary().is_empty() {
+            buffer.push_str(format!("    desc: {}\n", yaml_string(cmd.summary())).as_str());
+        }
+        if cmd.description.lines().count() > 1 {
+            buffer.push_str(format!("    summary: {}\n", yaml_block(&cmd.description, 6)).as_str());
+        }
+        if cmd.is_hidden() {
+            buffer.push_str("    internal: true\n");
+        }
+        let (tasks, files) = cmd.prior_commands.iter().partition::<Vec<_>, _>(|prior| is_task(prior));
+        // Task runs the `deps` in parallel, unlike the prerequisites of a `#@ notparallel` target
+        if !tasks.is_empty() && !cmd.notparallel {
+            buffer.push_str("    deps:\n");
+            for prior in &tasks {
+                buffer.push_str(format!("      - {}\n", yaml_string(prior)).as_str());
+            }
+        }
+        if cmd.file {
+            buffer.push_str("    method: timestamp\n");
+            if !files.is_empty() {
+                buffer.push_str("    sources:\n");
+                for prior in &files {
+                    buffer.push_str(format!("      - {}\n", yaml_string(prior)).as_str());
+                }
+            }
+            buffer.push_str(format!("    generates:\n      - {}\n", yaml_string(&cmd.name)).as_str());
+        }
+        for (key, exported) in [("vars", false), ("env", true)] {
+            let variables = cmd.variables.iter().filter(|variable| variable.exported == exported).collect::<Vec<_>>();
+            if !variables.is_empty() {
+                buffer.push_str(format!("    {}:\n", key).as_str());
+                for variable in variables {
+                    buffer.push_str(format!("      {}: {}\n", variable.name, yaml_string(&unmake(&variable.value, None))).as_str());
+                }
+            }
+        }
+        if !cmd.requires.is_empty() {
+            buffer.push_str("    preconditions:\n");
+            for tool in &cmd.requires {
+                buffer.push_str(format!("      - sh: {}\n", yaml_string(&format!("command -v {}", tool))).as_str());
+                buffer.push_str(format!("        msg: {}\n", yaml_string(&format!("`{}` is required but was not found in the PATH", tool))).as_str());
+            }
+        }
+
+        buffer.push_str("    cmds:\n");
+        if cmd.notparallel {
+            for prior in &tasks {
+                buffer.push_str(format!("      - task: {}\n", yaml_string(prior)).as_str());
+            }
+        }
+        if let Some(advice) = &cmd.deprecated {
+            let advice = if advice.is_empty() { String::new() } else { format!(", {}", advice) };
+            let warning = format!("Warning: target `{}` is deprecated{}", cmd.name, advice).replace('\'', "'\\''");
+            buffer.push_str(format!("      - cmd: {}\n        silent: true\n", yaml_string(&format!("echo '{}' >&2", unmake(&warning, None)))).as_str());
+        }
+        if cmd.oneshell {
+            let (prefix, commands) = cmd.oneshell_script();
+            buffer.push_str(format!("      - cmd: {}\n", yaml_block(&unmake(&commands.join("\n"), Some(cmd)), 10)).as_str());
+            if prefix.contains('@') {
+                buffer.push_str("        silent: true\n");
+            }
+            if prefix.contains('-') {
+                buffer.push_str("        ignore_error: true\n");
+            }
+            continue;
+        }
+        for instruction in &cmd.instructions {
+            let (names, instruction) = restrictions(instruction);
+            let command = instruction.trim_start_matches(['@', '-', '+']);
+            let prefix = &instruction[..instruction.len() - command.len()];
+            buffer.push_str(format!("      - cmd: {}\n", yaml_block(&unmake(command, Some(cmd)), 10)).as_str());
+            if prefix.contains('@') {
+                buffer.push_str("        silent: true\n");
+            }
+            if prefix.contains('-') {
+                buffer.push_str("        ignore_error: true\n");
+            }
+            if !names.is_empty() {
+                let platforms = names.iter().map(|name| task_platform(name)).collect::<Vec<_>>().join("/");
+                buffer.push_str(format!("        platforms: [{}]\n", platforms).as_str());
+            }
+        }
+    }
+    buffer
+}
+
+/// A path of a `build` statement, where spaces and colons would separate the paths.
+fn ninja_path(path: &str) -> String {
+    path.replace('$', "$$").replace(' ', "$ ").replace(':', "$:")
+}
+
+/// Translates what make would expand in `text` for Ninja, which escapes `$` the same way but
+/// writes the references `${NAME}`.
+fn ninja_text(text: &str, cmd: Option<&Command>) -> String {
+    unmake(&text.replace("$$", "$$$$"), cmd, &|name| format!("${{{}}}", name))
+}
+
+/// Renders the targets of the Dofile that declare their file (`[file: out.txt]`) as a
+/// `build.ninja`: each one gets a rule running its instructions, built from its prerequisites.
+///
+/// Ninja only builds files, so the other targets, the pattern rules, includes and raw blocks are
+/// left out, as are the prerequisites on the other targets.
+pub fn ninja(dofile: &Dofile) -> String {
+    let mut buffer = String::from("# This build.ninja was done using 'domake'\nninja_required_version = 1.3\n\n");
+    if let Some(shell) = &dofile.shell {
+        buffer.push_str(format!("# Ninja runs the commands with `/bin/sh` instead of `{}`\n", shell.name).as_str());
+    }
+    for include in &dofile.includes {
+        buffer.push_str(format!("# left out, as it is a Makefile: include {}\n", include.path).as_str());
+    }
+    if !dofile.raws.is_empty() {
+        buffer.push_str(format!("# left out, as they are Makefile syntax: {} raw block(s)\n", dofile.raws.len()).as_str());
+    }
+    for cmd in &dofile.commands {
+        if cmd.is_pattern() {
+            buffer.push_str(format!("# left out, as Ninja has no pattern rules: {}\n", cmd.name).as_str());
+        } else if !cmd.file {
+            buffer.push_str(format!("# left out, as Ninja only builds files: {}\n", cmd.name).as_str());
+        }
+    }
+    if dofile.variables.iter().any(|variable| variable.exported) {
+        buffer.push_str("# the exported variables are not in the environment of the commands\n");
+    }
+
+    // the platform is chosen by the shell, when the command runs
+    let (os, arch) = uses_platforms(dofile);
+    if os {
+        buffer.push_str("DOMAKE_OS = $$(case $$(uname -s) in Darwin) echo macos;; *) echo linux;; esac)\n");
+    }
+    if arch {
+        buffer.push_str("DOMAKE_ARCH = $$(case $$(uname -m) in arm64|aarch64) echo arm64;; *) echo amd64;; esac)\n");
+    }
+    let mut defined: Vec<&str> = Vec::new();
+    for variable in &dofile.variables {
+        let value = ninja_text(&variable.value, None);
+        match variable.assignment {
+            Assignment::Conditional if defined.contains(&variable.name.as_str()) => continue,
+            Assignment::Append if defined.contains(&variable.name.as_str()) => {
+                buffer.push_str(format!("{} = ${{{}}} {}\n", variable.name, variable.name, value).as_str());
+            }
+            _ => buffer.push_str(format!("{} = {}\n", variable.name, value).as_str()),
+        }
+        defined.push(&variable.name);
+    }
+
+    let is_built = |name: &str| dofile.commands.iter().any(|cmd| cmd.name == name && cmd.file);
+    let mut rules: Vec<String> = Vec::new();
+    for cmd in dofile.commands.iter().filter(|cmd| cmd.file && !cmd.is_pattern()) {
+        let inputs = cmd.prior_commands.iter()
+            .filter(|prior| !dofile.commands.iter().any(|other| other.name == **prior) || is_built(prior))
+            .map(|prior| ninja_path(prior))
+            .collect::<Vec<_>>();
+        let mut rule = cmd.name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect::<String>();
+        while rules.contains(&rule) {
+            rule.push('_');
+        }
+        rules.push(rule.clone());
+        let command = if cmd.oneshell {
+            let (_, commands) = cmd.oneshell_script();
+            let checks = checks(cmd).into_iter().map(|check| check.trim_start_matches('@').to_string());
+            checks.chain(commands).collect::<Vec<_>>().join("; ")
+        } else {
+            commands(cmd).iter()
+                .map(|command| {
+                    let line = command.trim_start_matches(['@', '-']);
+                    if command[..command.len() - line.len()].contains('-') { format!("{{ {{ {}; }} || true; }}", line) } else { line.to_string() }
+                })
+                .collect::<Vec<_>>()
+                .join(" && ")
+        };
+        let description = if cmd.summary().is_empty() { cmd.name.as_str() } else { cmd.summary() };
+        buffer.push_str(format!("\nrule {}\n  command = {}\n  description = {}\n", rule, ninja_text(&command, Some(cmd)), description.replace('$', "$$")).as_str());
+        let inputs = if inputs.is_empty() { String::new() } else { format!(" {}", inputs.join(" ")) };
+        buffer.push_str(format!("build {}: {}{}\n", ninja_path(&cmd.name), rule, inputs).as_str());
+    }
+
+    if let Some(goal) = dofile.default.as_ref().filter(|goal| is_built(&goal.name)) {
+        buffer.push_str(format!("\ndefault {}\n", ninja_path(&goal.name)).as_str());
+    }
+    buffer
+}
+
+/// A shell word of `text`, single-quoted so that nothing in it is expanded.
+fn sh_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// A name usable in a shell function name, with the other characters replaced by `_`.
+fn function_name(name: &str, taken: &mut Vec<String>) -> String {
+    let mut function = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect::<String>();
+    while taken.contains(&function) {
+        function.push('_');
+    }
+    taken.push(function.clone());
+    function
+}
+
+/// Renders the Dofile as a standalone `do.sh`, run as `./do.sh [TARGET]...` where make isn't
+/// installed: each target becomes a function, dispatched by its name, which first runs its
+/// prerequisites, once each, so that they run in topological order like with make.
+///
+/// Like make, each instruction runs in its own subshell and a file target is only run when its
+/// file is older than one of its prerequisites; the pattern rules, includes and raw blocks are
+/// Makefile syntax and are left out.
+pub fn shell_script(dofile: &Dofile) -> String {
+    let (program, options) = match &dofile.shell {
+        Some(shell) => {
+            let options = shell.arguments().into_iter().filter(|argument| *argument != "-c").collect::<Vec<_>>().join(" ");
+            (shell.name.clone(), if options.is_empty() { "-e".to_string() } else { options })
+        }
+        None => ("sh".to_string(), "-e".to_string()),
+    };
+    let shebang = if program.contains('/') { program } else { format!("/usr/bin/env {}", program) };
+    let mut buffer = format!("#!{}\n# This script was done using 'domake', run `./do.sh --help` for its targets\nset {}\ncd \"$(dirname \"$0\")\"\n\n", shebang, options);
+
+    for include in &dofile.includes {
+        buffer.push_str(format!("# left out, as it is a Makefile: include {}\n", include.path).as_str());
+    }
+    if !dofile.raws.is_empty() {
+        buffer.push_str(format!("# left out, as they are Makefile syntax: {} raw block(s)\n", dofile.raws.len()).as_str());
+    }
+    for cmd in dofile.commands.iter().filter(|cmd| cmd.is_pattern()) {
+        buffer.push_str(format!("# left out, as a script has no pattern rules: {}\n", cmd.name).as_str());
+    }
+
+    let (os, arch) = uses_platforms(dofile);
+    if os {
+        buffer.push_str("case \"$(uname -s)\" in\n    Darwin) DOMAKE_OS=macos ;;\n    MINGW*|MSYS*|CYGWIN*) DOMAKE_OS=windows ;;\n    *) DOMAKE_OS=linux ;;\nesac\n");
+    }
+    if arch {
+        buffer.push_str("case \"$(uname -m)\" in\n    arm64|aarch64) DOMAKE_ARCH=arm64 ;;\n    *) DOMAKE_ARCH=amd64 ;;\nesac\n");
+    }
+    // the values are expanded by the shell, as make would let it do in the instructions
+    let reference = |name: &str| format!("${{{}}}", name);
+    let value = |text: &str| unmake(&text.replace('\\', "\\\\").replace('"', "\\\"").replace('`', "\\`"), None, &reference);
+    for variable in &dofile.variables {
+        match variable.assignment {
+            Assignment::Conditional => buffer.push_str(format!("[ -n \"${{{}+set}}\" ] || {}=\"{}\"\n", variable.name, variable.name, value(&variable.value)).as_str()),
+            Assignment::Append => buffer.push_str(format!("{}=\"${{{}}} {}\"\n", variable.name, variable.name, value(&variable.value)).as_str()),
+            _ => buffer.push_str(format!("{}=\"{}\"\n", variable.name, value(&variable.value)).as_str()),
+        }
+        if variable.exported {
+            buffer.push_str(format!("export {}\n", variable.name).as_str());
+        }
+    }
+
+    let exported = dofile.commands.iter().filter(|cmd| !cmd.is_pattern()).collect::<Vec<_>>();
+    let width = exported.iter().filter(|cmd| !cmd.is_hidden()).map(|cmd| cmd.name.len()).max().unwrap_or_default();
+    buffer.push_str("\nusage() {\n    echo \"Usage: $0 [TARGET]...\"\n    echo \"Targets:\"\n");
+    for cmd in exported.iter().filter(|cmd| !cmd.is_hidden()) {
+        buffer.push_str(format!("    echo {}\n", sh_quote(&format!("  {:width$}  {}", cmd.name, cmd.summary())).trim_end()).as_str());
+    }
+    buffer.push_str("}\n\n");
+    buffer.push_str("# whether the file $1 is missing, or older than one of the other files\noutdated() {\n    [ -e \"$1\" ] || return 0\n    outdated_file=$1\n    shift\n    for prerequisite in \"$@\"; do\n        [ \"$prerequisite\" -nt \"$outdated_file\" ] && return 0\n    done\n    return 1\n}\n");
+
+    let mut taken = Vec::new();
+    let functions = exported.iter().map(|cmd| (cmd, format!("target_{}", function_name(&cmd.name, &mut taken)))).collect::<Vec<_>>();
+    for (cmd, function) in &functions {
+        buffer.push_str(format!("\n# {}\n{}() {{\n", cmd.summary(), function).as_str());
+        let is_target = |name: &str| exported.iter().any(|other| other.name == name);
+        for prior in cmd.prior_commands.iter().filter(|prior| is_target(prior)) {
+            buffer.push_str(format!("    run {}\n", sh_quote(prior)).as_str());
+        }
+        if cmd.file {
+            let files = [&cmd.name].into_iter().chain(&cmd.prior_commands).map(|name| sh_quote(name)).collect::<Vec<_>>();
+            buffer.push_str(format!("    outdated {} || return 0\n", files.join(" ")).as_str());
+        }
+        // the variables of the target are kept to its own instructions, as its prerequisites ran before
+        let indent = if cmd.variables.is_empty() { "    " } else { "        " };
+        if !cmd.variables.is_empty() {
+            buffer.push_str("    (\n");
+            for variable in &cmd.variables {
+                buffer.push_str(format!("{}{}{}=\"{}\"\n", indent, if variable.exported { "export " } else { "" }, variable.name, value(&variable.value)).as_str());
+            }
+        }
+        let mut lines = Vec::new();
+        if cmd.oneshell {
+            let (prefix, commands) = cmd.oneshell_script();
+            let checks = checks(cmd).into_iter().map(|check| check.trim_start_matches('@').to_string());
+            let script = checks.chain(commands).collect::<Vec<_>>().join("\n");
+            lines.push((prefix.contains('@'), prefix.contains('-'), script));
+        } else {
+            for command in commands(cmd) {
+                let line = command.trim_start_matches(['@', '-']);
+                let prefix = &command[..command.len() - line.len()];
+                lines.push((prefix.contains('@'), prefix.contains('-'), line.to_string()));
+            }
+        }
+        for (silent, ignored, command) in lines {
+            let command = unmake(&command, Some(cmd), &reference);
+            // a subshell of nothing but a comment isn't valid
+            if command.lines().all(|line| line.trim().is_empty() || line.trim_start().starts_with('#')) {
+                continue;
+            }
+            if !silent {
+                buffer.push_str(format!("{}echo {}\n", indent, sh_quote(&command)).as_str());
+            }
+            // a comment would hide the closing parenthesis
+            let close = if command.contains('#') { format!("\n{})", indent) } else { ")".to_string() };
+            let command = command.replace('\n', &format!("\n{}", indent));
+            buffer.push_str(format!("{}({}{}{}\n", indent, command, close, if ignored { " || true" } else { "" }).as_str());
+        }
+        if !cmd.variables.is_empty() {
+            buffer.push_str("    )\n");
+        }
+        buffer.push_str("}\n");
+    }
+
+    buffer.push_str("\n# runs the target $1, unless it already ran\nrun() {\n    case \" $DOMAKE_DONE \" in\n        *\" $1 \"*) return 0 ;;\n    esac\n    DOMAKE_DONE=\"$DOMAKE_DONE $1\"\n    case \"$1\" in\n");
+    for (cmd, function) in &functions {
+        buffer.push_str(format!("        {}) {} ;;\n", sh_quote(&cmd.name), function).as_str());
+    }
+    buffer.push_str("        *)\n            echo \"$0: unknown target \\`$1\\`\" >&2\n            usage >&2\n            exit 2\n            ;;\n    esac\n}\n\n");
+
+    buffer.push_str("case \"${1-}\" in\n    -h|--help)\n        usage\n        exit 0\n        ;;\nesac\nDOMAKE_DONE=\n");
+    match &dofile.default {
+        Some(goal) => buffer.push_str(format!("[ $# -gt 0 ] || set -- {}\n", sh_quote(&goal.name)).as_str()),
+        None => buffer.push_str("if [ $# -eq 0 ]; then\n    usage\n    exit 0\nfi\n"),
+    }
+    buffer.push_str("for target in \"$@\"; do\n    run \"$target\"\ndone\n");
+    buffer
+}
+
+/// A PowerShell string of `text`, single-quoted so that nothing in it is expanded.
+fn ps_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// A PowerShell string of what make would expand in `text`, double-quoted so that the references
+/// become `${NAME}` while the `$` escaped with `$$` stay for `Invoke-Expression`.
+fn ps_expandable(text: &str, cmd: Option<&Command>) -> String {
+    let text = text.replace('`', "``").replace('"', "`\"").replace("$$", "`$$");
+    format!("\"{}\"", unmake(&text, cmd, &|name| format!("${{{}}}", name)))
+}
+
+/// Renders the Dofile as a standalone `do.ps1`, run as `.\do.ps1 [TARGET]...` on Windows without
+/// make: each target becomes a function, dispatched by its name, which first runs its
+/// prerequisites, once each, so that they run in topological order like with make.
+///
+/// The instructions are run by `Invoke-Expression` once the variables are expanded, so they must be
+/// PowerShell commands; the pattern rules, includes and raw blocks are Makefile syntax and are left out.
+pub fn powershell_script(dofile: &Dofile) -> String {
+    let mut buffer = String::from("# This script was done using 'domake', run `.\\do.ps1 -Help` for its targets\nparam(\n    [switch]$Help,\n    [Parameter(ValueFromRemainingArguments = $true)]\n    [string[]]$Targets\n)\n$ErrorActionPreference = 'Stop'\nSet-Location $PSScriptRoot\n\n");
+    if let Some(shell) = &dofile.shell {
+        buffer.push_str(format!("# the instructions are run by PowerShell instead of `{}`\n", shell.name).as_str());
+    }
+    for include in &dofile.includes {
+        buffer.push_str(format!("# left out, as it is a Makefile: include {}\n", include.path).as_str());
+    }
+    if !dofile.raws.is_empty() {
+        buffer.push_str(format!("# left out, as they are Makefile syntax: {} raw block(s)\n", dofile.raws.len()).as_str());
+    }
+    for cmd in dofile.commands.iter().filter(|cmd| cmd.is_pattern()) {
+        buffer.push_str(format!("# left out, as a script has no pattern rules: {}\n", cmd.name).as_str());
+    }
+
+    let (os, arch) = uses_platforms(dofile);
+    if os {
+        // `$IsLinux` and `$IsMacOS` are only defined from PowerShell 6
+        buffer.push_str("$DOMAKE_OS = if ($IsMacOS) { 'macos' } elseif ($IsLinux) { 'linux' } else { 'windows' }\n");
+    }
+    if arch {
+        buffer.push_str("$DOMAKE_ARCH = if ([System.Runtime.InteropServices.RuntimeInformation]::OSArchitecture -eq 'Arm64') { 'arm64' } else { 'amd64' }\n");
+    }
+    for variable in &dofile.variables {
+        let value = ps_expandable(&variable.value, None);
+        match variable.assignment {
+            Assignment::Conditional => buffer.push_str(format!("${0} = if ($env:{0}) {{ $env:{0} }} else {{ {1} }}\n", variable.name, value).as_str()),
+            Assignment::Append => buffer.push_str(format!("${0} = \"${{{0}}} \" + {1}\n", variable.name, value).as_str()),
+            _ => buffer.push_str(format!("${} = {}\n", variable.name, value).as_str()),
+        }
+        if variable.exported {
+            buffer.push_str(format!("$env:{0} = ${0}\n", variable.name).as_str());
+        }
+    }
+
+    let exported = dofile.commands.iter().filter(|cmd| !cmd.is_pattern()).collect::<Vec<_>>();
+    let width = exported.iter().filter(|cmd| !cmd.is_hidden()).map(|cmd| cmd.name.len()).max().unwrap_or_default();
+    buffer.push_str("\nfunction Show-Usage {\n    Write-Output 'Usage: .\\do.ps1 [TARGET]...'\n    Write-Output 'Targets:'\n");
+    for cmd in exported.iter().filter(|cmd| !cmd.is_hidden()) {
+        buffer.push_str(format!("    Write-Output {}\n", ps_quote(format!("  {:width$}  {}", cmd.name, cmd.summary()).trim_end())).as_str());
+    }
+    buffer.push_str("}\n\n");
+    buffer.push_str("# whether the file is missing, or older than one of the prerequisites\nfunction Test-Outdated([string]$File, [string[]]$Prerequisites) {\n    if (-not (Test-Path $File)) { return $true }\n    $time = (Get-Item $File).LastWriteTime\n    foreach ($prerequisite in $Prerequisites) {\n        if ((Test-Path $prerequisite) -and (Get-Item $prerequisite).LastWriteTime -gt $time) { return $true }\n    }\n    return $false\n}\n\n");
+    buffer.push_str("# runs the instruction, printed first unless it is silent, and stops at its failure unless it is ignored\nfunction Invoke-Instruction([string]$Command, [switch]$Silent, [switch]$Ignore) {\n    if (-not $Silent) { Write-Output $Command }\n    $global:LASTEXITCODE = 0\n    try {\n        Invoke-Expression $Command\n    } catch {\n        if (-not $Ignore) { throw }\n    }\n    if ($LASTEXITCODE -ne 0 -and -not $Ignore) { throw \"``$Command`` failed with the exit code $LASTEXITCODE\" }\n}\n");
+
+    let mut taken = Vec::new();
+    let functions = exported.iter().map(|cmd| (cmd, format!("Invoke-Target_{}", function_name(&cmd.name, &mut taken)))).collect::<Vec<_>>();
+    for (cmd, function) in &functions {
+        buffer.push_str(format!("\n# {}\nfunction {} {{\n", cmd.summary(), function).as_str());
+        // the variables of the target are seen by the prerequisites it runs, as with make
+        for variable in &cmd.variables {
+            buffer.push_str(format!("    ${} = {}\n", variable.name, ps_expandable(&variable.value, None)).as_str());
+            if variable.exported {
+                buffer.push_str(format!("    $env:{0} = ${0}\n", variable.name).as_str());
+            }
+        }
+        let is_target = |name: &str| exported.iter().any(|other| other.name == name);
+        for prior in cmd.prior_commands.iter().filter(|prior| is_target(prior)) {
+            buffer.push_str(format!("    Invoke-Target {}\n", ps_quote(prior)).as_str());
+        }
+        if cmd.file {
+            let prerequisites = cmd.prior_commands.iter().map(|prior| ps_quote(prior)).collect::<Vec<_>>();
+            buffer.push_str(format!("    if (-not (Test-Outdated {} @({}))) {{ return }}\n", ps_quote(&cmd.name), prerequisites.join(", ")).as_str());
+        }
+        if let Some(advice) = &cmd.deprecated {
+            let advice = if advice.is_empty() { String::new() } else { format!(", {}", advice) };
+            buffer.push_str(format!("    Write-Warning {}\n", ps_quote(&format!("target `{}` is deprecated{}", cmd.name, advice))).as_str());
+        }
+        for tool in &cmd.requires {
+            let message = format!("{}: `{}` is required but was not found in the PATH", cmd.name, tool);
+            buffer.push_str(format!("    if (-not (Get-Command {} -ErrorAction SilentlyContinue)) {{ throw {} }}\n", ps_quote(tool), ps_quote(&message)).as_str());
+        }
+
+        let flags = |prefix: &str| format!("{}{}", if prefix.contains('@') { " -Silent" } else { "" }, if prefix.contains('-') { " -Ignore" } else { "" });
+        if cmd.oneshell {
+            // the instructions run as a single script, with the flags of the first one
+            let mut prefix = "";
+            let mut lines = Vec::new();
+            for (index, instruction) in cmd.instructions.iter().enumerate() {
+                let (names, instruction) = restrictions(instruction);
+                let command = instruction.trim_start_matches(['@', '-', '+']);
+                if index == 0 {
+                    prefix = &instruction[..instruction.len() - command.len()];
+                }
+                let conditions = names.iter().map(|name| format!("${} -eq '{}'", platform_variable(name), name)).collect::<Vec<_>>();
+                lines.push(if conditions.is_empty() { command.to_string() } else { format!("if ({}) {{ {} }}", conditions.join(" -and "), command) });
+            }
+            let script = ps_expandable(&lines.join("\n"), Some(cmd)).replace("$DOMAKE_", "`$DOMAKE_");
+            buffer.push_str(format!("    Invoke-Instruction {}{}\n", script.replace('\n', "\n    "), flags(prefix)).as_str());
+        } else {
+            for instruction in &cmd.instructions {
+                let (names, instruction) = restrictions(instruction);
+                let command = instruction.trim_start_matches(['@', '-', '+']);
+                let prefix = &instruction[..instruction.len() - command.len()];
+                let call = format!("Invoke-Instruction {}{}", ps_expandable(command, Some(cmd)).replace('\n', "\n    "), flags(prefix));
+                if names.is_empty() {
+                    buffer.push_str(format!("    {}\n", call).as_str());
+                } else {
+                    let conditions = names.iter().map(|name| format!("${} -eq '{}'", platform_variable(name), name)).collect::<Vec<_>>();
+                    buffer.push_str(format!("    if ({}) {{ {} }}\n", conditions.join(" -and "), call).as_str());
+                }
+            }
+        }
+        buffer.push_str("}\n");
+    }
+
+    buffer.push_str("\n# runs the target, unless it already ran\n$Done = @{}\nfunction Invoke-Target([string]$Name) {\n    if ($Done.ContainsKey($Name)) { return }\n    $Done[$Name] = $true\n    switch -exact ($Name) {\n");
+    for (cmd, function) in &functions {
+        buffer.push_str(format!("        {} {{ {} }}\n", ps_quote(&cmd.name), function).as_str());
+    }
+    buffer.push_str("        default {\n            Write-Error \"unknown target ``$Name``\" -ErrorAction Continue\n            Show-Usage\n            exit 2\n        }\n    }\n}\n\n");
+
+    buffer.push_str("if ($Help) {\n    Show-Usage\n    exit 0\n}\n");
+    match &dofile.default {
+        Some(goal) => buffer.push_str(format!("if (-not $Targets) {{ $Targets = @({}) }}\n", ps_quote(&goal.name)).as_str()),
+        None => buffer.push_str("if (-not $Targets) {\n    Show-Usage\n    exit 0\n}\n"),
+    }
+    buffer.push_str("try {\n    foreach ($target in $Targets) { Invoke-Target $target }\n} catch {\n    Write-Error $_ -ErrorAction Continue\n    exit 1\n}\n");
+    buffer
+}
+
+/// The text of an `echo` in a batch file, where `^`, `&`, `|`, `<` and `>` are escaped by `^`.
+fn bat_echo(text: &str) -> String {
+    text.chars().fold(String::new(), |mut echo, c| {
+        if matches!(c, '^' | '&' | '|' | '<' | '>') {
+            echo.push('^');
+        }
+        echo.push(c);
+        echo
+    })
+}
+
+/// Translates what make would expand in `text` for a batch file, where the references are
+/// written `%NAME%` and a literal `%` is `%%`.
+fn bat_text(text: &str, cmd: Option<&Command>) -> String {
+    unmake(&text.replace('%', "%%"), cmd, &|name| format!("%{}%", name))
+}
+
+/// Renders the Dofile as a standalone batch file, run as `do.bat [TARGET]...` in minimal Windows
+/// environments: each target becomes a labeled block, dispatched by its name, which first runs
+/// its prerequisites, once each, so that they run in topological order like with make.
+///
+/// The instructions of a target run in the same `cmd.exe`, each target from the directory of the script; the
+/// pattern rules, includes and raw blocks are Makefile syntax and are left out.
+pub fn batch_file(dofile: &Dofile) -> String {
+    let mut buffer = String::from("@echo off\nrem This script was done using 'domake', run `do.bat help` for its targets\nsetlocal EnableExtensions\ncd /d \"%~dp0\"\n\n");
+    if let Some(shell) = &dofile.shell {
+        buffer.push_str(format!("rem the instructions are run by cmd.exe instead of `{}`\n", shell.name).as_str());
+    }
+    for include in &dofile.includes {
+        buffer.push_str(format!("rem left out, as it is a Makefile: include {}\n", include.path).as_str());
+    }
+    if !dofile.raws.is_empty() {
+        buffer.push_str(format!("rem left out, as they are Makefile syntax: {} raw block(s)\n", dofile.raws.len()).as_str());
+    }
+    for cmd in dofile.commands.iter().filter(|cmd| cmd.is_pattern()) {
+        buffer.push_str(format!("rem left out, as a script has no pattern rules: {}\n", cmd.name).as_str());
+    }
+
+    let (os, arch) = uses_platforms(dofile);
+    if os {
+        buffer.push_str("set \"DOMAKE_OS=windows\"\n");
+    }
+    if arch {
+        buffer.push_str("set \"DOMAKE_ARCH=amd64\"\nif /i \"%PROCESSOR_ARCHITECTURE%\"==\"ARM64\" set \"DOMAKE_ARCH=arm64\"\n");
+    }
+    // the variables are all in the environment of the instructions
+    for variable in &dofile.variables {
+        let value = bat_text(&variable.value, None);
+        match variable.assignment {
+            Assignment::Conditional => buffer.push_str(format!("if not defined {0} set \"{0}={1}\"\n", variable.name, value).as_str()),
+            Assignment::Append => buffer.push_str(format!("set \"{0}=%{0}% {1}\"\n", variable.name, value).as_str()),
+            _ => buffer.push_str(format!("set \"{}={}\"\n", variable.name, value).as_str()),
+        }
+    }
+
+    buffer.push_str("\nif \"%~1\"==\"help\" goto :usage\nif \"%~1\"==\"/?\" goto :usage\n");
+    match &dofile.default {
+        Some(goal) => buffer.push_str(format!("if \"%~1\"==\"\" (\n    call :run \"{}\" || exit /b 1\n    exit /b 0\n)\n", goal.name).as_str()),
+        None => buffer.push_str("if \"%~1\"==\"\" goto :usage\n"),
+    }
+    buffer.push_str(":next\nif \"%~1\"==\"\" exit /b 0\ncall :run \"%~1\" || exit /b 1\nshift\ngoto :next\n\n");
+
+    let exported = dofile.commands.iter().filter(|cmd| !cmd.is_pattern()).collect::<Vec<_>>();
+    let width = exported.iter().filter(|cmd| !cmd.is_hidden()).map(|cmd| cmd.name.len()).max().unwrap_or_default();
+    buffer.push_str(":usage\necho Usage: %~nx0 [TARGET]...\necho Targets:\n");
+    for cmd in exported.iter().filter(|cmd| !cmd.is_hidden()) {
+        buffer.push_str(format!("echo {}\n", bat_echo(&format!("  {:width$}  {}", cmd.name, cmd.summary()).replace('%', "%%")).trim_end()).as_str());
+    }
+    buffer.push_str("exit /b 0\n\n");
+
+    let mut taken = Vec::new();
+    let labels = exported.iter().map(|cmd| (cmd, format!("target_{}", function_name(&cmd.name, &mut taken)))).collect::<Vec<_>>();
+    buffer.push_str("rem runs the target %1\n:run\n");
+    for (cmd, label) in &labels {
+        buffer.push_str(format!("if \"%~1\"==\"{}\" goto :{}\n", cmd.name, label).as_str());
+    }
+    buffer.push_str("echo %~nx0: unknown target `%~1` 1>&2\ncall :usage 1>&2\nexit /b 2\n\n");
+    // `xcopy /d /l` lists the source when it is newer than the destination
+    buffer.push_str("rem whether the file %1 is missing, or older than one of the other files\n:outdated\nif not exist \"%~1\" exit /b 0\nset \"DOMAKE_FILE=%~1\"\n:outdated_next\nshift\nif \"%~1\"==\"\" exit /b 1\nxcopy /d /l /y \"%~1\" \"%DOMAKE_FILE%*\" | findstr /b /c:\"1 \" >nul && exit /b 0\ngoto :outdated_next\n");
+
+    for (cmd, label) in &labels {
+        buffer.push_str(format!("\nrem {}\n:{}\nif defined DOMAKE_DONE_{1} exit /b 0\nset \"DOMAKE_DONE_{1}=1\"\n", cmd.summary(), label).as_str());
+        let is_target = |name: &str| exported.iter().any(|other| other.name == name);
+        for prior in cmd.prior_commands.iter().filter(|prior| is_target(prior)) {
+            buffer.push_str(format!("call :run \"{}\" || exit /b 1\n", prior).as_str());
+        }
+        if cmd.file {
+            let files = [&cmd.name].into_iter().chain(&cmd.prior_commands).map(|name| format!("\"{}\"", name.replace('/', "\\"))).collect::<Vec<_>>();
+            buffer.push_str(format!("call :outdated {} || exit /b 0\n", files.join(" ")).as_str());
+        }
+        // the variables of the target are kept to it, and a `cd` of its instructions
+        buffer.push_str("setlocal\ncd /d \"%~dp0\"\n");
+        for variable in &cmd.variables {
+            buffer.push_str(format!("set \"{}={}\"\n", variable.name, bat_text(&variable.value, None)).as_str());
+        }
+        if let Some(advice) = &cmd.deprecated {
+            let advice = if advice.is_empty() { String::new() } else { format!(", {}", advice) };
+            buffer.push_str(format!("echo {} 1>&2\n", bat_echo(&format!("Warning: target `{}` is deprecated{}", cmd.name, advice).replace('%', "%%"))).as_str());
+        }
+        for tool in &cmd.requires {
+            let message = bat_echo(&format!("{}: `{}` is required but was not found in the PATH", cmd.name, tool));
+            buffer.push_str(format!("where {} >nul 2>&1 || (echo {} 1>&2 & exit /b 1)\n", tool, message.replace(')', "^)")).as_str());
+        }
+        // a `#@ oneshell` target is run the same way, with the flags of its first instruction
+        let oneshell = cmd.oneshell.then(|| restrictions(&cmd.instructions[0]).1).map(|first| &first[..first.len() - first.trim_start_matches(['@', '-', '+']).len()]);
+        for instruction in &cmd.instructions {
+            let (names, instruction) = restrictions(instruction);
+            let command = instruction.trim_start_matches(['@', '-', '+']);
+            if command.starts_with('#') {
+                continue;
+            }
+            let prefix = oneshell.unwrap_or(&instruction[..instruction.len() - command.len()]);
+            let command = bat_text(command, Some(cmd));
+            let conditions = names.iter().map(|name| format!("if \"%{}%\"==\"{}\" ", platform_variable(name), name)).collect::<String>();
+            if !prefix.contains('@') {
+                buffer.push_str(format!("{}echo {}\n", conditions, bat_echo(&command)).as_str());
+            }
+            let failure = if prefix.contains('-') { "" } else { " || exit /b 1" };
+            buffer.push_str(format!("{}{}{}\n", conditions, command, failure).as_str());
+        }
+        buffer.push_str("endlocal\nexit /b 0\n");
+    }
+    // cmd.exe reads the labels of a batch file by its lines ending with CRLF
+    buffer.replace('\n', "\r\n")
+}
+
+/// The value of the variable `name` as make would expand it where it is used, from the
+/// assignments of the Dofile, else a reference to the environment variable.
+fn resolve(name: &str, dofile: &Dofile, depth: usize) -> String {
+    let mut value: Option<String> = None;
+    for variable in dofile.variables.iter().filter(|variable| variable.name == name) {
+        value = match (variable.assignment, value) {
+            (Assignment::Conditional, Some(value)) => Some(value),
+            (Assignment::Conditional, None) => Some(format!("${{{}:-{}}}", name, variable.value)),
+            (Assignment::Append, Some(value)) => Some(format!("{} {}", value, variable.value)),
+            _ => Some(variable.value.clone()),
+        };
+    }
+    match value {
+        // a variable referring to itself would never end
+        Some(value) if depth < 16 => unmake(&value, None, &|name| resolve(name, dofile, depth + 1)),
+        _ if name == "DOMAKE_OS" => "$(case $(uname -s) in Darwin) echo macos;; MINGW*|MSYS*|CYGWIN*) echo windows;; *) echo linux;; esac)".to_string(),
+        _ if name == "DOMAKE_ARCH" => "$(case $(uname -m) in arm64|aarch64) echo arm64;; *) echo amd64;; esac)".to_string(),
+        _ => format!("${{{}}}", name),
+    }
+}
+
+/// The instructions of the target as a single npm script, after `npm run` of its prerequisites,
+/// with the variables of the Dofile expanded as make would.
+fn inline_script(cmd: &Command, dofile: &Dofile) -> String {
+    let reference = |name: &str| match cmd.variables.iter().find(|variable| variable.name == name) {
+        Some(variable) => unmake(&variable.value, None, &|name| resolve(name, dofile, 1)),
+        None => resolve(name, dofile, 0),
+    };
+    let mut steps = cmd.prior_commands.iter()
+        .filter(|prior| dofile.commands.iter().any(|other| &other.name == *prior && !other.is_pattern()))
+        .map(|prior| format!("npm run {}", prior))
+        .collect::<Vec<_>>();
+    // make puts the exported variables in the environment of the instructions
+    let mut exported: Vec<&str> = Vec::new();
+    for variable in dofile.variables.iter().chain(&cmd.variables).filter(|variable| variable.exported) {
+        if !exported.contains(&variable.name.as_str()) {
+            exported.push(&variable.name);
+        }
+    }
+    for name in exported {
+        steps.push(format!("export {}=\"{}\"", name, reference(name).replace('"', "\\\"")));
+    }
+    if cmd.oneshell {
+        let (_, commands) = cmd.oneshell_script();
+        let checks = checks(cmd).into_iter().map(|check| check.trim_start_matches('@').to_string());
+        steps.push(format!("({})", unmake(&checks.chain(commands).collect::<Vec<_>>().join("; "), Some(cmd), &reference)));
+    } else {
+        for command in commands(cmd) {
+            let line = command.trim_start_matches(['@', '-']);
+            let ignored = command[..command.len() - line.len()].contains('-');
+            let line = unmake(line, Some(cmd), &reference);
+            // so that a `||` of the line doesn't catch the failure of the steps before it
+            steps.push(if ignored { format!("({} || true)", line) } else if line.contains("||") { format!("{{ {}; }}", line) } else { line });
+        }
+    }
+    steps.join(" && ")
+}
+
+/// Merges the targets of the Dofile into the `scripts` of `package`, an existing `package.json`
+/// if any, keeping its other scripts and members: each target runs `make <target>`, or its
+/// instructions themselves with `inline`. Also returns the existing scripts that are replaced.
+///
+/// The pattern rules are left out, as npm cannot run them.
+pub fn package_json(dofile: &Dofile, package: Option<Json>, inline: bool) -> Result<(Json, Vec<String>), String> {
+    let mut members = match package {
+        Some(Json::Object(members)) => members,
+        Some(_) => return Err("it isn't a JSON object".to_string()),
+        None => Vec::new(),
+    };
+    let mut scripts = match members.iter().find(|(key, _)| key == "scripts").map(|(_, scripts)| scripts) {
+        Some(Json::Object(scripts)) => scripts.clone(),
+        Some(_) => return Err("its `scripts` isn't a JSON object".to_string()),
+        None => Vec::new(),
+    };
+
+    let mut replaced = Vec::new();
+    for cmd in dofile.commands.iter().filter(|cmd| !cmd.is_pattern()) {
+        let script = if inline { inline_script(cmd, dofile) } else { format!("make {}", cmd.name) };
+        match scripts.iter_mut().find(|(name, _)| *name == cmd.name) {
+            Some((_, existing)) => {
+                if existing.as_str() != Some(script.as_str()) {
+                    replaced.push(cmd.name.clone());
+                }
+                *existing = Json::from(script);
+            }
+            None => scripts.push((cmd.name.clone(), Json::from(script))),
+        }
+    }
+    match members.iter_mut().find(|(key, _)| key == "scripts") {
+        Some((_, existing)) => *existing = Json::Object(scripts),
+        None => members.push(("scripts".to_string(), Json::Object(scripts))),
+    }
+    Ok((Json::Object(members), replaced))
+}
+
+/// The targets of the group `group` run by a CI pipeline, in order, leaving out the pattern rules;
+/// fails when there are none.
+fn ci_targets<'a>(dofile: &'a Dofile, group: &str) -> Result<Vec<&'a Command>, String> {
+    let targets = dofile.commands.iter().filter(|cmd| cmd.group.as_deref() == Some(group) && !cmd.is_pattern()).collect::<Vec<_>>();
+    if targets.is_empty() {
+        return Err(format!("no target is in the group `{}`, tag the targets to run with a `#@ group: {}` line", group, group));
+    }
+    Ok(targets)
+}
+
+/// Renders a GitHub Actions workflow running the targets of the group `group` (e.g. those with a
+/// `#@ group: ci` line) on every push and pull request: after checking out the repository, each
+/// target is a step running `make <target>`, named after its description.
+///
+/// The steps run the Makefile generated from the Dofile, which must be committed with it.
+pub fn github_actions(dofile: &Dofile, group: &str) -> Result<String, String> {
+    let targets = ci_targets(dofile, group)?;
+    let mut buffer = String::from("# This workflow was done using 'domake'\n");
+    buffer.push_str(format!("# it runs the Makefile generated from the Dofile, keep them both committed\nname: {}\n", yaml_string(group)).as_str());
+    buffer.push_str("on:\n  push:\n  pull_request:\njobs:\n  make:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n");
+    for cmd in targets {
+        let name = if cmd.summary().is_empty() { cmd.name.as_str() } else { cmd.summary() };
+        buffer.push_str(format!("      - name: {}\n        run: {}\n", yaml_string(name), yaml_string(&format!("make {}", cmd.name))).as_str());
+    }
+    Ok(buffer)
+}
+
+/// The keys of `.gitlab-ci.yml` that cannot name a job.
+const GITLAB_KEYWORDS: &[&str] = &["default", "include", "stages", "variables", "workflow", "image", "services", "cache", "before_script", "after_script", "pages"];
+
+/// The number of jobs on the longest chain of prerequisites below the target `name`.
+fn job_depth(dofile: &Dofile, name: &str, is_job: &dyn Fn(&str) -> bool) -> usize {
+    let Some(cmd) = dofile.commands.iter().find(|cmd| cmd.name == name) else { return 0 };
+    cmd.prior_commands.iter().map(|prior| job_depth(dofile, prior, is_job) + usize::from(is_job(prior))).max().unwrap_or(0)
+}
+
+/// Renders a `.gitlab-ci.yml` pipeline running the targets of the group `group` (e.g. those with a
+/// `#@ group: ci` line): each target is a job whose script is `make <target>`, in a stage after the
+/// stages of the targets of the group it depends on, even through other targets.
+///
+/// The jobs run the Makefile generated from the Dofile, which must be committed with it.
+pub fn gitlab_ci(dofile: &Dofile, group: &str) -> Result<String, String> {
+    let targets = ci_targets(dofile, group)?;
+    let is_job = |name: &str| targets.iter().any(|cmd| cmd.name == name);
+    let stages = targets.iter().map(|cmd| job_depth(dofile, &cmd.name, &is_job)).collect::<Vec<_>>();
+
+    let mut buffer = String::from("# This pipeline was done using 'domake'\n");
+    buffer.push_str("# it runs the Makefile generated from the Dofile, keep them both committed\nstages:\n");
+    for stage in 0..=stages.iter().copied().max().unwrap_or(0) {
+        buffer.push_str(format!("  - {}\n", yaml_string(&format!("{}-{}", group, stage + 1))).as_str());
+    }
+    for (cmd, stage) in targets.iter().zip(stages) {
+        let job = if GITLAB_KEYWORDS.contains(&cmd.name.as_str()) { format!("make-{}", cmd.name) } else { cmd.name.clone() };
+        buffer.push_str(format!("\n# {}\n{}:\n", cmd.summary(), yaml_string(&job)).as_str());
+        buffer.push_str(format!("  stage: {}\n  script:\n    - {}\n", yaml_string(&format!("{}-{}", group, stage + 1)), yaml_string(&format!("make {}", cmd.name))).as_str());
+    }
+    Ok(buffer)
+}
+
+/// Renders the parsed Dofile as JSON for other tools: its includes, variables, targets and their
+/// instructions, each with its line and the bytes of its span in the Dofile. It is the schema
+/// read back by `Dofile::try_from`.
+pub fn json(dofile: &Dofile) -> String {
+    format!("{}\n", Json::from(dofile).pretty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_dofile;
+
+    #[test]
+    fn shell_script_skips_the_comment_instructions() {
+        let dofile = parse_dofile("[build]\n# Builds\necho hi\n# just a comment\n").unwrap();
+        let script = shell_script(&dofile);
+        assert!(!script.contains("(# just a comment"));
+        assert!(script.contains("    (echo hi)\n"));
+    }
+
+    #[test]
+    fn npm_scripts_skip_the_comment_instructions() {
+        let dofile = parse_dofile("[out]\n# Builds out\necho hi\n# a note\ntouch out\n").unwrap();
+        assert_eq!(inline_script(&dofile.commands[0], &dofile), "echo hi && touch out");
+        let dofile = parse_dofile("[out]\n# Builds out\n#@ oneshell\necho hi\n# a note\ntouch out\n").unwrap();
+        assert_eq!(inline_script(&dofile.commands[0], &dofile), "(set -e; echo hi; touch out)");
+    }
+
+    #[test]
+    fn ninja_skips_the_comment_instructions() {
+        let dofile = parse_dofile("[file: out]\n# Builds out\necho hi\n# a note\n@linux: # a linux note\ntouch out\n").unwrap();
+        let ninja = ninja(&dofile);
+        assert!(ninja.contains("  command = echo hi && touch out\n"), "{}", ninja);
+        assert!(ninja.contains("build out: out\n"), "{}", ninja);
+    }
+}
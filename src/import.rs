@@ -0,0 +1,1492 @@
+//! Reads the files of other task runners into a Dofile, for projects adopting domake.
+//!
+//! Each importer fills an [`Import`], which [`Import::to_dofile`] writes as a Dofile: what has no
+//! equivalent is kept in a raw block when it is Makefile syntax, and noted for the report otherwise.
+
+use std::iter::Peekable;
+use std::str::Chars;
+use crate::dofile::{declared_variables, platform_variable};
+use crate::makefile::{ARCH_DETECTION, OS_DETECTION};
+use crate::json::Json;
+use crate::{is_valid_name, is_variable_name, toml, yaml, ARCHITECTURES, HELPER_TARGETS, OPERATING_SYSTEMS};
+
+/// A target of the imported file.
+#[derive(Debug, Clone, Default)]
+pub struct Target {
+    pub name: String,
+    /// Whether it makes the file named after it, instead of being `.PHONY`.
+    pub file: bool,
+    /// The lines of its description, its name if there are none.
+    pub description: Vec<String>,
+    pub prerequisites: Vec<String>,
+    /// The section of `make help` it is listed in.
+    pub group: Option<String>,
+    /// The `NAME = value` assignments set only while it runs.
+    pub variables: Vec<String>,
+    pub oneshell: bool,
+    pub notparallel: bool,
+    /// The instructions as make reads them, with `$$` for a `$` of the shell.
+    pub instructions: Vec<String>,
+}
+
+/// What is read from the file of another task runner.
+#[derive(Debug, Clone, Default)]
+pub struct Import {
+    pub shell: Option<String>,
+    pub oneshell: bool,
+    pub notparallel: bool,
+    /// The target run by default.
+    pub default: Option<String>,
+    /// The included Makefiles, with whether a missing one is skipped.
+    pub includes: Vec<(String, bool)>,
+    /// The `.env` files read, with whether a missing one is skipped.
+    pub dotenvs: Vec<(String, bool)>,
+    /// The assignments of the variables, written the same in the Dofile and the Makefile.
+    pub variables: Vec<String>,
+    /// The Makefile syntax kept as it is, each in a raw block.
+    pub raws: Vec<String>,
+    pub targets: Vec<Target>,
+    /// What was left out or changed, for the conversion report.
+    pub notes: Vec<String>,
+}
+
+/// Whether the instruction needs `dollars: make` to be written as it is, as it has a `$` that make
+/// reads other than a reference to a `declared` variable (e.g. `$@` or `$(shell pwd)`), or a
+/// `$$(NAME)` that the Dofile would read as a reference.
+fn needs_make_dollars(instruction: &str, declared: &[String]) -> bool {
+    // the name of the reference opening `text`, e.g. `NAME` of `(NAME)` or `{NAME:.c=.o}`
+    let is_declared = |text: &str| {
+        let close = if text.starts_with('(') { ')' } else { '}' };
+        text[1..].split_once(close).map(|(inside, _)| inside.split_once(':').map_or(inside, |(name, _)| name).trim())
+            .is_some_and(|name| is_variable_name(name) && declared.iter().any(|declared| declared == name))
+    };
+    let mut rest = instruction;
+    while let Some(start) = rest.find('$') {
+        rest = &rest[start + 1..];
+        match rest.chars().next() {
+            Some('$') => {
+                rest = &rest[1..];
+                if rest.starts_with(['(', '{']) && is_declared(rest) {
+                    return true;
+                }
+            }
+            Some('(' | '{') if is_declared(rest) => {}
+            _ => return true,
+        }
+    }
+    false
+}
+
+impl Import {
+    /// The target named `name`, added at the end if there is none yet.
+    pub fn target(&mut self, name: &str) -> &mut Target {
+        let index = match self.targets.iter().position(|target| target.name == name) {
+            Some(index) => index,
+            None => {
+                self.targets.push(Target { name: name.to_string(), ..Target::default() });
+                self.targets.len() - 1
+            }
+        };
+        &mut self.targets[index]
+    }
+
+    /// Writes the Dofile of what was imported from `source`: its settings, includes, `.env` files,
+    /// variables and raw blocks, then a target per target, given the instruction `@:` when it has none.
+    pub fn to_dofile(&self, source: &str) -> String {
+        let mut buffer = format!("# Imported from `{}` by `domake import`\n\n", source);
+        let mut section = String::new();
+        if let Some(shell) = &self.shell {
+            section.push_str(format!("shell {}\n", shell).as_str());
+        }
+        if self.oneshell {
+            section.push_str("oneshell\n");
+        }
+        if self.notparallel {
+            section.push_str("notparallel\n");
+        }
+        if let Some(goal) = &self.default {
+            section.push_str(format!("default {}\n", goal).as_str());
+        }
+        for (path, optional) in &self.includes {
+            section.push_str(format!("include{} {}\n", if *optional { "?" } else { "" }, path).as_str());
+        }
+        for (path, optional) in &self.dotenvs {
+            section.push_str(format!("dotenv{} {}\n", if *optional { "?" } else { "" }, path).as_str());
+        }
+        for sections in [section, self.variables.iter().map(|variable| format!("{}\n", variable)).collect()] {
+            if !sections.is_empty() {
+                buffer.push_str(&sections);
+                buffer.push('\n');
+            }
+        }
+        for raw in &self.raws {
+            buffer.push_str(format!("```make\n{}\n```\n\n", raw.trim_end()).as_str());
+        }
+
+        let assignments = self.variables.iter().chain(&self.raws).flat_map(|text| text.lines()).collect::<Vec<_>>();
+        let declared = declared_variables(&assignments);
+        for target in &self.targets {
+            let header = if target.file { format!("[file: {}]", target.name) } else { format!("[{}]", target.name) };
+            buffer.push_str(&header);
+            for prior in &target.prerequisites {
+                buffer.push_str(format!(" {}", prior).as_str());
+            }
+            buffer.push('\n');
+            let description = if target.description.iter().all(|line| line.trim().is_empty()) { vec![target.name.clone()] } else { target.description.clone() };
+            for line in description {
+                buffer.push_str(format!("# {}", line.trim()).trim_end());
+                buffer.push('\n');
+            }
+            for variable in &target.variables {
+                buffer.push_str(format!("vars: {}\n", variable).as_str());
+            }
+            if let Some(group) = &target.group {
+                buffer.push_str(format!("#@ group: {}\n", group).as_str());
+            }
+            if target.oneshell {
+                buffer.push_str("#@ oneshell\n");
+            }
+            if target.notparallel {
+                buffer.push_str("#@ notparallel\n");
+            }
+            // the instructions of a pattern rule are already read by make as they are
+            let instructions = target.instructions.iter().filter(|instruction| !instruction.trim().is_empty()).collect::<Vec<_>>();
+            let assignments = target.variables.iter().map(String::as_str).collect::<Vec<_>>();
+            let declared = declared.iter().cloned().chain(declared_variables(&assignments)).collect::<Vec<_>>();
+            let make_dollars = !target.name.contains('%') && instructions.iter().any(|instruction| needs_make_dollars(instruction, &declared));
+            if make_dollars {
+                buffer.push_str("dollars: make\n");
+            }
+            if instructions.is_empty() {
+                buffer.push_str("@:\n");
+            }
+            for instruction in instructions {
+                let instruction = if make_dollars || target.name.contains('%') { instruction.to_string() } else { instruction.replace("$$", "$") };
+                buffer.push_str(format!("{}\n", instruction).as_str());
+            }
+            buffer.push('\n');
+        }
+        buffer.truncate(buffer.trim_end().len());
+        buffer.push('\n');
+        buffer
+    }
+}
+
+/// Whether `name` can be the name of a Dofile target, a pattern with a single `%` included.
+fn is_target_name(name: &str) -> bool {
+    let checked = if name.matches('%').count() == 1 { name.replace('%', "_") } else { name.to_string() };
+    is_valid_name(&checked)
+}
+
+/// The lines of the Makefile, with those carried on by a final `\` joined: by a space outside of
+/// the recipes, where make does so, and by a newline in them, where the shell does so.
+fn logical_lines(makefile: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut carried = false;
+    for line in makefile.lines() {
+        match lines.last_mut() {
+            Some(last) if carried && last.starts_with('\t') => {
+                last.push('\n');
+                last.push_str(line.strip_prefix('\t').unwrap_or(line));
+            }
+            Some(last) if carried => {
+                last.truncate(last.trim_end_matches('\\').trim_end().len());
+                last.push(' ');
+                last.push_str(line.trim_start());
+            }
+            _ => lines.push(line.to_string()),
+        }
+        carried = line.ends_with('\\') && !line.ends_with("\\\\");
+    }
+    lines
+}
+
+/// Where the recipe lines after a rule go.
+enum Recipe {
+    None,
+    /// To the targets of the rule, by their index.
+    Targets(Vec<usize>),
+    /// To the last raw block, with the rule.
+    Raw,
+}
+
+/// Reads a hand-written Makefile: its rules become targets, described by the `#` comments above
+/// them or a `## description` at the end of their line, and its variables, includes and special
+/// targets become those of the Dofile, with the `ifeq ($(DOMAKE_OS),linux)` written by domake
+/// around recipe lines read back as `@linux:`; the rest (conditionals, `define` blocks, static
+/// pattern rules, the rules with other conditionals in their recipe, ...) is kept in raw blocks.
+pub fn makefile(makefile: &str) -> Import {
+    let mut import = Import::default();
+    let mut phony: Vec<String> = Vec::new();
+    let mut comments: Vec<String> = Vec::new();
+    // the descriptions of the `## name: description` lines read by a `help` target, carried on by
+    // the `#` lines right after them
+    let mut help: Vec<(String, Vec<String>)> = Vec::new();
+    let mut in_help = false;
+    let mut first = None;
+    let mut recipe = Recipe::None;
+    let mut depth = 0;
+    // the platforms of the `ifeq ($(DOMAKE_OS),linux)` around the recipe lines, written by domake for `@linux:`
+    let mut platforms: Vec<&str> = Vec::new();
+    // the rules with other conditionals in their recipe stay Makefile rules, written as they are
+    let mut conditional: Vec<String> = Vec::new();
+    let mut recipes: Vec<(String, String)> = Vec::new();
+    let mut recipe_depth = 0;
+    // a conditional after a blank line is outside of the recipe
+    let mut in_recipe = false;
+
+    for line in logical_lines(makefile) {
+        let keyword = line.split_whitespace().next().unwrap_or_default();
+        if let Recipe::Targets(indices) = &recipe {
+            let names = indices.iter().map(|index| import.targets[*index].name.clone()).collect::<Vec<_>>();
+            if recipe_depth > 0 || (in_recipe && matches!(keyword, "ifeq" | "ifneq" | "ifdef" | "ifndef" | "else" | "endif")) {
+                match platform_condition(&line) {
+                    Some(name) if recipe_depth == 0 => platforms.push(name),
+                    _ if keyword == "endif" && recipe_depth == 0 && !platforms.is_empty() => {
+                        platforms.pop();
+                    }
+                    _ => {
+                        conditional.extend(names.iter().filter(|name| !conditional.contains(name)).cloned().collect::<Vec<_>>());
+                        match keyword {
+                            "ifeq" | "ifneq" | "ifdef" | "ifndef" => recipe_depth += 1,
+                            "endif" => recipe_depth = usize::saturating_sub(recipe_depth, 1),
+                            _ => {}
+                        }
+                    }
+                }
+                record_recipe(&mut recipes, &names, &line);
+                continue;
+            }
+            if line.starts_with('\t') {
+                record_recipe(&mut recipes, &names, &line);
+            }
+        }
+
+        // the conditionals and `define` blocks are kept whole
+        if depth > 0 || matches!(keyword, "ifeq" | "ifneq" | "ifdef" | "ifndef" | "define") {
+            if depth == 0 {
+                import.raws.push(String::new());
+            }
+            match keyword {
+                "ifeq" | "ifneq" | "ifdef" | "ifndef" | "define" => depth += 1,
+                "endif" | "endef" => depth -= 1,
+                _ => {}
+            }
+            let raw = import.raws.last_mut().unwrap();
+            raw.push_str(&line);
+            raw.push('\n');
+            recipe = Recipe::None;
+            continue;
+        }
+
+        if let Some(instruction) = line.strip_prefix('\t') {
+            match &recipe {
+                Recipe::Targets(indices) => {
+                    let restrictions = platforms.iter().map(|name| format!("@{}: ", name)).collect::<String>();
+                    for index in indices {
+                        import.targets[*index].instructions.push(format!("{}{}", restrictions, instruction));
+                    }
+                }
+                Recipe::Raw => {
+                    let raw = import.raws.last_mut().unwrap();
+                    raw.push_str(&line);
+                    raw.push('\n');
+                }
+                Recipe::None if !instruction.trim().is_empty() => import.notes.push(format!("left out, as it follows no rule: {}", instruction.trim())),
+                Recipe::None => {}
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            comments.clear();
+            in_help = false;
+            in_recipe = false;
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            match comment.strip_prefix('#').map(str::trim).and_then(|help| help.split_once(':')) {
+                Some((name, description)) if !name.contains(char::is_whitespace) => {
+                    help.push((name.to_string(), vec![description.trim().to_string()]));
+                    in_help = true;
+                }
+                _ if in_help => help.last_mut().unwrap().1.push(comment.trim_start_matches('#').trim().to_string()),
+                _ => comments.push(comment.trim_start_matches('#').trim().to_string()),
+            }
+            continue;
+        }
+        recipe = Recipe::None;
+        platforms.clear();
+        in_help = false;
+        let described = std::mem::take(&mut comments);
+
+        if let Some((keyword, path)) = trimmed.split_once(char::is_whitespace).filter(|(keyword, _)| matches!(*keyword, "include" | "-include" | "sinclude")) {
+            for path in path.split_whitespace() {
+                import.includes.push((path.to_string(), keyword != "include"));
+            }
+            continue;
+        }
+        if let Some(assignment) = assignment(trimmed) {
+            match assignment {
+                (".DEFAULT_GOAL", _, value, _) => import.default = Some(value.to_string()),
+                ("SHELL", _, value, _) => import.shell = Some(value.trim_start_matches("/usr/bin/env ").to_string()),
+                (".SHELLFLAGS", _, value, _) => {
+                    if let Some(shell) = &mut import.shell {
+                        shell.push_str(format!(" {}", value).as_str());
+                    }
+                }
+                (name, operator, value, exported) if is_variable_name(name) && operator != "!=" => {
+                    let operator = if operator == "::=" { ":=" } else { operator };
+                    import.variables.push(format!("{}{} {} {}", if exported { "export " } else { "" }, name, operator, value).trim_end().to_string());
+                }
+                _ => import.raws.push(line.clone()),
+            }
+            continue;
+        }
+
+        let Some((targets, rest)) = rule(trimmed) else {
+            import.raws.push(line.clone());
+            continue;
+        };
+        let (rest, inline) = match rest.split_once(" ##") {
+            Some((rest, description)) => (rest, Some(description.trim().to_string())),
+            None => (rest, None),
+        };
+        let (prerequisites, instruction) = match rest.split_once(';') {
+            Some((prerequisites, instruction)) => (prerequisites, Some(instruction.trim())),
+            None => (rest, None),
+        };
+        let targets = targets.split_whitespace().collect::<Vec<_>>();
+        match targets.as_slice() {
+            [".PHONY"] => phony.extend(prerequisites.split_whitespace().map(str::to_string)),
+            [".ONESHELL"] => import.oneshell = true,
+            [".NOTPARALLEL"] if prerequisites.trim().is_empty() => import.notparallel = true,
+            [".NOTPARALLEL"] => {
+                for name in prerequisites.split_whitespace() {
+                    import.target(name).notparallel = true;
+                }
+            }
+            [".DEFAULT_GOAL"] => import.default = prerequisites.split_whitespace().next().map(str::to_string),
+            _ => {}
+        }
+        if targets.iter().any(|name| name.starts_with('.')) {
+            if matches!(targets.as_slice(), [".PHONY" | ".ONESHELL" | ".NOTPARALLEL" | ".DEFAULT_GOAL"]) {
+                // e.g. the `.PHONY: build` between the description of `build` and its rule
+                comments = described;
+            } else {
+                import.raws.push(format!("{}\n", line));
+                recipe = Recipe::Raw;
+            }
+            continue;
+        }
+
+        // a target-specific variable, e.g. `build: CFLAGS += -g`
+        if let Some((name, operator, value, _)) = assignment(prerequisites.trim()).filter(|(name, ..)| is_variable_name(name)) {
+            for target in &targets {
+                if is_target_name(target) {
+                    import.target(target).variables.push(format!("{} {} {}", name, operator, value).trim_end().to_string());
+                }
+            }
+            continue;
+        }
+        // double-colon and static pattern rules, or names made by make, have no Dofile equivalent
+        let prerequisites = prerequisites.split_whitespace().filter(|prior| *prior != "|").collect::<Vec<_>>();
+        if rest.starts_with(':') || prerequisites.iter().any(|prior| prior.contains(['$', ':'])) || !targets.iter().all(|name| is_target_name(name)) {
+            import.raws.push(format!("{}\n", line));
+            recipe = Recipe::Raw;
+            continue;
+        }
+
+        let mut indices = Vec::new();
+        for name in targets {
+            if first.is_none() && !name.contains('%') {
+                first = Some(name.to_string());
+            }
+            if HELPER_TARGETS.contains(&name) {
+                import.notes.push(format!("left out, as domake writes its own: target `{}`", name));
+                continue;
+            }
+            let target = import.target(name);
+            target.prerequisites.extend(prerequisites.iter().map(|prior| prior.to_string()));
+            if target.description.is_empty() {
+                target.description = match &inline {
+                    Some(description) => vec![description.clone()],
+                    None => described.clone(),
+                };
+                // the `# _name: description` of the targets left out of `make help`
+                if let Some(first) = target.description.first_mut() {
+                    if let Some(description) = first.strip_prefix(name).and_then(|rest| rest.strip_prefix(':')) {
+                        *first = description.trim().to_string();
+                    }
+                }
+            }
+            if let Some(instruction) = instruction.filter(|instruction| !instruction.is_empty()) {
+                target.instructions.push(instruction.to_string());
+            }
+            indices.push(import.targets.iter().position(|target| target.name == name).unwrap());
+        }
+        recipe = Recipe::Targets(indices);
+        in_recipe = true;
+    }
+
+    for target in &mut import.targets {
+        if let Some((_, description)) = help.iter().find(|(name, _)| *name == target.name) {
+            target.description = description.clone();
+        }
+        // without a `.PHONY`, a name with a `.` or a `/` is taken for a file
+        target.file = !target.name.contains('%') && if phony.is_empty() { target.name.contains(['.', '/']) } else { !phony.contains(&target.name) };
+    }
+    // the rules only adding prerequisites to a file, e.g. `main.o: main.h`, stay Makefile rules
+    let (targets, rules): (Vec<_>, Vec<_>) = import.targets.drain(..)
+        .partition(|target| (!target.file || !target.instructions.is_empty()) && !conditional.contains(&target.name));
+    for rule in rules {
+        let recipe = recipes.iter().find(|(name, _)| *name == rule.name).map(|(_, recipe)| recipe.as_str()).unwrap_or_default();
+        if conditional.contains(&rule.name) {
+            import.notes.push(format!("kept as a Makefile rule, as its recipe has conditionals: target `{}`", rule.name));
+        }
+        let phony = if rule.file { String::new() } else { format!(".PHONY: {}\n", rule.name) };
+        import.raws.push(format!("{}{}:{}\n{}", phony, rule.name, rule.prerequisites.iter().map(|prior| format!(" {}", prior)).collect::<String>(), recipe));
+    }
+    import.targets = targets;
+    for target in import.targets.iter().filter(|target| target.instructions.is_empty()) {
+        import.notes.push(format!("given the instruction `@:`, as a Dofile target has at least one: target `{}`", target.name));
+    }
+    // the platforms of the `@linux:` instructions are detected by the Makefile domake writes
+    for (detection, variable) in [(OS_DETECTION, "DOMAKE_OS"), (ARCH_DETECTION, "DOMAKE_ARCH")] {
+        if let Some(index) = import.raws.iter().position(|raw| raw == detection) {
+            import.raws.remove(index);
+            import.notes.push(format!("left out, as domake writes its own: the detection of `{}`", variable));
+        }
+    }
+    // the `help` of domake is what its Makefile runs without a `default` line
+    if import.default.as_ref().is_some_and(|goal| HELPER_TARGETS.contains(&goal.as_str())) {
+        import.default = None;
+    }
+    if let Some(first) = first.filter(|name| import.default.is_none() && !HELPER_TARGETS.contains(&name.as_str())) {
+        import.notes.push(format!("make ran the first target `{}` by default, the Dofile runs `help` unless given a `default {}` line", first, first));
+    }
+    import
+}
+
+/// Records the recipe line of the rules of `names`, as it is written.
+fn record_recipe(recipes: &mut Vec<(String, String)>, names: &[String], line: &str) {
+    for name in names {
+        match recipes.iter_mut().find(|(recipe, _)| recipe == name) {
+            Some((_, recipe)) => recipe.push_str(format!("{}\n", line).as_str()),
+            None => recipes.push((name.clone(), format!("{}\n", line))),
+        }
+    }
+}
+
+/// The platform of an `ifeq ($(DOMAKE_OS),linux)` line, as domake writes around the instructions
+/// restricted to it.
+fn platform_condition(line: &str) -> Option<&'static str> {
+    OPERATING_SYSTEMS.iter().chain(ARCHITECTURES)
+        .find(|name| line.trim() == format!("ifeq ($({}),{})", platform_variable(name), name))
+        .copied()
+}
+
+/// The variable assignment of the line: its name, operator, value and whether it is exported.
+fn assignment(line: &str) -> Option<(&str, &str, &str, bool)> {
+    let (line, exported) = match line.strip_prefix("export ") {
+        Some(rest) => (rest.trim_start(), true),
+        None => (line.strip_prefix("override ").map(str::trim_start).unwrap_or(line), false),
+    };
+    let end = line.find(|c: char| c.is_whitespace() || matches!(c, ':' | '?' | '+' | '!' | '='))?;
+    let (name, rest) = line.split_at(end);
+    let rest = rest.trim_start();
+    let operator = ["::=", ":=", "?=", "+=", "!=", "="].into_iter().find(|operator| rest.starts_with(operator))?;
+    Some((name, operator, rest[operator.len()..].trim(), exported)).filter(|(name, ..)| !name.is_empty())
+}
+
+/// The targets and the rest of the rule of the line, after its first `:`.
+fn rule(line: &str) -> Option<(&str, &str)> {
+    let (targets, rest) = line.split_once(':')?;
+    Some((targets.trim(), rest)).filter(|(targets, _)| !targets.is_empty())
+}
+
+/// Skips the spaces and tabs at the start of `chars`.
+fn skip_blanks(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+}
+
+/// The make value of a just expression, or `None` when it has none (e.g. an `if`).
+fn just_value(expression: &str) -> Option<String> {
+    let mut chars = expression.trim().chars().peekable();
+    let value = just_terms(&mut chars)?;
+    chars.next().is_none().then_some(value)
+}
+
+/// The make value of the terms joined by `+` (concatenation) or `/` (path join) at the start of `chars`.
+fn just_terms(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        skip_blanks(chars);
+        value.push_str(&just_term(chars)?);
+        skip_blanks(chars);
+        match chars.peek() {
+            Some('+') => {}
+            Some('/') => value.push('/'),
+            _ => return Some(value),
+        }
+        chars.next();
+    }
+}
+
+/// The make value of the string, backtick, variable, function call or parenthesized expression at
+/// the start of `chars`.
+fn just_term(chars: &mut Peekable<Chars>) -> Option<String> {
+    let text = match chars.next()? {
+        '\'' => chars.by_ref().take_while(|c| *c != '\'').collect::<String>(),
+        '"' => {
+            let mut text = String::new();
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => match chars.next()? {
+                        't' => text.push('\t'),
+                        c @ ('"' | '\\') => text.push(c),
+                        _ => return None,
+                    },
+                    c => text.push(c),
+                }
+            }
+            text
+        }
+        '`' => {
+            let command = chars.by_ref().take_while(|c| *c != '`').collect::<String>();
+            return Some(format!("$(shell {})", command.trim().replace('$', "$$")));
+        }
+        '(' => {
+            let value = just_terms(chars)?;
+            skip_blanks(chars);
+            return chars.next_if_eq(&')').map(|_| value);
+        }
+        c if c.is_ascii_alphabetic() || c == '_' => {
+            let mut name = c.to_string();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-') {
+                name.push(c);
+            }
+            skip_blanks(chars);
+            if chars.next_if_eq(&'(').is_none() {
+                return (name != "if").then(|| format!("$({})", name));
+            }
+            let mut arguments = Vec::new();
+            skip_blanks(chars);
+            while chars.next_if_eq(&')').is_none() {
+                arguments.push(just_terms(chars)?);
+                skip_blanks(chars);
+                chars.next_if_eq(&',');
+                skip_blanks(chars);
+            }
+            return match (name.as_str(), arguments.as_slice()) {
+                ("env_var" | "env", [variable]) => Some(format!("$({})", variable)),
+                ("env_var_or_default" | "env", [variable, default]) => Some(format!("$(or $({}),{})", variable, default)),
+                ("justfile_directory" | "invocation_directory", []) => Some("$(CURDIR)".to_string()),
+                ("num_cpus", []) => Some("$(shell nproc)".to_string()),
+                _ => None,
+            };
+        }
+        _ => return None,
+    };
+    // a make value has a single line
+    (!text.contains('\n')).then(|| text.replace('$', "$$"))
+}
+
+/// The words of a recipe line, split on the blanks outside of quotes and parentheses.
+fn just_words(line: &str) -> Vec<String> {
+    let mut words = vec![String::new()];
+    let (mut quote, mut depth) = (None, 0);
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ' ' | '\t') if depth == 0 => {
+                if !words.last().unwrap().is_empty() {
+                    words.push(String::new());
+                }
+                continue;
+            }
+            _ => {}
+        }
+        words.last_mut().unwrap().push(c);
+    }
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+/// The position of the `:` ending the parameters of a recipe line, outside of quotes and parentheses.
+fn just_colon(line: &str) -> Option<usize> {
+    let (mut quote, mut depth) = (None, 0);
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ':') if depth == 0 => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The instruction of a just recipe line as make reads it: its `$` escaped and its `{{...}}`
+/// interpolations made references, or `None` when one has no make value.
+fn just_instruction(line: &str) -> Option<String> {
+    let mut instruction = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        instruction.push_str(&rest[..start].replace('$', "$$"));
+        // `{{{{` is a literal `{{`
+        if let Some(after) = rest[start..].strip_prefix("{{{{") {
+            instruction.push_str("{{");
+            rest = after;
+            continue;
+        }
+        let (expression, after) = rest[start + 2..].split_once("}}")?;
+        instruction.push_str(&just_value(expression)?);
+        rest = after;
+    }
+    instruction.push_str(&rest.replace('$', "$$"));
+    Some(instruction)
+}
+
+/// Reads a justfile: its recipes become targets, with their dependencies as prerequisites, their
+/// doc comment as description and their parameters as `vars:` set to their default, the variables
+/// and the `dotenv-load` and `shell` settings become those of the Dofile, and the recipes of the
+/// same name for different systems are merged with `@os:` prefixes.
+pub fn justfile(justfile: &str) -> Import {
+    let mut import = Import::default();
+    let mut comments: Vec<String> = Vec::new();
+    let mut attributes: Vec<String> = Vec::new();
+    let mut private: Vec<String> = Vec::new();
+    let (mut dotenv, mut dotenv_required, mut export) = (None, false, false);
+    let mut first = None;
+    let mut lines = justfile.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || line.starts_with([' ', '\t']) {
+            comments.clear();
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            if !comment.starts_with('!') {
+                comments.push(comment.trim().to_string());
+            }
+            continue;
+        }
+        if let Some(list) = trimmed.strip_prefix('[').and_then(|list| list.strip_suffix(']')) {
+            attributes.extend(just_words(&list.replace(',', " ")));
+            continue;
+        }
+        let described = std::mem::take(&mut comments);
+        let words = just_words(trimmed);
+
+        match words.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+            ["set", setting, rest @ ..] => {
+                let value = rest.strip_prefix(&[":="]).unwrap_or(rest).join(" ");
+                match *setting {
+                    "shell" => {
+                        let words = value.trim_matches(['[', ']']).split(',').filter_map(|word| just_value(word.trim())).collect::<Vec<_>>();
+                        import.shell = Some(words.join(" ")).filter(|shell| !shell.is_empty());
+                    }
+                    "dotenv-load" if value != "false" => dotenv = dotenv.or(Some(".env".to_string())),
+                    "dotenv-filename" | "dotenv-path" => dotenv = just_value(&value),
+                    "dotenv-required" if value != "false" => dotenv_required = true,
+                    "export" if value != "false" => export = true,
+                    _ => import.notes.push(format!("left out, as it has no Dofile equivalent: set {}", setting)),
+                }
+                continue;
+            }
+            ["alias", alias, ":=", target] => {
+                let alias = import.target(alias);
+                alias.prerequisites.push(target.to_string());
+                alias.description = vec![format!("Runs `{}`", target)];
+                continue;
+            }
+            [keyword, ..] if matches!(*keyword, "import" | "import?" | "mod" | "mod?" | "unexport") => {
+                import.notes.push(format!("left out, as it has no Dofile equivalent: {}", trimmed));
+                continue;
+            }
+            _ => {}
+        }
+
+        let (exported, assignment) = match trimmed.strip_prefix("export ") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, trimmed),
+        };
+        if let Some((name, expression)) = assignment.split_once(":=").filter(|(name, _)| is_variable_name(name.trim())) {
+            let name = name.trim();
+            let export = if exported { "export " } else { "" };
+            match just_value(expression) {
+                // the value of the environment, or else a default, is that of `?=`
+                Some(value) if value.starts_with(format!("$(or $({}),", name).as_str()) => {
+                    let default = &value[format!("$(or $({}),", name).len()..value.len() - 1];
+                    import.variables.push(format!("{}{} ?= {}", export, name, default).trim_end().to_string());
+                }
+                Some(value) => import.variables.push(format!("{}{} := {}", export, name, value).trim_end().to_string()),
+                None => import.notes.push(format!("left out, as it has no make value: variable `{}`", name)),
+            }
+            continue;
+        }
+
+        let Some(colon) = just_colon(trimmed) else {
+            import.notes.push(format!("left out, as it was not understood: {}", trimmed));
+            continue;
+        };
+        let mut header = just_words(&trimmed[..colon]).into_iter();
+        let name = header.next().unwrap_or_default();
+        let (quiet, name) = match name.strip_prefix('@') {
+            Some(name) => (true, name.to_string()),
+            None => (false, name),
+        };
+        if !is_valid_name(&name) {
+            import.notes.push(format!("left out, as its name is not one of a target: recipe `{}`", name));
+            continue;
+        }
+
+        // the recipe lines, with those carried on by a final `\` joined
+        let mut body: Vec<String> = Vec::new();
+        let mut indent = None;
+        let mut carried = false;
+        while let Some(line) = lines.next_if(|line| line.trim().is_empty() || line.starts_with([' ', '\t'])) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indent = indent.get_or_insert_with(|| line.len() - line.trim_start().len());
+            let line = line.get(*indent..).unwrap_or(line.trim_start());
+            match body.last_mut() {
+                Some(last) if carried => {
+                    last.push('\n');
+                    last.push_str(line);
+                }
+                _ => body.push(line.to_string()),
+            }
+            carried = line.ends_with('\\');
+        }
+
+        let mut platforms: Vec<&str> = Vec::new();
+        let target = import.target(&name);
+        let mut notes = Vec::new();
+        for attribute in std::mem::take(&mut attributes) {
+            let (kind, argument) = match attribute.split_once('(') {
+                Some((kind, argument)) => (kind.to_string(), just_value(argument.trim_end_matches(')'))),
+                None => (attribute.clone(), None),
+            };
+            match (kind.as_str(), argument) {
+                ("private", _) => private.push(name.clone()),
+                ("group", Some(group)) => target.group = Some(group),
+                ("doc", Some(doc)) => target.description = vec![doc],
+                ("linux" | "macos" | "windows", _) => platforms.push(if kind == "linux" { "linux" } else if kind == "macos" { "macos" } else { "windows" }),
+                ("unix", _) => platforms.extend(["linux", "macos"]),
+                _ => notes.push(format!("left out, as it has no Dofile equivalent: attribute `[{}]` of recipe `{}`", attribute, name)),
+            }
+        }
+        if target.description.is_empty() {
+            target.description = described;
+        }
+
+        for parameter in header {
+            let variadic = parameter.starts_with(['+', '*']);
+            let parameter = parameter.trim_start_matches(['+', '*']);
+            let (exported, parameter) = match parameter.strip_prefix('$') {
+                Some(parameter) => (true, parameter),
+                None => (export, parameter),
+            };
+            let (variable, value) = match parameter.split_once('=') {
+                Some((variable, default)) => (variable, just_value(default)),
+                None => (parameter, Some(String::new())),
+            };
+            let Some(value) = value.filter(|_| is_variable_name(variable)) else {
+                notes.push(format!("left out, as it has no make value: parameter `{}` of recipe `{}`", variable, name));
+                continue;
+            };
+            if !parameter.contains('=') || variadic {
+                notes.push(format!("given on the command line, as in `make {} {}=...`: parameter `{}`", name, variable, variable));
+            }
+            target.variables.push(format!("{}{} = {}", if exported { "export " } else { "" }, variable, value).trim_end().to_string());
+        }
+
+        let (dependencies, after) = match trimmed[colon + 1..].split_once("&&") {
+            Some((dependencies, after)) => (dependencies, Some(after.trim())),
+            None => (&trimmed[colon + 1..], None),
+        };
+        for dependency in just_words(dependencies) {
+            let prior = dependency.trim_start_matches('(').split_whitespace().next().unwrap_or_default().trim_end_matches(')').to_string();
+            if dependency.starts_with('(') && dependency.contains(char::is_whitespace) {
+                notes.push(format!("left out, as prerequisites take no arguments: the arguments of `{}` for recipe `{}`", prior, name));
+            }
+            if !target.prerequisites.contains(&prior) {
+                target.prerequisites.push(prior);
+            }
+        }
+        if let Some(after) = after {
+            notes.push(format!("left out, as prerequisites run before: `&& {}` of recipe `{}`", after, name));
+        }
+
+        let mut body = body.into_iter().peekable();
+        // a shebang recipe runs as a single script
+        if let Some(shebang) = body.next_if(|line| line.starts_with("#!")) {
+            target.oneshell = true;
+            let program = shebang.split_whitespace().last().unwrap_or_default().rsplit('/').next().unwrap_or_default();
+            if !matches!(program, "sh" | "bash" | "zsh" | "dash" | "ksh") {
+                notes.push(format!("run by the shell instead of `{}`: recipe `{}`", program, name));
+            }
+        }
+        for line in body {
+            let Some(instruction) = just_instruction(&line) else {
+                notes.push(format!("left out, as it has an interpolation with no make value: `{}` of recipe `{}`", line, name));
+                continue;
+            };
+            // the `@` of a quiet recipe makes the line echoed
+            let instruction = match (quiet && !target.oneshell, instruction.strip_prefix('@')) {
+                (true, Some(echoed)) => echoed.to_string(),
+                (true, None) => format!("@{}", instruction),
+                (false, _) => instruction,
+            };
+            if platforms.is_empty() {
+                target.instructions.push(instruction);
+            } else {
+                target.instructions.extend(platforms.iter().map(|platform| format!("@{}: {}", platform, instruction)));
+            }
+        }
+        import.notes.extend(notes);
+
+        // just runs the first recipe, domake the `help` target
+        if first.is_none() {
+            let index = import.targets.iter().position(|target| target.name == name).unwrap();
+            let target = &import.targets[index];
+            let lists = target.prerequisites.is_empty() && !target.instructions.is_empty() && target.instructions.iter().all(|line| line.contains("just --list") || line.contains("just -l"));
+            if lists {
+                import.targets.remove(index);
+                import.notes.push(format!("left out, as `make help` lists the targets: recipe `{}`", name));
+            }
+            first = Some((!lists).then_some(name));
+        }
+    }
+
+    if let Some(path) = dotenv {
+        import.dotenvs.push((path, !dotenv_required));
+    }
+    if export {
+        for variable in import.variables.iter_mut().filter(|variable| !variable.starts_with("export ")) {
+            variable.insert_str(0, "export ");
+        }
+    }
+    // a private recipe is hidden from `make help` by a leading `_`
+    for name in private.iter().filter(|name| !name.starts_with('_')) {
+        let hidden = format!("_{}", name);
+        for target in &mut import.targets {
+            if target.name == *name {
+                target.name = hidden.clone();
+            }
+            for prior in target.prerequisites.iter_mut().filter(|prior| *prior == name) {
+                *prior = hidden.clone();
+            }
+        }
+    }
+    import.default = first.flatten().map(|name| if private.contains(&name) && !name.starts_with('_') { format!("_{}", name) } else { name });
+    import
+}
+
+/// The make form of a Task text: its `$` escaped and its `{{.NAME}}` templates made references,
+/// with whether it has other templates, which have no make equivalent.
+fn task_text(text: &str) -> (String, bool) {
+    let mut converted = String::new();
+    let mut templated = false;
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        converted.push_str(&rest[..start].replace('$', "$$"));
+        let Some((template, after)) = rest[start + 2..].split_once("}}") else {
+            break;
+        };
+        match template.trim().strip_prefix('.') {
+            Some("TASK") => converted.push_str("$@"),
+            Some("ROOT_DIR" | "TASKFILE_DIR" | "USER_WORKING_DIR") => converted.push_str("$(CURDIR)"),
+            Some(name) if is_variable_name(name) => converted.push_str(format!("$({})", name).as_str()),
+            _ => {
+                templated = true;
+                converted.push_str(&rest[start..start + 4 + template.len()]);
+            }
+        }
+        rest = after;
+    }
+    converted.push_str(&rest.replace('$', "$$"));
+    (converted, templated)
+}
+
+/// The make value of a Task variable, the output of its command for a `sh:` one, with whether it has
+/// templates with no make equivalent.
+fn task_value(value: &Json) -> Option<(String, bool)> {
+    match value {
+        Json::String(text) => Some(task_text(text)),
+        Json::Number(_) | Json::Bool(_) => Some((value.to_string(), false)),
+        Json::Null => Some((String::new(), false)),
+        Json::Object(_) => {
+            let (command, templated) = task_text(value.get("sh")?.as_str()?);
+            Some((format!("$(shell {})", command), templated))
+        }
+        Json::Array(_) => None,
+    }
+}
+
+/// The text of a scalar, which YAML may have read as a boolean or a number.
+pub(crate) fn scalar_text(value: &Json) -> Option<String> {
+    match value {
+        Json::String(text) => Some(text.clone()),
+        Json::Number(_) | Json::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The members of a mapping, none for another value.
+fn entries(value: &Json) -> &[(String, Json)] {
+    match value {
+        Json::Object(members) => members,
+        _ => &[],
+    }
+}
+
+/// The strings of a sequence, or the string itself.
+fn strings(value: Option<&Json>) -> Vec<String> {
+    match value {
+        Some(Json::Array(values)) => values.iter().filter_map(Json::as_str).map(str::to_string).collect(),
+        Some(Json::String(text)) => vec![text.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// The `@os:` and `@arch:` prefixes of the `platforms` of a Task command, one per platform, and
+/// the platforms with no Dofile equivalent.
+fn task_platforms(value: Option<&Json>) -> (Vec<String>, Vec<String>) {
+    let (mut prefixes, mut unknown) = (Vec::new(), Vec::new());
+    for platform in strings(value) {
+        let names = platform.split('/').map(|name| if name == "darwin" { "macos" } else { name }).collect::<Vec<_>>();
+        if names.iter().all(|name| OPERATING_SYSTEMS.contains(name) || ARCHITECTURES.contains(name)) {
+            prefixes.push(names.iter().map(|name| format!("@{}: ", name)).collect());
+        } else {
+            unknown.push(platform);
+        }
+    }
+    (prefixes, unknown)
+}
+
+/// A Task command on several lines as a single instruction, each line carried on to the next one
+/// with a `\`, after a `;` when the shell needs one.
+fn task_command(command: &str) -> String {
+    let lines = command.lines().map(str::trim_end).filter(|line| !line.trim().is_empty()).collect::<Vec<_>>();
+    let mut instruction = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        instruction.push_str(line);
+        if index + 1 < lines.len() {
+            let ends = ["then", "do", "else", "{", "(", "&&", "||", "|", ";", "\\"].iter().any(|end| line.ends_with(end));
+            instruction.push_str(if line.ends_with('\\') { "\n" } else if ends { " \\\n" } else { "; \\\n" });
+        }
+    }
+    instruction
+}
+
+/// Reads a Taskfile of go-task: its tasks become targets, with their `deps` as prerequisites, their
+/// `desc` and `summary` as description, their `cmds` as instructions and their `vars` and `env`
+/// as `vars:`; the variables, environment and `dotenv` files become those of the Dofile. What has no
+/// Dofile equivalent, such as the includes, the templates other than `{{.NAME}}` and the `sources`
+/// of a task, is noted for the conversion report.
+pub fn taskfile(taskfile: &str) -> Result<Import, String> {
+    let root = yaml::parse(taskfile)?;
+    if !matches!(root, Json::Object(_)) {
+        return Err("expected a mapping with `version` and `tasks`".to_string());
+    }
+    let mut import = Import::default();
+    let mut silent = false;
+    let templated = |notes: &mut Vec<String>, what: String| notes.push(format!("kept as is, as its templates have no make equivalent: {}", what));
+
+    for (key, value) in entries(&root) {
+        match key.as_str() {
+            "version" | "tasks" => {}
+            "vars" | "env" => {
+                for (name, value) in entries(value) {
+                    let Some((value, template)) = task_value(value).filter(|_| is_variable_name(name)) else {
+                        import.notes.push(format!("left out, as it has no make value: variable `{}`", name));
+                        continue;
+                    };
+                    if template {
+                        templated(&mut import.notes, format!("variable `{}`", name));
+                    }
+                    let operator = if value.starts_with("$(shell ") { ":=" } else { "=" };
+                    import.variables.push(format!("{}{} {} {}", if key == "env" { "export " } else { "" }, name, operator, value).trim_end().to_string());
+                }
+            }
+            "dotenv" => import.dotenvs.extend(strings(Some(value)).into_iter().map(|path| (path, true))),
+            "includes" => {
+                for (namespace, _) in entries(value) {
+                    import.notes.push(format!("left out, as it is a Taskfile: include `{}`", namespace));
+                }
+            }
+            "silent" => silent = value.as_bool() == Some(true),
+            other => import.notes.push(format!("left out, as it has no Dofile equivalent: `{}`", other)),
+        }
+    }
+
+    let tasks = root.get("tasks").map(entries).unwrap_or_default();
+    // the internal tasks are hidden from `make help` by a leading `_`
+    let name_of = |name: &str| {
+        let internal = tasks.iter().any(|(task, value)| task == name && value.get("internal").and_then(Json::as_bool) == Some(true));
+        let name = name.replace(':', "/");
+        if internal && !name.starts_with('_') { format!("_{}", name) } else { name }
+    };
+    for (task, value) in tasks {
+        let name = name_of(task);
+        if !is_valid_name(&name) || HELPER_TARGETS.contains(&name.as_str()) {
+            import.notes.push(format!("left out, as its name is not one of a target: task `{}`", task));
+            continue;
+        }
+        if task.contains(':') {
+            import.notes.push(format!("renamed `{}`, as it is a target name: task `{}`", name, task));
+        }
+        // a task can be written as its command or the list of its commands
+        let value = match value {
+            Json::String(_) => Json::object([("cmds", Json::Array(vec![value.clone()]))]),
+            Json::Array(_) => Json::object([("cmds", value.clone())]),
+            _ => value.clone(),
+        };
+        let mut notes = Vec::new();
+        let mut target = Target { name: name.clone(), ..Target::default() };
+
+        if let Some(desc) = value.get("desc").and_then(Json::as_str) {
+            target.description.push(desc.to_string());
+        }
+        if let Some(summary) = value.get("summary").and_then(Json::as_str) {
+            let desc = target.description.first().cloned().unwrap_or_default();
+            let lines = summary.trim_end().lines().skip_while(|line| line.trim() == desc.trim());
+            target.description.extend(lines.map(str::to_string));
+        }
+        if value.get("prompt").is_some() {
+            target.prerequisites.push("confirm".to_string());
+        }
+        for dependency in value.get("deps").and_then(Json::as_array).unwrap_or_default() {
+            let Some(prior) = dependency.as_str().or_else(|| dependency.get("task").and_then(Json::as_str)) else {
+                continue;
+            };
+            if dependency.get("vars").is_some() {
+                notes.push(format!("left out, as prerequisites take no variables: the `vars` of `{}` for task `{}`", prior, task));
+            }
+            target.prerequisites.push(name_of(prior));
+        }
+        for (key, export) in [("vars", ""), ("env", "export ")] {
+            for (variable, value) in value.get(key).map(entries).unwrap_or_default() {
+                let Some((value, template)) = task_value(value).filter(|_| is_variable_name(variable)) else {
+                    notes.push(format!("left out, as it has no make value: variable `{}` of task `{}`", variable, task));
+                    continue;
+                };
+                if template {
+                    templated(&mut notes, format!("variable `{}` of task `{}`", variable, task));
+                }
+                target.variables.push(format!("{}{} = {}", export, variable, value).trim_end().to_string());
+            }
+        }
+
+        // what applies to every command of the task
+        let directory = value.get("dir").and_then(Json::as_str).map(|directory| format!("cd {} && ", task_text(directory).0)).unwrap_or_default();
+        let quiet = silent || value.get("silent").and_then(Json::as_bool) == Some(true);
+        let ignored = value.get("ignore_error").and_then(Json::as_bool) == Some(true);
+        let (platforms, unknown) = task_platforms(value.get("platforms"));
+        for platform in unknown {
+            notes.push(format!("left out, as it is not a Dofile platform: `{}` of task `{}`", platform, task));
+        }
+
+        for precondition in value.get("preconditions").and_then(Json::as_array).unwrap_or_default() {
+            let Some(check) = scalar_text(precondition).or_else(|| precondition.get("sh").and_then(scalar_text)) else {
+                continue;
+            };
+            let message = precondition.get("msg").and_then(Json::as_str).map(str::to_string).unwrap_or_else(|| format!("precondition not met: {}", check));
+            let (check, template) = task_text(&check);
+            if template {
+                templated(&mut notes, format!("precondition `{}` of task `{}`", check, task));
+            }
+            let message = task_text(&message).0.replace('\'', "'\\''");
+            target.instructions.push(format!("@{}{} || {{ echo '{}' >&2; exit 1; }}", directory, check, message));
+        }
+
+        for command in value.get("cmds").and_then(Json::as_array).unwrap_or_default() {
+            let (text, quiet, ignored, platforms) = match command {
+                Json::String(_) | Json::Number(_) | Json::Bool(_) => (scalar_text(command).unwrap_or_default(), quiet, ignored, platforms.clone()),
+                Json::Object(_) if command.get("cmd").is_some() => {
+                    let (own, unknown) = task_platforms(command.get("platforms"));
+                    for platform in unknown {
+                        notes.push(format!("left out, as it is not a Dofile platform: `{}` of task `{}`", platform, task));
+                    }
+                    let quiet = command.get("silent").and_then(Json::as_bool).unwrap_or(quiet);
+                    let ignored = command.get("ignore_error").and_then(Json::as_bool).unwrap_or(ignored);
+                    let platforms = if command.get("platforms").is_some() { own } else { platforms.clone() };
+                    (command.get("cmd").and_then(scalar_text).unwrap_or_default(), quiet, ignored, platforms)
+                }
+                Json::Object(_) if command.get("task").is_some() => {
+                    let called = command.get("task").and_then(Json::as_str).unwrap_or_default();
+                    if command.get("vars").is_some() {
+                        notes.push(format!("left out, as a target takes no variables: the `vars` of `{}` in task `{}`", called, task));
+                    }
+                    target.instructions.push(format!("@$(MAKE) --no-print-directory {}", name_of(called)));
+                    continue;
+                }
+                _ => {
+                    let kind = entries(command).first().map(|(key, _)| key.as_str()).unwrap_or("command");
+                    notes.push(format!("left out, as it has no Dofile equivalent: a `{}` of task `{}`", kind, task));
+                    continue;
+                }
+            };
+            let (instruction, template) = task_text(&text);
+            if template {
+                templated(&mut notes, format!("command `{}` of task `{}`", text.trim(), task));
+            }
+            let flags = format!("{}{}", if quiet { "@" } else { "" }, if ignored { "-" } else { "" });
+            let instruction = format!("{}{}{}", flags, directory, task_command(&instruction));
+            if platforms.is_empty() {
+                target.instructions.push(instruction);
+            } else {
+                target.instructions.extend(platforms.iter().map(|prefix| format!("{}{}", prefix, instruction)));
+            }
+        }
+
+        for key in ["sources", "generates", "status", "method", "run", "requires", "label", "watch", "interval", "output", "set", "shopt"] {
+            if value.get(key).is_some() {
+                notes.push(format!("left out, as it has no Dofile equivalent: the `{}` of task `{}`", key, task));
+            }
+        }
+        import.targets.push(target);
+        for alias in strings(value.get("aliases")) {
+            import.targets.push(Target {
+                name: alias.replace(':', "/"),
+                description: vec![format!("Runs `{}`", name)],
+                prerequisites: vec![name.clone()],
+                ..Target::default()
+            });
+        }
+        import.notes.extend(notes);
+    }
+    // Task runs its `default` task when given none
+    import.default = import.targets.iter().find(|target| target.name == "default").map(|target| target.name.clone());
+    Ok(import)
+}
+
+/// The core tasks of cargo-make a `Makefile.toml` can depend on without defining them, with the
+/// command they run.
+const CARGO_MAKE_CORE: &[(&str, &str)] = &[
+    ("build", "cargo build"),
+    ("test", "cargo test"),
+    ("check", "cargo check"),
+    ("clean", "cargo clean"),
+    ("format", "cargo fmt"),
+    ("clippy", "cargo clippy"),
+    ("docs", "cargo doc --no-deps"),
+];
+
+/// The make form of a cargo-make text: its `$` escaped except in its `${NAME}` references, which
+/// make reads the same, with the variables of cargo-make it references but has no value for.
+fn cargo_make_text(text: &str) -> (String, Vec<String>) {
+    let mut converted = String::new();
+    let mut unknown = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        converted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest.strip_prefix("${").and_then(|after| after.split_once('}')).filter(|(name, _)| is_variable_name(name));
+        match reference {
+            Some(("CARGO_MAKE_WORKING_DIRECTORY", after)) => {
+                converted.push_str("$(CURDIR)");
+                rest = after;
+            }
+            Some((name, after)) => {
+                if name.starts_with("CARGO_MAKE_") {
+                    unknown.push(name.to_string());
+                }
+                converted.push_str(format!("${{{}}}", name).as_str());
+                rest = after;
+            }
+            None => {
+                converted.push_str("$$");
+                rest = &rest[1..];
+            }
+        }
+    }
+    converted.push_str(rest);
+    (converted, unknown)
+}
+
+/// An argument of a cargo-make `command`, which runs without a shell, quoted for the shell.
+fn cargo_make_argument(argument: &str) -> String {
+    let unreferenced = argument.split("${").map(|part| part.split_once('}').map(|(_, after)| after).unwrap_or(part)).collect::<String>();
+    if !argument.is_empty() && unreferenced.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)) {
+        argument.to_string()
+    } else {
+        format!("'{}'", argument.replace('\'', "'\\''"))
+    }
+}
+
+/// The make value of a cargo-make environment variable, the output of its script for a `script` one.
+fn cargo_make_value(value: &Json) -> Option<String> {
+    match value {
+        Json::String(_) | Json::Number(_) | Json::Bool(_) => scalar_text(value).map(|text| cargo_make_text(&text).0),
+        Json::Object(_) if value.get("script").is_some() => {
+            let script = strings(value.get("script")).join("; ");
+            Some(format!("$(shell {})", cargo_make_text(&script).0))
+        }
+        Json::Object(_) => value.get("value").and_then(cargo_make_value),
+        _ => None,
+    }
+}
+
+/// Reads a `Makefile.toml` of cargo-make: its tasks become targets, with their `dependencies` as
+/// prerequisites, their `description` as description, their `category` as group and their `command`
+/// and `args` or their `script` as instructions; its `env` and `env_files` become the variables and
+/// `.env` files of the Dofile. The core tasks of cargo-make it depends on without defining them are
+/// given their cargo command, and what has no Dofile equivalent is noted for the report.
+pub fn cargo_make(makefile: &str) -> Result<Import, String> {
+    let root = toml::parse(makefile)?;
+    let mut import = Import::default();
+    for (key, value) in entries(&root) {
+        match key.as_str() {
+            "tasks" => {}
+            "env" => {
+                for (name, value) in entries(value) {
+                    if matches!(value, Json::Object(_)) && value.get("script").is_none() && value.get("value").is_none() {
+                        import.notes.push(format!("left out, as it is an environment profile: `[env.{}]`", name));
+                        continue;
+                    }
+                    let Some(value) = cargo_make_value(value).filter(|_| is_variable_name(name)) else {
+                        import.notes.push(format!("left out, as it has no make value: variable `{}`", name));
+                        continue;
+                    };
+                    let operator = if value.starts_with("$(shell ") { ":=" } else { "=" };
+                    import.variables.push(format!("export {} {} {}", name, operator, value).trim_end().to_string());
+                }
+            }
+            "env_files" => {
+                for file in value.as_array().unwrap_or_default() {
+                    let path = file.as_str().or_else(|| file.get("path").and_then(Json::as_str)).unwrap_or_default();
+                    import.dotenvs.push((path.to_string(), true));
+                }
+            }
+            other => import.notes.push(format!("left out, as it has no Dofile equivalent: `[{}]`", other)),
+        }
+    }
+
+    let tasks = root.get("tasks").map(entries).unwrap_or_default();
+    // the private tasks are hidden from `make help` by a leading `_`
+    let name_of = |name: &str| {
+        let private = tasks.iter().any(|(task, value)| task == name && value.get("private").and_then(Json::as_bool) == Some(true));
+        let name = name.replace(':', "/");
+        if private && !name.starts_with('_') { format!("_{}", name) } else { name }
+    };
+    let mut core: Vec<&str> = Vec::new();
+    for (task, value) in tasks {
+        let name = name_of(task);
+        if value.get("disabled").and_then(Json::as_bool) == Some(true) {
+            import.notes.push(format!("left out, as it is disabled: task `{}`", task));
+            continue;
+        }
+        if !is_valid_name(&name) || HELPER_TARGETS.contains(&name.as_str()) {
+            import.notes.push(format!("left out, as its name is not one of a target: task `{}`", task));
+            continue;
+        }
+        if task.contains(':') {
+            import.notes.push(format!("renamed `{}`, as it is a target name: task `{}`", name, task));
+        }
+        let mut notes = Vec::new();
+        if let Some(alias) = value.get("alias").and_then(Json::as_str) {
+            import.targets.push(Target {
+                name,
+                description: vec![format!("Runs `{}`", name_of(alias))],
+                prerequisites: vec![name_of(alias)],
+                ..Target::default()
+            });
+            continue;
+        }
+        let mut target = Target { name: name.clone(), group: value.get("category").and_then(scalar_text), ..Target::default() };
+        target.description.extend(value.get("description").and_then(scalar_text));
+
+        for dependency in value.get("dependencies").and_then(Json::as_array).unwrap_or_default() {
+            let Some(prior) = dependency.as_str().or_else(|| dependency.get("name").and_then(Json::as_str)) else {
+                continue;
+            };
+            let defined = tasks.iter().any(|(task, _)| task == prior);
+            match CARGO_MAKE_CORE.iter().find(|(core, _)| *core == prior) {
+                _ if defined => {}
+                Some((prior, _)) if !core.contains(prior) => core.push(prior),
+                Some(_) => {}
+                None => {
+                    notes.push(format!("left out, as it is not in the file: dependency `{}` of task `{}`", prior, task));
+                    continue;
+                }
+            }
+            target.prerequisites.push(name_of(prior));
+        }
+        for (variable, value) in value.get("env").map(entries).unwrap_or_default() {
+            match cargo_make_value(value).filter(|_| is_variable_name(variable)) {
+                Some(value) => target.variables.push(format!("export {} = {}", variable, value).trim_end().to_string()),
+                None => notes.push(format!("left out, as it has no make value: variable `{}` of task `{}`", variable, task)),
+            }
+        }
+
+        // what applies to every instruction of the task
+        let directory = value.get("cwd").and_then(Json::as_str).map(|directory| format!("cd {} && ", cargo_make_argument(directory))).unwrap_or_default();
+        let ignored = [value.get("ignore_errors"), value.get("force")].into_iter().flatten().any(|flag| flag.as_bool() == Some(true));
+        let condition = value.get("condition");
+        let mut platforms = strings(condition.and_then(|condition| condition.get("platforms")))
+            .into_iter()
+            .map(|platform| if platform == "mac" { "macos".to_string() } else { platform })
+            .filter(|platform| OPERATING_SYSTEMS.contains(&platform.as_str()))
+            .collect::<Vec<_>>();
+        for (key, _) in condition.map(entries).unwrap_or_default().iter().filter(|(key, _)| key != "platforms") {
+            notes.push(format!("left out, as it has no Dofile equivalent: the `{}` condition of task `{}`", key, task));
+        }
+
+        // the instructions of the task, then those of its overrides for a platform
+        let mut sets = vec![(None, value.clone())];
+        for (key, platform) in [("linux", "linux"), ("mac", "macos"), ("windows", "windows")] {
+            if let Some(platform_task) = value.get(key).filter(|platform_task| ["command", "script", "run_task"].iter().any(|key| platform_task.get(key).is_some())) {
+                sets.push((Some(platform), platform_task.clone()));
+            }
+        }
+        let overridden = sets.iter().filter_map(|(platform, _)| *platform).collect::<Vec<_>>();
+        if !overridden.is_empty() && platforms.is_empty() {
+            platforms = OPERATING_SYSTEMS.iter().map(|platform| platform.to_string()).collect();
+        }
+        for (platform, task_value) in sets {
+            let (instructions, oneshell) = cargo_make_instructions(&task_value, task, &name_of, &mut notes);
+            target.oneshell |= oneshell;
+            let prefixes = match platform {
+                Some(platform) => vec![format!("@{}: ", platform)],
+                None => platforms.iter().filter(|platform| !overridden.contains(&platform.as_str())).map(|platform| format!("@{}: ", platform)).collect(),
+            };
+            for (index, instruction) in instructions.into_iter().enumerate() {
+                // a single shell runs the lines of a script, so the first one sets it up
+                let setup = if index == 0 || !oneshell { format!("{}{}", if ignored { "-" } else { "" }, directory) } else { String::new() };
+                let instruction = format!("{}{}", setup, instruction);
+                if prefixes.is_empty() && platform.is_none() && platforms.is_empty() {
+                    target.instructions.push(instruction);
+                } else {
+                    target.instructions.extend(prefixes.iter().map(|prefix| format!("{}{}", prefix, instruction)));
+                }
+            }
+        }
+
+        for key in ["install_crate", "install_crate_args", "install_script", "workspace", "watch", "extend", "clear", "env_files", "condition_script", "plugin", "deprecated"] {
+            if value.get(key).is_some() {
+                notes.push(format!("left out, as it has no Dofile equivalent: the `{}` of task `{}`", key, task));
+            }
+        }
+        import.targets.push(target);
+        import.notes.extend(notes);
+    }
+
+    for name in core {
+        let command = CARGO_MAKE_CORE.iter().find(|(core, _)| *core == name).map(|(_, command)| *command).unwrap_or_default();
+        import.targets.push(Target {
+            name: name.to_string(),
+            description: vec![format!("Runs `{}`, as the core task of cargo-make", command)],
+            instructions: vec![command.to_string()],
+            ..Target::default()
+        });
+        import.notes.push(format!("given the command of the core task of cargo-make: target `{}`", name));
+    }
+    import.default = import.targets.iter().find(|target| target.name == "default").map(|target| target.name.clone());
+    Ok(import)
+}
+
+/// The instructions of a cargo-make task or of its override for a platform, from its `command`
+/// and `args`, its `script` or its `run_task`, with whether they are a script run by a single shell.
+fn cargo_make_instructions(task: &Json, name: &str, name_of: &dyn Fn(&str) -> String, notes: &mut Vec<String>) -> (Vec<String>, bool) {
+    let mut instructions = Vec::new();
+    let mut unknown = Vec::new();
+    let mut convert = |text: &str| {
+        let (text, references) = cargo_make_text(text);
+        unknown.extend(references);
+        text
+    };
+    let mut oneshell = false;
+    if let Some(command) = task.get("command").and_then(Json::as_str) {
+        let mut words = vec![convert(&cargo_make_argument(command))];
+        if let Some(toolchain) = task.get("toolchain").and_then(Json::as_str) {
+            if command == "cargo" {
+                words.push(format!("+{}", toolchain));
+            } else {
+                notes.push(format!("left out, as it only applies to cargo: the `toolchain` of task `{}`", name));
+            }
+        }
+        words.extend(strings(task.get("args")).iter().map(|argument| convert(&cargo_make_argument(argument))));
+        instructions.push(words.join(" "));
+    } else if let Some(script) = task.get("script") {
+        let runner = task.get("script_runner").and_then(Json::as_str).unwrap_or("@shell");
+        let program = runner.rsplit('/').next().unwrap_or_default();
+        if runner.starts_with('@') && runner != "@shell" {
+            notes.push(format!("left out, as it is not a shell script: the `{}` script of task `{}`", runner, name));
+            return (instructions, false);
+        }
+        if !matches!(program, "@shell" | "sh" | "bash" | "zsh" | "dash" | "ksh") {
+            notes.push(format!("run by the shell instead of `{}`: task `{}`", runner, name));
+        }
+        let lines = match script.get("file").and_then(Json::as_str) {
+            Some(file) => vec![format!("sh {}", cargo_make_argument(file))],
+            None => strings(Some(script)).iter().flat_map(|text| text.lines().map(str::to_string).collect::<Vec<_>>()).collect(),
+        };
+        let lines = lines.into_iter().filter(|line| !line.trim().is_empty() && !line.starts_with("#!")).collect::<Vec<_>>();
+        oneshell = lines.len() > 1;
+        instructions.extend(lines.iter().map(|line| convert(line)));
+    }
+    if let Some(run) = task.get("run_task") {
+        for called in strings(Some(run)).into_iter().chain(strings(run.get("name"))) {
+            instructions.push(format!("@$(MAKE) --no-print-directory {}", name_of(&called)));
+        }
+        for (key, _) in entries(run).iter().filter(|(key, _)| key != "name") {
+            notes.push(format!("left out, as it has no Dofile equivalent: the `{}` of the `run_task` of task `{}`", key, name));
+        }
+    }
+    unknown.dedup();
+    for variable in unknown {
+        notes.push(format!("left empty, as it is set by cargo-make: variable `{}` in task `{}`", variable, name));
+    }
+    (instructions, oneshell)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_dofile, render_makefile};
+
+    #[test]
+    fn the_makefile_of_domake_reads_back() {
+        let content = "[build] lib\n# Builds the project\n# in release mode\n@linux: echo linux\n@macos: @arm64: echo mac\necho done\n\n[lib]\n# Lib\necho lib\n\n[_hidden]\n# Internal\necho hidden\n";
+        let dofile = parse_dofile(content).unwrap();
+        let import = makefile(&render_makefile(&dofile));
+        assert_eq!(import.default, None);
+        assert!(import.raws.is_empty());
+        assert!(import.notes.iter().all(|note| note.contains("domake writes its own")), "{:?}", import.notes);
+
+        let imported = parse_dofile(&import.to_dofile("Makefile")).unwrap();
+        assert_eq!(imported.commands.len(), dofile.commands.len());
+        for (imported, cmd) in imported.commands.iter().zip(&dofile.commands) {
+            assert_eq!((&imported.name, &imported.description, &imported.instructions), (&cmd.name, &cmd.description, &cmd.instructions));
+        }
+    }
+
+    #[test]
+    fn a_rule_with_conditionals_in_its_recipe_stays_a_makefile_rule() {
+        let import = makefile(".PHONY: build\nbuild:\n\techo a\nifeq ($(DEBUG),1)\n\techo debug\nendif\n\techo b\n\nifeq ($(X),1)\nCFLAGS += -g\nendif\n");
+        assert!(import.targets.is_empty());
+        assert_eq!(import.raws, ["ifeq ($(X),1)\nCFLAGS += -g\nendif\n", ".PHONY: build\nbuild:\n\techo a\nifeq ($(DEBUG),1)\n\techo debug\nendif\n\techo b\n"]);
+    }
+
+    #[test]
+    fn only_a_declared_default_goal_is_imported() {
+        let import = makefile("all: build\n\nbuild:\n\techo build\n");
+        assert_eq!(import.default, None);
+        assert!(import.notes.iter().any(|note| note.contains("first target `all`")));
+        assert_eq!(makefile(".DEFAULT_GOAL := build\nall: build\n\nbuild:\n\techo build\n").default.as_deref(), Some("build"));
+    }
+}
@@ -0,0 +1,497 @@
+use std::fmt;
+
+/// A JSON value, enough for the machine-readable outputs of domake.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// The members keep their insertion order.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<const N: usize>(members: [(&str, Json); N]) -> Json {
+        Json::Object(members.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    /// Parses a JSON document.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { text, position: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// The member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(value) if *value >= 0.0 && value.fract() == 0.0 => Some(*value as usize),
+            _ => None,
+        }
+    }
+
+    /// Serializes the value over several lines, indented by two spaces.
+    pub fn pretty(&self) -> String {
+        let mut buffer = String::new();
+        self.write(&mut buffer, Some(0));
+        buffer
+    }
+
+    fn write(&self, buffer: &mut String, indent: Option<usize>) {
+        let newline = |buffer: &mut String, level: usize| {
+            if indent.is_some() {
+                buffer.push('\n');
+                buffer.push_str(&"  ".repeat(level));
+            }
+        };
+        let level = indent.unwrap_or(0);
+        let inner = indent.map(|level| level + 1);
+
+        match self {
+            Json::Null => buffer.push_str("null"),
+            Json::Bool(value) => buffer.push_str(if *value { "true" } else { "false" }),
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => buffer.push_str(&(*value as i64).to_string()),
+            Json::Number(value) if value.is_finite() => buffer.push_str(&value.to_string()),
+            Json::Number(_) => buffer.push_str("null"),
+            Json::String(value) => write_string(buffer, value),
+            Json::Array(values) if values.is_empty() => buffer.push_str("[]"),
+            Json::Array(values) => {
+                buffer.push('[');
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        buffer.push(',');
+                    }
+                    newline(buffer, level + 1);
+                    value.write(buffer, inner);
+                }
+                newline(buffer, level);
+                buffer.push(']');
+            }
+            Json::Object(members) if members.is_empty() => buffer.push_str("{}"),
+            Json::Object(members) => {
+                buffer.push('{');
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        buffer.push(',');
+                    }
+                    newline(buffer, level + 1);
+                    write_string(buffer, key);
+                    buffer.push_str(if indent.is_some() { ": " } else { ":" });
+                    value.write(buffer, inner);
+                }
+                newline(buffer, level);
+                buffer.push('}');
+            }
+        }
+    }
+}
+
+fn write_string(buffer: &mut String, value: &str) {
+    buffer.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            c if (c as u32) < 0x20 => buffer.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buffer.push(c),
+        }
+    }
+    buffer.push('"');
+}
+
+/// The values as the other serde formats read and write them, e.g. to give `serde_json` the
+/// output of `domake export --format json`.
+#[cfg(feature = "serde")]
+mod serialization {
+    use std::fmt;
+    use serde::de::{self, MapAccess, SeqAccess, Visitor};
+    use serde::ser::{SerializeMap, SerializeSeq};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::Json;
+
+    impl Serialize for Json {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Json::Null => serializer.serialize_unit(),
+                Json::Bool(value) => serializer.serialize_bool(*value),
+                Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => serializer.serialize_i64(*value as i64),
+                Json::Number(value) => serializer.serialize_f64(*value),
+                Json::String(value) => serializer.serialize_str(value),
+                Json::Array(values) => {
+                    let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                    for value in values {
+                        seq.serialize_element(value)?;
+                    }
+                    seq.end()
+                }
+                Json::Object(members) => {
+                    let mut map = serializer.serialize_map(Some(members.len()))?;
+                    for (key, value) in members {
+                        map.serialize_entry(key, value)?;
+                    }
+                    map.end()
+                }
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Json {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Json, D::Error> {
+            deserializer.deserialize_any(JsonVisitor)
+        }
+    }
+
+    struct JsonVisitor;
+
+    impl<'de> Visitor<'de> for JsonVisitor {
+        type Value = Json;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a JSON value")
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Json, E> {
+            Ok(Json::Null)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Json, E> {
+            Ok(Json::Null)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Json, D::Error> {
+            Json::deserialize(deserializer)
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> Result<Json, E> {
+            Ok(Json::Bool(value))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Json, E> {
+            Ok(Json::Number(value as f64))
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Json, E> {
+            Ok(Json::Number(value as f64))
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<Json, E> {
+            Ok(Json::Number(value))
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Json, E> {
+            Ok(Json::String(value.to_string()))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+            let mut values = Vec::new();
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(Json::Array(values))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+            let mut members = Vec::new();
+            while let Some(member) = map.next_entry()? {
+                members.push(member);
+            }
+            Ok(Json::Object(members))
+        }
+    }
+}
+
+/// The deepest nesting of arrays and objects read, so that a document cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    text: &'a str,
+    /// The byte offset of the next character.
+    position: usize,
+    /// The arrays and objects the next value is in.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        let before = &self.text[..self.position];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        format!("{} at line {} column {}", message, line, column)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, wanted: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == wanted => Ok(()),
+            _ => Err(self.error(&format!("expected `{}`", wanted))),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        if !self.text[self.position..].starts_with(keyword) {
+            return Err(self.error("unexpected character"));
+        }
+        self.position += keyword.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.nested(Self::array),
+            Some('{') => self.nested(Self::object),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    /// Reads an array or an object with `read`, one level deeper.
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            self.position += 1;
+        }
+        self.text[start..self.position].parse().map(Json::Number).map_err(|_| {
+            self.position = start;
+            self.error("invalid number")
+        })
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => {
+                        let mut code = self.code_unit()?;
+                        // a surrogate pair stands for a single character
+                        if (0xd800..0xdc00).contains(&code) && self.text[self.position..].starts_with("\\u") {
+                            self.position += 2;
+                            let low = self.code_unit()?;
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                        }
+                        value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn code_unit(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.position..self.position + 4).ok_or_else(|| self.error("invalid escape"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid escape"))?;
+        self.position += 4;
+        Ok(code)
+    }
+}
+
+/// The compact serialization, on a single line.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = String::new();
+        self.write(&mut buffer, None);
+        f.write_str(&buffer)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Json {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Json {
+        Json::String(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Json {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_printed_documents_read_back() {
+        let text = r#"{"name": "build", "deps": ["a", "b"], "file": false, "line": 3, "group": null, "text": "tab\t\"quoted\" é 😀"}"#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(json.get("text").and_then(Json::as_str), Some("tab\t\"quoted\" é 😀"));
+        assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+        assert_eq!(Json::parse(&json.pretty()).unwrap(), json);
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        for text in ["", "{", "[1,]", r#"{"a" 1}"#, r#"{a: 1}"#, r#""open"#, r#""\x""#, "tru", "1 2", "-"] {
+            assert!(Json::parse(text).is_err(), "`{}` was read", text);
+        }
+        assert_eq!(Json::parse("[1,\n  x]").unwrap_err(), "unexpected character at line 2 column 3");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_formats_read_the_values() {
+        use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+        use serde::Deserialize;
+        let array = Json::deserialize(SeqDeserializer::<_, Error>::new(["a", "b"].into_iter())).unwrap();
+        assert_eq!(array, Json::parse(r#"["a", "b"]"#).unwrap());
+        let object = Json::deserialize(MapDeserializer::<_, Error>::new([("line", 3u64), ("column", 1)].into_iter())).unwrap();
+        assert_eq!(object, Json::parse(r#"{"line": 3, "column": 1}"#).unwrap());
+    }
+
+    #[test]
+    fn the_nesting_is_limited() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err(), "nested too deeply at line 1 column 129");
+        assert!(Json::parse(&"{\"a\":".repeat(100_000)).is_err());
+    }
+}
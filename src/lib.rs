@@ -0,0 +1,30 @@
+//! The engine of domake: reads a Dofile and renders the Makefile generated from it.
+//!
+//! The parsed model converts to and from [`json::Json`], so that it can be
+//! stored or exchanged with other tools. With the `serde` feature, [`Dofile`] and its
+//! parts (and [`json::Json`] itself) implement `Serialize` and `Deserialize` as well, in the
+//! same shape, for `serde_json`, `serde_yaml` and the other serde formats.
+//!
+//! ```no_run
+//! let dofile = domake::parse_dofile(&std::fs::read_to_string("Dofile").unwrap()).unwrap();
+//! for cmd in &dofile.commands {
+//!     println!("{}: {}", cmd.name, cmd.summary());
+//! }
+//! std::fs::write("Makefile", domake::render_makefile(&dofile)).unwrap();
+//! ```
+
+mod dofile;
+mod makefile;
+mod sha256;
+mod structured;
+mod validate;
+pub mod export;
+pub mod import;
+pub mod json;
+pub mod toml;
+pub mod yaml;
+
+pub use dofile::{block_end, is_valid_name, is_variable_name, parse_dofile, parse_dofile_partial, parse_dofile_partial_with, parse_dofile_with, Assignment, Command, Context, Dofile, ErrorKind, Goal, Include, ParseError, Raw, Shell, Use, Variable, ARCHITECTURES, OPERATING_SYSTEMS};
+pub use structured::{is_structured, parse_structured_dofile, STRUCTURED_DOFILES};
+pub use validate::prerequisite_span;
+pub use makefile::{comparable_lines, generation_date, is_generated, is_timestamp, managed_region, source_hash, merge_makefile, render_makefile, render_makefile_with, rule_targets, unsupported_features, Flavor, MakeVersion, same_line, BEGIN_MARKER, END_MARKER, HEADER, HELPER_TARGETS};
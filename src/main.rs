@@ -1,10 +1,20 @@
+mod cli;
+mod commands;
+mod diff;
+mod output;
+mod report;
+
+use std::collections::HashMap;
 use std::{env, fs};
 use std::env::args;
 use std::fs::File;
 use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use console::{style, Style};
-use regex::Regex;
+use console::style;
+use domake::json::Json;
+use cli::{Invocation, Matches, Subcommand};
+use domake::{is_generated, is_structured, is_timestamp, is_variable_name, parse_dofile_partial_with, parse_structured_dofile, managed_region, merge_makefile, render_makefile_with, same_line, source_hash, generation_date, unsupported_features, Context, Dofile, Flavor, MakeVersion, ParseError, BEGIN_MARKER, END_MARKER, ARCHITECTURES, OPERATING_SYSTEMS, STRUCTURED_DOFILES};
 
 fn description() {
     println!("{} {}",
@@ -13,181 +23,434 @@ fn description() {
       from a custom and simpler file named `Dofile`.").bold().blue())
 }
 
-fn usage() {
-    let title_style = Style::new().bold().green();
-    let text_style = Style::new().bold().cyan();
-    println!("{}\n\
-                {}\n\
-              {}\n\
-                {:18}{}\n\
-                {:18}{}\n\
-              {}\n\
-                {}\n\
-                {}",
-             title_style.apply_to("Usage:"),
-             text_style.apply_to("\tdomake [OPTION]"),
-             title_style.apply_to("Options:"),
-             text_style.apply_to("\t-h, --help"), text_style.apply_to("Prints help information"),
-             text_style.apply_to("\t-v, --version"), text_style.apply_to("Prints version information"),
-             title_style.apply_to("Conditions:"),
-             text_style.apply_to("\t- you need to have a valid `Dofile` in the current directory."),
-             text_style.apply_to("\t- any `Makefile` existent in the current directory will be erased after confirmation."));
-}
-
-fn version() {
+fn version() -> ! {
     println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
     exit(0)
 }
 
+const DEFAULT_DOFILE: &str = "Dofile";
+const DEFAULT_MAKEFILE: &str = "Makefile";
+const STDIN_PATH: &str = "-";
+
 fn main() {
-    let args = args().skip(1).collect::<Vec<_>>();
-    if !args.is_empty() {
-        if args.len() > 1 {
-            error("Too many arguments");
+    match cli::parse(args().skip(1).collect(), commands::ALL, commands::DEFAULT) {
+        Ok(Invocation::Help(command)) => help(command),
+        Ok(Invocation::Version) => version(),
+        Ok(Invocation::Command(matches)) => {
+            if matches.flag("json") {
+                output::use_json();
+            }
+            if matches.flag("quiet") {
+                output::use_quiet();
+            }
+            output::use_verbosity(matches.values("verbose").len());
+            if let Some(directory) = locate(&matches).filter(|_| !output::is_quiet()) {
+                eprintln!("{} {}", style("-> Using the Dofile of").bold().green(), directory.display());
+            }
+            commands::run(&matches)
         }
-        match args.first().unwrap().as_str() {
-            "-v" | "--version" => version(),
-            "-h" | "--help" => help(),
-            _ => error("Wrong argument"),
+        Err(err) => fail(err.command, &err.message),
+    }
+}
+
+fn write(path: &str, makefile: &str) -> Result<(), std::io::Error> {
+    // create the parent directories if needed
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    // the hand-written lines around the part domake wrote are kept
+    let makefile = merge_makefile(&fs::read_to_string(path).unwrap_or_default(), makefile);
+    // written aside then renamed over it, so that the Makefile is never left half written
+    let temporary = format!("{}.tmp", path);
+    let result = File::create(&temporary)
+        .and_then(|mut file| file.write_all(makefile.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&temporary, path));
+    match &result {
+        Ok(_) => log::debug!("wrote {} bytes to `{}`", makefile.len(), path),
+        Err(_) => {
+            let _ = fs::remove_file(&temporary);
         }
     }
+    result
+}
 
-    if is_makefile() {
-        let ok = confirm();
-        if !ok { exit(0); }
+/// Writes the Makefile generated from the Dofile, refusing to replace
+/// a Makefile that wasn't generated by domake; returns whether it was written.
+fn regenerate(dofile: &Dofile, overrides: &Overrides, makefile: &str) -> Result<bool, String> {
+    if let Ok(existing) = fs::read_to_string(makefile) {
+        if !is_generated(&existing) {
+            return Err(format!("`{}` was not generated by domake, run `domake gen` to replace it", makefile));
+        }
+    }
+    let generated = render(dofile, overrides);
+    if is_up_to_date(makefile, &generated) {
+        return Ok(false);
     }
+    write(makefile, &generated).map_err(|err| format!("Cannot write `{}`: {}", makefile, err))?;
+    Ok(true)
+}
+
+/// Renders the Makefile generated from the Dofile, without a date with `--reproducible`, warning of
+/// what the make of `--make-version` doesn't support.
+fn render(dofile: &Dofile, overrides: &Overrides) -> String {
+    if overrides.flavor == Flavor::Gnu {
+        for feature in unsupported_features(dofile, overrides.make_version) {
+            output::warning(&feature);
+        }
+    }
+    let date = (!overrides.reproducible).then(generation_date);
+    render_makefile_with(dofile, date.as_deref(), overrides.flavor, overrides.make_version)
+}
+
+/// Whether the Makefile at `path` was generated from the same Dofile as `makefile`, from the hash
+/// in their header, so that writing it again would only change its date.
+fn is_up_to_date(path: &str, makefile: &str) -> bool {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let dated = |makefile: &str| makefile.lines().any(is_timestamp);
+    source_hash(&existing).is_some_and(|hash| source_hash(makefile) == Some(hash)) && dated(&existing) == dated(makefile)
+}
+
+/// Whether `tool` is an executable of the PATH, like `command -v` checks.
+fn is_installed(tool: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else { return false };
+    env::split_paths(&path).any(|dir| {
+        dir.join(tool).is_file() || (cfg!(windows) && dir.join(format!("{}.exe", tool)).is_file())
+    })
+}
 
-    let file = read_file();
+fn is_makefile(path: &str) -> bool {
+    fs::exists(path).unwrap_or(false)
+}
 
-    match file {
+fn get_pwd() -> String {
+    let path = env::current_dir();
+    match path {
+        Ok(path) => path.to_str().unwrap().to_string(),
+        Err(_) => "NAN".to_string(),
+    }
+}
+
+fn read_file(path: &str) -> Result<String, std::io::Error> {
+    if path == STDIN_PATH {
+        return std::io::read_to_string(std::io::stdin());
+    }
+    fs::read_to_string(path)
+}
+
+/// Moves to the closest parent directory with a Dofile when the command reads the default one and
+/// the current directory has none, like git finds its repository, up to the root of the git
+/// repository (or of the filesystem); returns the directory moved to.
+fn locate(matches: &Matches) -> Option<PathBuf> {
+    let reads_dofile = matches.command.name != "init" && matches.command.flags.iter().any(|flag| flag.long == "file");
+    if !reads_dofile || !matches.values("file").is_empty() || matches.flag("recursive") || matches.value("from-json").is_some() {
+        return None;
+    }
+    let current = env::current_dir().ok()?;
+    let mut directory = current.as_path();
+    while find_dofile(directory).is_none() {
+        if directory.join(".git").exists() {
+            return None;
+        }
+        directory = directory.parent()?;
+    }
+    if directory == current {
+        return None;
+    }
+    env::set_current_dir(directory).ok()?;
+    Some(directory.to_path_buf())
+}
+
+/// Reads the Dofile, exiting with an error message if it cannot be read.
+fn load_dofile(path: &str) -> String {
+    match read_file(path) {
+        Ok(content) => content,
         Err(err) => {
             if err.kind() == ErrorKind::NotFound {
-                println!("{} {}", style("No 'Dofile' found in directory").bold().red(), get_pwd());
-            }
-            error(err.to_string().as_str());
-        },
-        Ok(content) => {
-            println!("{}", style("-> Dofile found").bold().green());
-            let (includes, cmds) = parse(content);
-            println!("{}", style("-> Content parsed").bold().green());
-
-            let res = write((includes, cmds));
-            match res {
-                Ok(_) => {
-                    println!("{}", style("-> Makefile successfully created!").bold().green());
-                }
-                Err(_) => {
-                    println!("Error writing to file!");
-                    exit(2);
+                if path == DEFAULT_DOFILE {
+                    eprintln!("{} {}", style("No 'Dofile' found in directory").bold().red(), get_pwd());
+                } else {
+                    eprintln!("{} {}", style("No Dofile found at").bold().red(), path);
                 }
             }
+            error(err.to_string().as_str());
         }
     }
-    exit(0)
 }
 
-fn write(contents: (Vec<String>, Vec<Command>)) -> Result<(), std::io::Error> {
-    let make_helpers = include_str!("../make_helpers");
-    let (includes, cmds) = contents;
-    let mut file = File::create("Makefile")?;
+/// The name of the Dofile of `directory`: a `Dofile`, or else a structured one
+/// (`Dofile.yml`, `Dofile.yaml` or `Dofile.toml`).
+fn find_dofile(directory: &Path) -> Option<&'static str> {
+    [DEFAULT_DOFILE].into_iter().chain(STRUCTURED_DOFILES).find(|name| directory.join(name).is_file())
+}
 
-    let mut buffer: String = String::new();
-    // add the header
-    buffer.push_str("# This Makefile was done using 'domake'\n");
-    buffer.push_str(format!("# Generated at {}\n", chrono::offset::Local::now().format("%d/%m/%Y")).as_str());
-    buffer.push_str("\n");
+/// The Dofile read when `--file` isn't given, the one of the current directory.
+fn default_dofile() -> &'static str {
+    find_dofile(Path::new(".")).unwrap_or(DEFAULT_DOFILE)
+}
 
-    // add the includes
-    for include in includes {
-        buffer.push_str(format!("include {}\n", include).as_str());
+/// The Dofiles given with `--file`, in order, or the default one.
+fn dofile_paths(matches: &Matches) -> Vec<String> {
+    match matches.values("file") {
+        [] => vec![default_dofile().to_string()],
+        paths => paths.to_vec(),
     }
-    buffer.push_str("\n");
-
-    // add the helpers
-    buffer.push_str(format!("{}\n", make_helpers).as_str());
-    buffer.push_str("\n");
+}
 
-    // add the commands
-    for cmd in cmds {
-        buffer.push_str(format!("{}\n", cmd.to_makefile()).as_str());
+/// Exits when the Dofile at `path` is a structured one, which `command` cannot read or edit
+/// since it works on the lines of the Dofile syntax.
+fn expect_dofile_syntax(command: &Subcommand, path: &str) {
+    if is_structured(path) {
+        abort(format!("`domake {}` works on the Dofile syntax, `{}` is a structured Dofile", command.name, path).as_str(), 1);
     }
+}
 
-    file.write_all(buffer.as_bytes())?;
-    Ok(())
+/// The name of the Dofile at `path` in messages.
+fn source(path: &str) -> &str {
+    if path == STDIN_PATH { "<stdin>" } else { path }
 }
 
-struct Command {
-    name: String,
-    description: String,
-    prior_commands: String,
-    instructions: Vec<String>,
+/// Parses the Dofile read from `path`, exiting with its first mistake, shown in context, if it is malformed.
+fn parse(path: &str, content: &str) -> Dofile {
+    parse_with(path, content, &Context::default())
 }
 
-impl Command {
-    fn to_makefile(&self) -> String {
-        let mut buffer = format!(
-            "## {}: {}\n\
-            .PHONY: {}\n\
-            {}: {}\n",
-            self.name, self.description[1..].trim(),
-            self.name,
-            self.name, self.prior_commands);
+/// Like [`parse`], keeping the sections selected by `context`.
+fn parse_with(path: &str, content: &str, context: &Context) -> Dofile {
+    parse_over(path, content, context, &Dofile::default())
+}
 
-        for instruction in &self.instructions {
-            buffer.push_str(format!("\t{}\n", instruction).as_str());
-        }
-        buffer
+/// Like [`parse_with`], for a Dofile laid over `base`, whose targets its prerequisites
+/// and `default` line may name.
+fn parse_over(path: &str, content: &str, context: &Context, base: &Dofile) -> Dofile {
+    if is_structured(path) {
+        return parse_structured(path, content, base).unwrap_or_else(|err| abort(&err, 1));
+    }
+    let (dofile, errors) = parse_dofile_partial_with(content, context);
+    let Some(err) = first_error(errors, base) else {
+        return dofile;
+    };
+    if output::is_json() {
+        let diagnostic = output::diagnostic(source(path), err.line, err.column, None, false, &err.to_string(), Some(&err.hint()));
+        output::finish(1, "error", vec![("diagnostics", Json::Array(vec![diagnostic]))]);
     }
+    eprintln!("{} {}", style("Error:").bold().red(), style(format!("{}:{}:{}: {}", source(path), err.line, err.column, err)).red());
+    eprint!("{}", report::snippet(content, &report::labels(&err), Some(&err.hint())));
+    exit(1)
 }
 
-fn is_makefile() -> bool {
-    fs::exists("Makefile").unwrap()
+/// Reads the structured Dofile at `path`, checked laid over `base` like a parsed Dofile.
+fn parse_structured(path: &str, content: &str, base: &Dofile) -> Result<Dofile, String> {
+    let dofile = parse_structured_dofile(path, content).map_err(|err| format!("{}: {}", source(path), err))?;
+    let mut whole = base.clone();
+    whole.layer(dofile.clone());
+    whole.check().map_err(|err| format!("{}: {}", source(path), err))?;
+    Ok(dofile)
 }
 
-fn get_pwd() -> String {
-    let path = env::current_dir();
-    match path {
-        Ok(path) => path.to_str().unwrap().to_string(),
-        Err(_) => "NAN".to_string(),
-    }
+/// What the command line changes in the Dofile: its `--profile`, `--target-os`, `--target-arch`,
+/// `--silent-all` and `--define` variables.
+#[derive(Default, PartialEq)]
+struct Overrides {
+    context: Context,
+    /// The operating system and architecture the instructions are restricted to, if given.
+    platforms: Vec<String>,
+    silent: bool,
+    /// Whether the glob patterns of the includes are expanded when generating.
+    expand_includes: bool,
+    /// Whether the date is left out of the header.
+    reproducible: bool,
+    sort: Sort,
+    flavor: Flavor,
+    /// The version of GNU make the Makefile is written for.
+    make_version: MakeVersion,
+    defines: Vec<(String, String)>,
 }
 
-fn read_file() -> Result<String, std::io::Error> {
-    std::fs::read_to_string("Dofile")
+/// The order of the targets in the Makefile, from `--sort`.
+#[derive(Default, PartialEq)]
+enum Sort {
+    /// As they are written in the Dofile.
+    #[default]
+    Source,
+    Name,
+    /// Each target after its prerequisites.
+    Dependencies,
 }
 
-fn parse(content: String) -> (Vec<String>, Vec<Command>) {
-    let re_includes = Regex::new(r"include (?<include>[[:print:]]+)").unwrap();
-
-    let includes: Vec<String> = re_includes.captures_iter(&content).map(|c| {
-        c.name("include").unwrap().as_str().to_string()
-    }).collect::<Vec<String>>();
+/// The `--profile`, `--target-os`, `--target-arch`, `--silent-all`, `--expand-includes`, `--reproducible`, `--sort`
+/// and `--define KEY=VALUE` options, exiting if one is malformed.
+fn overrides(matches: &Matches) -> Overrides {
+    let defines = matches.values("define").iter().map(|define| {
+        match define.split_once('=') {
+            Some((name, value)) if is_variable_name(name) => (name.to_string(), value.to_string()),
+            _ => fail(Some(matches.command), format!("`--define` expects KEY=VALUE, got `{}`", define).as_str()),
+        }
+    }).collect();
+    let mut context = Context { profile: matches.value("profile").map(str::to_string), ..Context::default() };
+    let mut platforms = Vec::new();
+    if let Some(os) = matches.value("target-os") {
+        if !OPERATING_SYSTEMS.contains(&os) {
+            fail(Some(matches.command), format!("Unknown operating system `{}`, expected one of: {}", os, OPERATING_SYSTEMS.join(", ")).as_str());
+        }
+        context.os = os.to_string();
+        platforms.push(context.os.clone());
+    }
+    if let Some(arch) = matches.value("target-arch") {
+        if !ARCHITECTURES.contains(&arch) {
+            fail(Some(matches.command), format!("Unknown architecture `{}`, expected one of: {}", arch, ARCHITECTURES.join(", ")).as_str());
+        }
+        context.arch = arch.to_string();
+        platforms.push(context.arch.clone());
+    }
+    let sort = match matches.value("sort") {
+        None | Some("source") => Sort::Source,
+        Some("name") => Sort::Name,
+        Some("dependencies") => Sort::Dependencies,
+        Some(other) => fail(Some(matches.command), format!("`--sort` expects `source`, `name` or `dependencies`, not `{}`", other).as_str()),
+    };
+    let flavor = match matches.value("flavor") {
+        None => Flavor::Gnu,
+        Some(name) => Flavor::ALL.into_iter().find(|flavor| flavor.name() == name).unwrap_or_else(|| {
+            let names = Flavor::ALL.map(|flavor| format!("`{}`", flavor.name())).join(", ");
+            fail(Some(matches.command), format!("`--flavor` expects one of {}, not `{}`", names, name).as_str())
+        }),
+    };
+    let make_version = match matches.value("make-version") {
+        None => MakeVersion::LATEST,
+        Some(version) => match MakeVersion::parse(version) {
+            Some(version) if version >= MakeVersion::OLDEST => version,
+            Some(_) => fail(Some(matches.command), format!("`--make-version` expects {} or later, not `{}`", MakeVersion::OLDEST, version).as_str()),
+            None => fail(Some(matches.command), format!("`--make-version` expects a version like `3.81` or `4.x`, not `{}`", version).as_str()),
+        },
+    };
+    Overrides {
+        context,
+        platforms,
+        silent: matches.flag("silent-all"),
+        expand_includes: matches.flag("expand-includes"),
+        reproducible: matches.flag("reproducible"),
+        sort,
+        flavor,
+        make_version,
+        defines,
+    }
+}
 
-    let re_commands = Regex::new(r"(?<name>\[[[:print:]]+])(?:\r\n|\n)?(?<prior_commands>[[:print:]]+)?(?:\r\n|\n)(?<description>#[[:print:]]+)(?:\r\n|\n)(?<instructions>(?:[[:print:]]+(?:\r\n|\n)?)+)").unwrap();
+/// The first of the mistakes of a Dofile laid over `base`, leaving out the targets it names that `base` defines.
+fn first_error(errors: Vec<ParseError>, base: &Dofile) -> Option<ParseError> {
+    let defined = |name: &str| base.commands.iter().any(|cmd| cmd.name == name) || base.raws.iter().any(|raw| raw.targets().contains(&name));
+    errors.into_iter().find(|err| match &err.kind {
+        domake::ErrorKind::UndefinedPrerequisite(name, _) | domake::ErrorKind::UndefinedDefault(name, _) => !defined(name),
+        _ => true,
+    })
+}
 
-    let commands: Vec<Command> = re_commands.captures_iter(&content).map(|c| {
+/// Parses the Dofile for the overrides, then loads its `.env` files and sets the variables given
+/// on the command line, as it is for generating the Makefile or running its targets.
+fn prepare(path: &str, content: &str, overrides: &Overrides) -> Dofile {
+    prepare_over(path, content, overrides, &Dofile::default())
+}
 
-        let name = c.name("name").unwrap().as_str().trim_start_matches("[").trim_end_matches("]").to_string();
-        let prior_commands = c.name("prior_commands").map(|m| m.as_str().to_string()).unwrap_or_default();
-        let description = c.name("description").unwrap().as_str().to_string();
-        let all_instructions = c.name("instructions").unwrap().as_str().to_string();
-        let instructions = all_instructions.split('\n').map(|i| i.to_string()).collect::<Vec<_>>();
+/// Like [`prepare`], where the targets of `base` count as defined prerequisites, e.g. a target
+/// removed with `--force` that others still depend on.
+fn prepare_over(path: &str, content: &str, overrides: &Overrides, base: &Dofile) -> Dofile {
+    let mut dofile = load(path, content, &overrides.context, base);
+    customize(&mut dofile, overrides);
+    dofile
+}
 
-        Command {
-            name,
-            prior_commands,
-            description,
-            instructions
+/// Like [`prepare`], for the Dofiles given with `--file`, each one laid over the ones before it:
+/// its targets replace those of the same name, reporting each one replaced.
+fn prepare_all(paths: &[String], overrides: &Overrides) -> Dofile {
+    let mut dofile = Dofile::default();
+    let mut origins: HashMap<String, &str> = HashMap::new();
+    for path in paths {
+        let layer = load(path, &load_dofile(path), &overrides.context, &dofile);
+        let names = layer.commands.iter().map(|cmd| cmd.name.clone()).collect::<Vec<_>>();
+        for name in dofile.layer(layer) {
+            let warning = format!("target `{}` of `{}` replaces the one of `{}`", name, source(path), source(origins[&name]));
+            output::warning(&warning);
         }
-    }).collect::<Vec<Command>>();
+        origins.extend(names.into_iter().map(|name| (name, path.as_str())));
+    }
+    customize(&mut dofile, overrides);
+    dofile
+}
+
+/// Reads the model of a Dofile from the JSON at `path`, as `domake export --format json` writes
+/// it, checks it like a parsed Dofile, then loads it like [`prepare`].
+fn prepare_json(path: &str, overrides: &Overrides) -> Dofile {
+    let content = read_file(path).unwrap_or_else(|err| abort(format!("Cannot read `{}`: {}", source(path), err).as_str(), 2));
+    let json = Json::parse(&content).unwrap_or_else(|err| abort(format!("Cannot read `{}`: {}", source(path), err).as_str(), 1));
+    let mut dofile = Dofile::try_from(&json)
+        .and_then(|dofile| dofile.check().map(|_| dofile))
+        .unwrap_or_else(|err| abort(format!("Invalid model in `{}`: {}", source(path), err).as_str(), 1));
+    if let Err(err) = dofile.load_uses(&overrides.context) {
+        abort(&err, 1);
+    }
+    if let Err(err) = dofile.load_dotenvs() {
+        abort(&err, 1);
+    }
+    customize(&mut dofile, overrides);
+    dofile
+}
+
+/// Parses the Dofile laid over `base` for `context`, then merges the Dofiles it uses and loads its `.env` files.
+fn load(path: &str, content: &str, context: &Context, base: &Dofile) -> Dofile {
+    let mut dofile = parse_over(path, content, context, base);
+    if let Err(err) = dofile.load_uses(context) {
+        abort(&err, 1);
+    }
+    if let Err(err) = dofile.load_dotenvs() {
+        abort(&err, 1);
+    }
+    dofile
+}
 
-    (includes, commands)
+/// Applies the command line overrides to the Dofile, once loaded.
+fn customize(dofile: &mut Dofile, overrides: &Overrides) {
+    for platform in &overrides.platforms {
+        dofile.restrict_to(platform);
+    }
+    if overrides.silent {
+        dofile.silence_all();
+    }
+    if overrides.expand_includes {
+        dofile.expand_includes();
+    }
+    match overrides.sort {
+        Sort::Source => {}
+        Sort::Name => dofile.sort_by_name(),
+        Sort::Dependencies => dofile.sort_by_dependencies(),
+    }
+    for (name, value) in &overrides.defines {
+        dofile.define(name, value);
+    }
 }
 
-fn confirm() -> bool {
-    let intro = style("A Makefile has been found in the current directory.\n\
-        Do you want to overwrite it?").bold().yellow();
-    let warning = style("(you will lose all data previously present in the Makefile)").bold().red();
+fn confirm(path: &str, makefile: &str) -> bool {
+    // preview what is going to change
+    let existing = fs::read_to_string(path).ok();
+    if let Some(existing) = &existing {
+        let makefile = merge_makefile(existing, makefile);
+        let (old, new) = (existing.lines().collect::<Vec<_>>(), makefile.lines().collect::<Vec<_>>());
+        let hunks = diff::hunks(&old, &new, 3, same_line);
+        if hunks.is_empty() {
+            println!("{}", style("-> The Makefile is already up to date").bold().green());
+        } else {
+            diff::print(path, "generated", &hunks);
+        }
+        println!();
+    }
+
+    let intro = style(format!("A Makefile has been found at `{}`.\n\
+        Do you want to overwrite it?", path)).bold().yellow();
+    let warning = match existing.as_deref().and_then(managed_region) {
+        Some(_) => style(format!("(the lines outside `{}` and `{}` are kept)", BEGIN_MARKER, END_MARKER)).bold().green(),
+        None => style("(you will lose all data previously present in the Makefile)".to_string()).bold().red(),
+    };
     let options = style("> [y/N]").bold().blue();
 
     print!("{} {}\n{} ", intro, warning, options);
@@ -195,26 +458,37 @@ fn confirm() -> bool {
 
     let mut choice = String::new();
     let res = std::io::stdin().read_line(&mut choice);
-    if let Err(_) = res {
+    if res.is_err() {
         error("Failed to read input from stdin");
-        return false;
-    }
-    match choice.trim().to_lowercase().as_str() {
-        "y" | "yes" => { true }
-        _ => { false }
     }
+    matches!(choice.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
-fn error(err: &str) {
-    println!("{} {}", style("Error:").bold().red(), style(err).red());
+fn error(err: &str) -> ! {
+    fail(None, err)
+}
+
+fn fail(command: Option<&Subcommand>, err: &str) -> ! {
+    if output::is_json() {
+        output::finish(1, "error", vec![("error", err.into())]);
+    }
+    eprintln!("{} {}", style("Error:").bold().red(), style(err).red());
     println!();
-    usage();
+    cli::usage(commands::ALL, command);
     exit(1);
 }
 
-fn help() {
+fn abort(err: &str, code: i32) -> ! {
+    if output::is_json() {
+        output::finish(code, "error", vec![("error", err.into())]);
+    }
+    eprintln!("{} {}", style("Error:").bold().red(), style(err).red());
+    exit(code);
+}
+
+fn help(command: Option<&Subcommand>) -> ! {
     description();
     println!();
-    usage();
+    cli::usage(commands::ALL, command);
     exit(0);
 }
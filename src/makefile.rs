@@ -0,0 +1,659 @@
+use crate::dofile::{is_glob, platform_variable, restrictions};
+use std::env;
+use std::ops::Range;
+use crate::json::Json;
+use crate::sha256::sha256;
+use crate::{Assignment, Command, Dofile, Variable};
+
+/// The first line of every generated Makefile, after [`BEGIN_MARKER`].
+pub const HEADER: &str = "# This Makefile was done using 'domake'";
+
+/// The start of the header line with the hash of what the Makefile is generated from.
+const SOURCE_HASH: &str = "# domake-source-sha256: ";
+
+/// The line opening the part of the Makefile that domake writes; the lines before it are kept.
+pub const BEGIN_MARKER: &str = "# BEGIN DOMAKE";
+
+/// The line closing the part of the Makefile that domake writes; the lines after it are kept.
+pub const END_MARKER: &str = "# END DOMAKE";
+
+/// The targets defined by the helpers prepended to every Makefile.
+pub const HELPER_TARGETS: &[&str] = &["help", "confirm"];
+
+/// Sets `DOMAKE_OS` to the operating system make runs on, as named in `@os:` prefixes.
+pub(crate) const OS_DETECTION: &str = "\
+ifeq ($(OS),Windows_NT)
+DOMAKE_OS := windows
+else ifeq ($(shell uname -s),Darwin)
+DOMAKE_OS := macos
+else
+DOMAKE_OS := linux
+endif
+";
+
+/// Sets `DOMAKE_ARCH` to the architecture make runs on, as named in `@arch:` prefixes.
+pub(crate) const ARCH_DETECTION: &str = "\
+ifeq ($(OS),Windows_NT)
+DOMAKE_ARCH := $(if $(filter ARM64,$(PROCESSOR_ARCHITECTURE)),arm64,amd64)
+else ifneq ($(filter arm64 aarch64,$(shell uname -m)),)
+DOMAKE_ARCH := arm64
+else
+DOMAKE_ARCH := amd64
+endif
+";
+
+/// The make a Makefile is written for, from `--flavor`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Flavor {
+    /// GNU make, with its conditionals, functions and special targets, and the helpers.
+    #[default]
+    Gnu,
+    /// Microsoft nmake, which has none of them and runs the instructions with `cmd.exe`.
+    Nmake,
+    /// The make of the BSD base systems, with its own `.include` and `.if` directives.
+    Bsd,
+}
+
+impl Flavor {
+    pub const ALL: [Flavor; 3] = [Flavor::Gnu, Flavor::Nmake, Flavor::Bsd];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flavor::Gnu => "gnu",
+            Flavor::Nmake => "nmake",
+            Flavor::Bsd => "bsd",
+        }
+    }
+}
+
+/// The version of GNU make a Makefile is written for, from `--make-version`, as its major and
+/// minor numbers.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct MakeVersion(pub u32, pub u32);
+
+impl MakeVersion {
+    /// The oldest version supported, the one of macOS.
+    pub const OLDEST: MakeVersion = MakeVersion(3, 81);
+    /// Any version, with everything domake writes.
+    pub const LATEST: MakeVersion = MakeVersion(u32::MAX, u32::MAX);
+    /// `.ONESHELL` and `.SHELLFLAGS`.
+    const ONESHELL: MakeVersion = MakeVersion(3, 82);
+    /// The `$(file ...)` function.
+    const FILE_FUNCTION: MakeVersion = MakeVersion(4, 0);
+    /// The grouped targets, `a b &: c`.
+    const GROUPED_TARGETS: MakeVersion = MakeVersion(4, 3);
+    /// `.NOTPARALLEL` with targets, instead of for the whole Makefile.
+    const NOTPARALLEL_TARGETS: MakeVersion = MakeVersion(4, 4);
+
+    /// The version of `3.81`, `4.4` or `4.x`, the latest of a major version.
+    pub fn parse(version: &str) -> Option<MakeVersion> {
+        let (major, minor) = version.split_once('.')?;
+        let minor = if minor == "x" { u32::MAX } else { minor.parse().ok()? };
+        Some(MakeVersion(major.parse().ok()?, minor))
+    }
+}
+
+impl Default for MakeVersion {
+    fn default() -> Self {
+        MakeVersion::LATEST
+    }
+}
+
+impl std::fmt::Display for MakeVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            &MakeVersion::LATEST => write!(f, "latest"),
+            MakeVersion(major, u32::MAX) => write!(f, "{}.x", major),
+            MakeVersion(major, minor) => write!(f, "{}.{}", major, minor),
+        }
+    }
+}
+
+/// What the Dofile needs that GNU make `version` doesn't support, each left out of the Makefile
+/// (or copied as is, for the raw blocks) with its consequence.
+pub fn unsupported_features(dofile: &Dofile, version: MakeVersion) -> Vec<String> {
+    let mut features = Vec::new();
+    if version < MakeVersion::ONESHELL {
+        if dofile.oneshell && !dofile.raws.is_empty() {
+            features.push(format!("make {} has no `.ONESHELL`, the rules of the raw blocks run each line in its own shell", version));
+        }
+        if dofile.shell.as_ref().is_some_and(|shell| shell.arguments().len() > 1) {
+            features.push(format!("make {} has no `.SHELLFLAGS`, the flags of the shell are written in `SHELL`", version));
+        }
+    }
+    // a `notparallel` line runs the whole Makefile one target at a time already
+    if version < MakeVersion::NOTPARALLEL_TARGETS && !dofile.notparallel {
+        for cmd in dofile.commands.iter().filter(|cmd| cmd.notparallel) {
+            features.push(format!("make {} reads `.NOTPARALLEL: {}` as for the whole Makefile, so it is left out and the prerequisites of `{}` may be made in parallel with `-j` (a `notparallel` line runs every target one at a time)", version, cmd.name, cmd.name));
+        }
+    }
+    for raw in &dofile.raws {
+        if version < MakeVersion::FILE_FUNCTION && raw.text.contains("$(file ") {
+            features.push(format!("make {} has no `$(file ...)`, used in the raw block at line {}", version, raw.line));
+        }
+        if version < MakeVersion::GROUPED_TARGETS && raw.text.contains("&:") {
+            features.push(format!("make {} has no grouped targets (`&:`), used in the raw block at line {}", version, raw.line));
+        }
+    }
+    features
+}
+
+/// Whether the line is the generation timestamp of the header.
+pub fn is_timestamp(line: &str) -> bool {
+    line.starts_with("# Generated at ")
+}
+
+/// Whether the Makefile was generated by domake, from its header.
+pub fn is_generated(makefile: &str) -> bool {
+    makefile.lines().any(|line| line == HEADER)
+}
+
+/// The hash of what the Makefile was generated from, written in its header: the Dofile once
+/// prepared, with its used Dofiles and variables, and the version of domake.
+pub fn source_hash(makefile: &str) -> Option<&str> {
+    makefile.lines().find_map(|line| line.strip_prefix(SOURCE_HASH))
+}
+
+/// Whether two Makefile lines are the same, regardless of when they were generated.
+pub fn same_line(a: &str, b: &str) -> bool {
+    a == b || (is_timestamp(a) && is_timestamp(b))
+}
+
+/// The lines of a generated Makefile that depend only on the Dofile,
+/// i.e. without the generation timestamp.
+pub fn comparable_lines(makefile: &str) -> Vec<&str> {
+    makefile.lines().filter(|line| !is_timestamp(line)).collect()
+}
+
+/// The targets of the rules of Makefile text, leaving out the special and pattern ones.
+pub fn rule_targets(makefile: &str) -> Vec<&str> {
+    makefile.lines()
+        .filter(|line| !line.starts_with(char::is_whitespace) && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':').filter(|(_, rest)| !rest.starts_with('=')))
+        .flat_map(|(targets, _)| targets.split_whitespace())
+        .filter(|name| !name.starts_with('.') && !name.contains(['%', '$', '(', '=']))
+        .collect()
+}
+
+/// The bytes of the part of the Makefile that domake writes, from its `# BEGIN DOMAKE` line to
+/// its `# END DOMAKE` line included, if it has both.
+pub fn managed_region(makefile: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    let mut start = None;
+    for line in makefile.split_inclusive('\n') {
+        match line.trim_end() {
+            BEGIN_MARKER if start.is_none() => start = Some(offset),
+            END_MARKER if start.is_some() => return start.map(|start| start..offset + line.len()),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The generated Makefile in place of the part domake wrote in the existing one, keeping the
+/// hand-written lines around it; the whole generated Makefile if the existing one has no such part.
+pub fn merge_makefile(existing: &str, generated: &str) -> String {
+    match managed_region(existing) {
+        Some(region) => format!("{}{}{}", &existing[..region.start], generated, &existing[region.end..]),
+        None => generated.to_string(),
+    }
+}
+
+/// The comment opening a section of the Makefile, like those of the helpers.
+fn banner(title: &str) -> String {
+    let rule = format!("# {} #", "=".repeat(83));
+    format!("{}\n# {}\n{}\n\n", rule, title.to_uppercase(), rule)
+}
+
+/// The date written in the header: the one of `SOURCE_DATE_EPOCH` (in seconds, UTC) if it is set,
+/// so that builds are reproducible, else today.
+pub fn generation_date() -> String {
+    let epoch = env::var("SOURCE_DATE_EPOCH").ok().and_then(|seconds| seconds.trim().parse::<i64>().ok());
+    match epoch.and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0)) {
+        Some(date) => date.format("%d/%m/%Y").to_string(),
+        None => chrono::offset::Local::now().format("%d/%m/%Y").to_string(),
+    }
+}
+
+/// Renders the Makefile generated from the Dofile, with its header, includes, variables, raw blocks and helpers.
+pub fn render_makefile(dofile: &Dofile) -> String {
+    render_makefile_with(dofile, Some(&generation_date()), Flavor::Gnu, MakeVersion::LATEST)
+}
+
+/// The header of the Makefile, between the markers and its first line, with `date` if any and
+/// the hash of what it is generated from, for the make of `variant` if not the latest GNU make.
+fn header(dofile: &Dofile, date: Option<&str>, variant: &str) -> String {
+    let mut buffer = format!("{}\n{}\n", BEGIN_MARKER, HEADER);
+    if let Some(date) = date {
+        buffer.push_str(format!("# Generated at {}\n", date).as_str());
+    }
+    let mut source = format!("{}\n{}", env!("CARGO_PKG_VERSION"), Json::from(dofile));
+    // so that the Makefile for another make isn't up to date
+    if !variant.is_empty() {
+        source.push_str(format!("\n{}", variant).as_str());
+    }
+    buffer.push_str(format!("{}{}\n", SOURCE_HASH, sha256(source.as_bytes())).as_str());
+    buffer.push('\n');
+    buffer
+}
+
+/// Like [`render_makefile`], with `date` in the header, or no date at all, so that the same
+/// Dofile always renders the same Makefile, and for the make of `flavor`, in `version` for GNU
+/// make, leaving out the [`unsupported_features`].
+pub fn render_makefile_with(dofile: &Dofile, date: Option<&str>, flavor: Flavor, version: MakeVersion) -> String {
+    match flavor {
+        Flavor::Nmake => return render_nmake(dofile, date),
+        Flavor::Bsd => return render_bsd(dofile, date),
+        Flavor::Gnu => {}
+    }
+    let make_helpers = include_str!("../make_helpers");
+
+    // add the header
+    let variant = if version == MakeVersion::LATEST { String::new() } else { format!("gnu {}", version) };
+    let mut buffer = header(dofile, date, &variant);
+
+    // add the includes
+    for include in &dofile.includes {
+        // make ignores a missing file after `-include`
+        let keyword = if include.optional { "-include" } else { "include" };
+        // make fails on a pattern matching no file, unlike on an empty `$(wildcard)`
+        if is_glob(&include.path) {
+            buffer.push_str(format!("{} $(wildcard {})\n", keyword, include.path).as_str());
+        } else {
+            buffer.push_str(format!("{} {}\n", keyword, include.path).as_str());
+        }
+    }
+    buffer.push('\n');
+
+    // detect the platform if some instructions are restricted to one
+    let platforms = dofile.commands.iter().flat_map(|cmd| &cmd.instructions)
+        .flat_map(|instruction| restrictions(instruction).0)
+        .map(platform_variable)
+        .collect::<Vec<_>>();
+    if platforms.contains(&"DOMAKE_OS") {
+        buffer.push_str(OS_DETECTION);
+        buffer.push('\n');
+    }
+    if platforms.contains(&"DOMAKE_ARCH") {
+        buffer.push_str(ARCH_DETECTION);
+        buffer.push('\n');
+    }
+
+    if let Some(shell) = &dofile.shell {
+        if version < MakeVersion::ONESHELL {
+            // make runs `$(SHELL) -c`, splitting it into words
+            let program = if shell.name.contains('/') { shell.name.clone() } else { format!("/usr/bin/env {}", shell.name) };
+            let flags = shell.arguments().into_iter().filter(|argument| *argument != "-c").map(|flag| format!(" {}", flag)).collect::<String>();
+            buffer.push_str(format!("SHELL := {}{}\n", program, flags).as_str());
+        } else {
+            buffer.push_str(&shell.to_makefile());
+        }
+        buffer.push('\n');
+    }
+    // the targets are already written as single lines, this is for the rules of the raw blocks
+    if dofile.oneshell && version >= MakeVersion::ONESHELL {
+        buffer.push_str(".ONESHELL:\n\n");
+    }
+    if dofile.notparallel {
+        buffer.push_str(".NOTPARALLEL:\n\n");
+    }
+
+    // add the variables, after the includes so that they can use what is included
+    if !dofile.variables.is_empty() {
+        for variable in &dofile.variables {
+            buffer.push_str(format!("{}\n", variable.to_makefile()).as_str());
+        }
+        buffer.push('\n');
+    }
+
+    // add the raw blocks as they are, before the targets that may use what they define
+    for raw in &dofile.raws {
+        buffer.push_str(format!("{}\n\n", raw.text.trim_end()).as_str());
+    }
+
+    // the helpers come first, so plain `make` would run `help` otherwise
+    if let Some(goal) = &dofile.default {
+        buffer.push_str(format!(".DEFAULT_GOAL := {}\n\n", goal.name).as_str());
+    }
+
+    // add the helpers
+    buffer.push_str(format!("{}\n", make_helpers).as_str());
+    buffer.push('\n');
+
+    // add the commands, the ungrouped ones first, then each group in the order it first appears
+    let mut groups = vec![None];
+    for cmd in &dofile.commands {
+        if !groups.contains(&cmd.group.as_deref()) {
+            groups.push(cmd.group.as_deref());
+        }
+    }
+    for group in groups {
+        if let Some(name) = group {
+            buffer.push_str(&banner(name));
+            // a heading in `make help`
+            buffer.push_str(format!("##\n## [{}]\n\n", name).as_str());
+        }
+        for cmd in dofile.commands.iter().filter(|cmd| cmd.group.as_deref() == group) {
+            let mut rule = cmd.to_makefile();
+            // before 4.4, it would make the whole Makefile run one target at a time
+            if version < MakeVersion::NOTPARALLEL_TARGETS {
+                rule = rule.replace(&format!(".NOTPARALLEL: {}\n", cmd.name), "");
+            }
+            buffer.push_str(format!("{}\n", rule).as_str());
+        }
+    }
+    buffer.push_str(format!("{}\n", END_MARKER).as_str());
+    buffer
+}
+
+/// The variable assignment for nmake, which only has `=`: the environment is checked for `?=`,
+/// and an exported variable is set in the environment of the instructions.
+fn nmake_variable(variable: &Variable) -> String {
+    let assignment = match variable.assignment {
+        Assignment::Conditional => format!("!IFNDEF {0}\n{0} = {1}\n!ENDIF", variable.name, variable.value),
+        Assignment::Append => format!("{0} = $({0}) {1}", variable.name, variable.value),
+        _ => format!("{} = {}", variable.name, variable.value),
+    };
+    let mut buffer = assignment.trim_end().to_string();
+    if variable.exported {
+        // the command is run while nmake reads the Makefile
+        buffer.push_str(format!("\n!IF [set {0}=$({0})]\n!ENDIF", variable.name).as_str());
+    }
+    buffer
+}
+
+/// The extensions of the source and of the target of a pattern rule like `[%.o] %.c`, written as
+/// a suffix rule `.c.o:` by nmake and BSD make, which have no pattern rules.
+fn suffix_rule(cmd: &Command) -> Option<(String, String)> {
+    let extension = |pattern: &str| pattern.strip_prefix("%.").filter(|extension| !extension.contains(['/', '%'])).map(str::to_string);
+    Some((extension(cmd.prior_commands.first()?)?, extension(&cmd.name)?))
+}
+
+/// The instruction with the automatic variables `$<` and `$^` replaced by `first` and `all`,
+/// leaving those escaped by `$$` for the shell.
+fn replace_automatic(instruction: &str, first: &str, all: &str) -> String {
+    let mut buffer = String::new();
+    let mut chars = instruction.chars();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            buffer.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('<') => buffer.push_str(first),
+            Some('^') => buffer.push_str(all),
+            Some(next) => {
+                buffer.push('$');
+                buffer.push(next);
+            }
+            None => buffer.push('$'),
+        }
+    }
+    buffer
+}
+
+/// The rule of the target for nmake: its checks run with `cmd.exe`, and a pattern rule becomes
+/// an inference rule, when it only changes the extension of a file in the same directory.
+fn nmake_command(cmd: &Command) -> String {
+    let marker = if cmd.is_hidden() { "#" } else { "##" };
+    let mut buffer = format!("{} {}: {}\n", marker, cmd.name, cmd.summary());
+    for line in cmd.description.lines().skip(1) {
+        buffer.push_str(format!("# {}\n", line).trim_end());
+        buffer.push('\n');
+    }
+    for variable in &cmd.variables {
+        buffer.push_str(format!("# left out, as nmake has no target-specific variables: {}\n", variable.to_makefile()).as_str());
+    }
+    if cmd.oneshell {
+        buffer.push_str("# the instructions run one at a time, as nmake has no single shell for them\n");
+    }
+
+    let prerequisites = cmd.prior_commands.iter().map(|prior| format!(" {}", prior)).collect::<String>();
+    if cmd.is_pattern() {
+        match suffix_rule(cmd) {
+            // nmake only infers the extensions listed in `.SUFFIXES`, which this adds to
+            Some((source, target)) => buffer.push_str(format!(".SUFFIXES: .{1} .{0}\n.{0}.{1}:\n", source, target).as_str()),
+            None => return format!("# left out, as nmake has no pattern rules: {}:{}\n", cmd.name, prerequisites),
+        }
+    } else {
+        buffer.push_str(format!("{}:{}\n", cmd.name, prerequisites).as_str());
+    }
+
+    if let Some(advice) = &cmd.deprecated {
+        let advice = if advice.is_empty() { String::new() } else { format!(", {}", advice) };
+        buffer.push_str(format!("\t@echo Warning: target `{}` is deprecated{} 1>&2\n", cmd.name, advice).as_str());
+    }
+    for tool in &cmd.requires {
+        buffer.push_str(format!("\t@where {} >nul 2>&1 || (echo {}: `{}` is required but was not found in the PATH 1>&2 && exit 1)\n", tool, cmd.name, tool).as_str());
+    }
+    for instruction in &cmd.instructions {
+        let (names, instruction) = restrictions(instruction);
+        // nmake only runs on Windows
+        if names.iter().any(|name| platform_variable(name) == "DOMAKE_OS" && *name != "windows") {
+            continue;
+        }
+        let command = instruction.trim_start_matches(['@', '-', '+']);
+        let prefix = instruction[..instruction.len() - command.len()].replace('+', "");
+        let command = replace_automatic(command, "$<", "$**");
+        let conditions = names.iter()
+            .filter(|name| platform_variable(name) == "DOMAKE_ARCH")
+            .map(|name| format!("if \"$(DOMAKE_ARCH)\"==\"{}\" ", name))
+            .collect::<String>();
+        buffer.push_str(format!("\t{}{}{}\n", prefix, conditions, command.replace('\n', "\n\t")).as_str());
+    }
+    buffer
+}
+
+/// Renders the Makefile for Microsoft nmake: its `!INCLUDE` and `!IF` directives replace those
+/// of GNU make, the targets are never `.PHONY` as nmake always makes a missing file, and the
+/// helpers, the shell and the special targets of GNU make are left out.
+fn render_nmake(dofile: &Dofile, date: Option<&str>) -> String {
+    let mut buffer = header(dofile, date, Flavor::Nmake.name());
+    for include in &dofile.includes {
+        if is_glob(&include.path) {
+            buffer.push_str(format!("# left out, as nmake has no glob patterns: include {}\n", include.path).as_str());
+        } else if include.optional {
+            buffer.push_str(format!("!IF EXIST({0})\n!INCLUDE {0}\n!ENDIF\n", include.path).as_str());
+        } else {
+            buffer.push_str(format!("!INCLUDE {}\n", include.path).as_str());
+        }
+    }
+    buffer.push('\n');
+
+    let platforms = dofile.commands.iter().flat_map(|cmd| &cmd.instructions)
+        .flat_map(|instruction| restrictions(instruction).0)
+        .map(platform_variable)
+        .collect::<Vec<_>>();
+    if platforms.contains(&"DOMAKE_ARCH") {
+        buffer.push_str("!IF \"$(PROCESSOR_ARCHITECTURE)\" == \"ARM64\"\nDOMAKE_ARCH = arm64\n!ELSE\nDOMAKE_ARCH = amd64\n!ENDIF\n\n");
+    }
+    if let Some(shell) = &dofile.shell {
+        buffer.push_str(format!("# left out, as nmake runs the instructions with cmd.exe: shell {}\n\n", shell.name).as_str());
+    }
+
+    if !dofile.variables.is_empty() {
+        for variable in &dofile.variables {
+            buffer.push_str(format!("{}\n", nmake_variable(variable)).as_str());
+        }
+        buffer.push('\n');
+    }
+    for raw in &dofile.raws {
+        buffer.push_str(format!("{}\n\n", raw.text.trim_end()).as_str());
+    }
+
+    // nmake makes the first target of the Makefile
+    if let Some(goal) = &dofile.default {
+        buffer.push_str(format!("# the target nmake makes by default\n_default: {}\n\n", goal.name).as_str());
+    }
+    for cmd in &dofile.commands {
+        buffer.push_str(format!("{}\n", nmake_command(cmd)).as_str());
+    }
+    buffer.push_str(format!("{}\n", END_MARKER).as_str());
+    buffer
+}
+
+/// The helpers for BSD make: `help` prints the `##` comments of the Makefile, read when it was
+/// generated as BSD make cannot tell which file it reads, aligned like `column -t -s ':'`.
+fn bsd_helpers(makefile: &str) -> String {
+    let rows = makefile.lines()
+        .filter_map(|line| line.strip_prefix("##"))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split(':').map(str::trim).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths = (0..columns).map(|column| rows.iter().filter_map(|row| row.get(column)).map(|cell| cell.len()).max().unwrap_or_default()).collect::<Vec<_>>();
+
+    let mut buffer = banner("helpers");
+    buffer.push_str("## help: print this help message\n.PHONY: help\nhelp:\n\t@echo 'Usage:'\n");
+    for row in [vec!["help", "print this help message"]].iter().chain(&rows) {
+        let line = row.iter().enumerate().map(|(column, cell)| format!("{:width$}", cell, width = widths[column].max(4))).collect::<Vec<_>>().join("  ");
+        buffer.push_str(format!("\t@echo ' {}'\n", line.trim_end().replace('\'', "'\\''").replace('$', "$$")).as_str());
+    }
+    buffer.push_str("\n.PHONY: confirm\nconfirm:\n\t@printf 'Are you sure? [y/N] ' && read ans && [ \"$${ans:-N}\" = y ]\n\n");
+    buffer.push_str(&banner("commands"));
+    buffer
+}
+
+/// The rule of the target for BSD make, which has no target-specific variables, reads `$<` only in
+/// suffix rules, and waits for the prerequisites before a `.WAIT` instead of `.NOTPARALLEL`.
+fn bsd_command(cmd: &Command) -> String {
+    let marker = if cmd.is_hidden() { "#" } else { "##" };
+    let mut buffer = format!("{} {}: {}\n", marker, cmd.name, cmd.summary());
+    for line in cmd.description.lines().skip(1) {
+        buffer.push_str(format!("# {}\n", line).trim_end());
+        buffer.push('\n');
+    }
+    for variable in &cmd.variables {
+        buffer.push_str(format!("# left out, as BSD make has no target-specific variables: {}\n", variable.to_makefile()).as_str());
+    }
+
+    let separator = if cmd.notparallel { " .WAIT" } else { "" };
+    let prerequisites = cmd.prior_commands.iter().map(|prior| format!(" {}", prior)).collect::<Vec<_>>().join(separator);
+    let suffixes = suffix_rule(cmd);
+    if cmd.is_pattern() {
+        match &suffixes {
+            // BSD make only infers the extensions listed in `.SUFFIXES`, which this adds to
+            Some((source, target)) => buffer.push_str(format!(".SUFFIXES: .{1} .{0}\n.{0}.{1}:\n", source, target).as_str()),
+            None => return format!("# left out, as BSD make has no pattern rules: {}:{}\n", cmd.name, prerequisites),
+        }
+    } else {
+        if !cmd.file {
+            buffer.push_str(format!(".PHONY: {}\n", cmd.name).as_str());
+        }
+        buffer.push_str(format!("{}:{}\n", cmd.name, prerequisites).as_str());
+    }
+
+    if let Some(advice) = &cmd.deprecated {
+        let warning = format!("Warning: target `{}` is deprecated{}", cmd.name, if advice.is_empty() { String::new() } else { format!(", {}", advice) });
+        let warning = warning.replace('\'', "'\\''").replace('$', "$$");
+        buffer.push_str(format!("\t@printf '\\033[1;33m%s\\033[0m\\n' '{}' >&2\n", warning).as_str());
+    }
+    for tool in &cmd.requires {
+        buffer.push_str(format!("\t@command -v {} >/dev/null 2>&1 || {{ echo '{}: `{}` is required but was not found in the PATH' >&2; exit 1; }}\n",
+                                tool, cmd.name, tool).as_str());
+    }
+    let automatic = |instruction: &str| if suffixes.is_some() { instruction.to_string() } else { replace_automatic(instruction, "${.ALLSRC:[1]}", "${.ALLSRC}") };
+    if cmd.oneshell {
+        // the script is already a single line, carried on by a final `\`
+        let lines = Command { oneshell: true, variables: Vec::new(), deprecated: None, requires: Vec::new(), ..cmd.clone() }.to_makefile();
+        let recipe = lines.lines().skip_while(|line| !line.starts_with('\t')).map(|line| format!("{}\n", line)).collect::<String>();
+        buffer.push_str(&automatic(&recipe));
+        return buffer;
+    }
+    for instruction in &cmd.instructions {
+        let (names, instruction) = restrictions(instruction);
+        for name in &names {
+            buffer.push_str(format!(".if ${{{}}} == \"{}\"\n", platform_variable(name), name).as_str());
+        }
+        buffer.push_str(format!("\t{}\n", automatic(instruction).replace('\n', "\n\t")).as_str());
+        buffer.push_str(&".endif\n".repeat(names.len()));
+    }
+    buffer
+}
+
+/// Renders the Makefile for the make of the BSD base systems: its `.include`, `.if`, `.export`
+/// and `.MAIN` replace the directives of GNU make, and the helpers use no GNU tools.
+fn render_bsd(dofile: &Dofile, date: Option<&str>) -> String {
+    let mut buffer = header(dofile, date, Flavor::Bsd.name());
+    for include in &dofile.includes {
+        if is_glob(&include.path) {
+            buffer.push_str(format!("# left out, as BSD make has no `$(wildcard)`: include {}\n", include.path).as_str());
+        } else {
+            buffer.push_str(format!(".{}include \"{}\"\n", if include.optional { "-" } else { "" }, include.path).as_str());
+        }
+    }
+    buffer.push('\n');
+
+    let platforms = dofile.commands.iter().flat_map(|cmd| &cmd.instructions)
+        .flat_map(|instruction| restrictions(instruction).0)
+        .map(platform_variable)
+        .collect::<Vec<_>>();
+    if platforms.contains(&"DOMAKE_OS") {
+        buffer.push_str("DOMAKE_UNAME != uname -s\n.if ${DOMAKE_UNAME} == \"Darwin\"\nDOMAKE_OS = macos\n.else\nDOMAKE_OS = linux\n.endif\n\n");
+    }
+    if platforms.contains(&"DOMAKE_ARCH") {
+        buffer.push_str("DOMAKE_MACHINE != uname -m\n.if ${DOMAKE_MACHINE} == \"arm64\" || ${DOMAKE_MACHINE} == \"aarch64\"\nDOMAKE_ARCH = arm64\n.else\nDOMAKE_ARCH = amd64\n.endif\n\n");
+    }
+    if let Some(shell) = &dofile.shell {
+        buffer.push_str(format!("# left out, as BSD make runs the instructions with sh: shell {}\n\n", shell.name).as_str());
+    }
+
+    if !dofile.variables.is_empty() {
+        for variable in &dofile.variables {
+            let assignment = Variable { exported: false, ..variable.clone() }.to_makefile();
+            buffer.push_str(format!("{}\n", assignment).as_str());
+            if variable.exported {
+                buffer.push_str(format!(".export {}\n", variable.name).as_str());
+            }
+        }
+        buffer.push('\n');
+    }
+    for raw in &dofile.raws {
+        buffer.push_str(format!("{}\n\n", raw.text.trim_end()).as_str());
+    }
+
+    // the helpers come first, so plain `make` would run `help` otherwise
+    if let Some(goal) = &dofile.default {
+        buffer.push_str(format!(".MAIN: {}\n\n", goal.name).as_str());
+    }
+    let mut commands = String::new();
+    let mut groups = vec![None];
+    for cmd in &dofile.commands {
+        if !groups.contains(&cmd.group.as_deref()) {
+            groups.push(cmd.group.as_deref());
+        }
+    }
+    for group in groups {
+        if let Some(name) = group {
+            commands.push_str(&banner(name));
+            commands.push_str(format!("##\n## [{}]\n\n", name).as_str());
+        }
+        for cmd in dofile.commands.iter().filter(|cmd| cmd.group.as_deref() == group) {
+            commands.push_str(format!("{}\n", bsd_command(cmd)).as_str());
+        }
+    }
+    let raws = dofile.raws.iter().map(|raw| raw.text.as_str()).collect::<Vec<_>>().join("\n");
+    buffer.push_str(&bsd_helpers(&format!("{}\n{}", raws, commands)));
+    buffer.push_str(&commands);
+    buffer.push_str(format!("{}\n", END_MARKER).as_str());
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_dofile;
+
+    #[test]
+    fn a_notparallel_target_is_reported_as_left_out_before_make_4_4() {
+        let content = "[build] a b\n# Builds\n#@ notparallel\necho build\n\n[a]\n# A\necho a\n\n[b]\n# B\necho b\n";
+        let dofile = parse_dofile(content).unwrap();
+        let makefile = render_makefile_with(&dofile, None, Flavor::Gnu, MakeVersion(4, 3));
+        assert!(!makefile.contains(".NOTPARALLEL"));
+        assert!(unsupported_features(&dofile, MakeVersion(4, 3))[0].contains("`.NOTPARALLEL: build` as for the whole Makefile, so it is left out"));
+        assert!(unsupported_features(&dofile, MakeVersion::LATEST).is_empty());
+        let dofile = parse_dofile(&format!("notparallel\n\n{}", content)).unwrap();
+        assert!(unsupported_features(&dofile, MakeVersion(4, 3)).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use console::style;
+use domake::json::Json;
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Whether the result is printed as JSON on stdout, with `--json`, instead of the colored messages.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Whether nothing but the errors is printed, with `--quiet`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// The warnings and the files written so far, reported in the JSON result.
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static FILES: Mutex<Vec<(String, &'static str)>> = Mutex::new(Vec::new());
+
+/// Prints the result of the command as JSON from now on, for the wrappers and editors reading it.
+pub fn use_json() {
+    JSON.store(true, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Prints only the errors from now on, for the scripts that have no use for the progress messages.
+pub fn use_quiet() {
+    QUIET.store(true, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Whether the progress messages are printed: neither the result is printed as JSON nor `--quiet` is given.
+pub fn shows_status() -> bool {
+    !is_json() && !is_quiet()
+}
+
+/// Prints a status message on stdout, unless the result is printed as JSON or `--quiet` is given.
+pub fn status(message: &str) {
+    if shows_status() {
+        println!("{}", message);
+    }
+}
+
+/// Prints a warning on stderr, or keeps it for the JSON result; left out with `--quiet`.
+pub fn warning(message: &str) {
+    if is_json() {
+        WARNINGS.lock().unwrap().push(message.to_string());
+    } else if !is_quiet() {
+        eprintln!("{} {}", style("Warning:").bold().yellow(), style(message).yellow());
+    }
+}
+
+/// Prints the traces of `--verbose` on stderr, dimmed so that they stand apart from the messages.
+struct Tracer;
+
+impl Log for Tracer {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", style(format!("[{}] {}", record.level().as_str().to_lowercase(), record.args())).dim());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static TRACER: Tracer = Tracer;
+
+/// Traces what domake does on stderr from now on, for the `--verbose` given `count` times: the parsed
+/// constructs, the resolved includes and the files written, then every decision of the parser.
+pub fn use_verbosity(count: usize) {
+    if count == 0 || log::set_logger(&TRACER).is_err() {
+        return;
+    }
+    log::set_max_level(if count == 1 { LevelFilter::Debug } else { LevelFilter::Trace });
+}
+
+/// Records what was done to the file at `path` for the JSON result: `written`, `up to date` or `backup`.
+pub fn file(path: &str, action: &'static str) {
+    FILES.lock().unwrap().push((path.to_string(), action));
+}
+
+/// A problem of the Dofile at `path`, for the `diagnostics` of the JSON result.
+pub fn diagnostic(path: &str, line: usize, column: usize, code: Option<&str>, warning: bool, message: &str, hint: Option<&str>) -> Json {
+    Json::object([
+        ("file", path.into()),
+        ("line", line.into()),
+        ("column", column.into()),
+        ("code", code.into()),
+        ("severity", if warning { "warning" } else { "error" }.into()),
+        ("message", message.into()),
+        ("hint", hint.into()),
+    ])
+}
+
+/// Prints the JSON result and exits with `code`: the `status` (`ok`, `failed` when the check found
+/// problems, or `error` when the command could not run), the warnings, the files and `fields`.
+pub fn finish(code: i32, status: &str, fields: Vec<(&str, Json)>) -> ! {
+    let warnings = WARNINGS.lock().unwrap().iter().map(String::as_str).collect::<Vec<_>>().into();
+    let files = FILES.lock().unwrap().iter()
+        .map(|(path, action)| Json::object([("path", path.as_str().into()), ("action", (*action).into())]))
+        .collect::<Vec<_>>();
+    let mut members = vec![("status".to_string(), status.into()), ("warnings".to_string(), warnings), ("files".to_string(), Json::Array(files))];
+    members.extend(fields.into_iter().map(|(key, value)| (key.to_string(), value)));
+    println!("{}", Json::Object(members).pretty());
+    exit(code)
+}
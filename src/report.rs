@@ -0,0 +1,61 @@
+use std::ops::Range;
+use console::style;
+use domake::ParseError;
+
+/// A part of the Dofile to underline, with what to say about it.
+pub struct Label {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Range<usize>, message: impl Into<String>) -> Label {
+        Label { span, message: message.into() }
+    }
+}
+
+/// The labels of a mistake found by the parser, the mistake itself first.
+pub fn labels(err: &ParseError) -> Vec<Label> {
+    let related = err.related.iter().map(|(span, message)| Label::new(span.clone(), message.as_str()));
+    [Label::new(err.span.clone(), "")].into_iter().chain(related).collect()
+}
+
+/// Renders the lines of `content` the labels are about, in order, each label underlined
+/// under its line; the first label is the main one, the others give context.
+pub fn snippet(content: &str, labels: &[Label], hint: Option<&str>) -> String {
+    let mut located = labels.iter().enumerate().map(|(index, label)| {
+        let start = label.span.start.min(content.len());
+        let line_start = content[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = content[start..].find('\n').map_or(content.len(), |index| start + index);
+        let number = content[..start].matches('\n').count() + 1;
+        (number, index, label, line_start, line_end)
+    }).collect::<Vec<_>>();
+    located.sort_by_key(|(number, _, label, _, _)| (*number, label.span.start));
+    let width = located.iter().map(|(number, ..)| number.to_string().len()).max().unwrap_or(1);
+    let gutter = |text: &str| style(format!("{:>width$} |", text)).bold().blue().to_string();
+
+    let mut buffer = format!("{}\n", gutter(""));
+    let mut last = None;
+    for (number, index, label, line_start, line_end) in located {
+        let line = content[line_start..line_end].trim_end_matches('\r');
+        // the labels of a same line are all shown under it
+        if last != Some(number) {
+            buffer.push_str(format!("{} {}\n", gutter(&number.to_string()), line).as_str());
+            last = Some(number);
+        }
+        let start = label.span.start.clamp(line_start, line_start + line.len()) - line_start;
+        let end = label.span.end.clamp(line_start + start, line_start + line.len()) - line_start;
+        // keep the tabs, so that the underline lines up with the text whatever their width
+        let padding = line[..start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+        let length = line[start..end].chars().count().max(1);
+
+        let marker = if index == 0 { "^" } else { "-" };
+        let underline = format!("{} {}", marker.repeat(length), label.message).trim_end().to_string();
+        let underline = if index == 0 { style(underline).bold().red() } else { style(underline).bold().blue() };
+        buffer.push_str(format!("{} {}{}\n", gutter(""), padding, underline).as_str());
+    }
+    if let Some(hint) = hint {
+        buffer.push_str(format!("{:>width$} {} {}\n", "", style("= hint:").bold().cyan(), hint).as_str());
+    }
+    buffer
+}
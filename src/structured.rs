@@ -0,0 +1,238 @@
+//! Reads the structured Dofiles, written in YAML (`Dofile.yml`) or TOML (`Dofile.toml`) instead of
+//! the Dofile syntax: the targets are plain keys with a `description`, `deps` and a `script`.
+//!
+//! ```yaml
+//! shell: bash
+//! default: build
+//! variables:
+//!   PROFILE: debug
+//! targets:
+//!   build:
+//!     description: Builds the project
+//!     deps: [fmt]
+//!     script: cargo build --profile {{PROFILE}}
+//!   fmt:
+//!     description: Formats the code
+//!     script:
+//!       - cargo fmt
+//! ```
+
+use std::path::Path;
+use log::debug;
+use crate::dofile::{declared_variables, escape_dollars, interpolate};
+use crate::import::scalar_text;
+use crate::json::Json;
+use crate::{toml, yaml, Assignment, Command, Dofile, Goal, Include, Raw, Shell, Use, Variable};
+
+/// The names of the structured Dofiles, looked for after `Dofile`.
+pub const STRUCTURED_DOFILES: [&str; 3] = ["Dofile.yml", "Dofile.yaml", "Dofile.toml"];
+
+/// Whether the Dofile at `path` is a structured one, from its extension.
+pub fn is_structured(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|extension| ["yml", "yaml", "toml"].iter().any(|wanted| extension == *wanted))
+}
+
+/// Reads the structured Dofile at `path`, in TOML when its extension is `.toml` and in YAML otherwise.
+/// The model has no lines or spans, since it doesn't come from the Dofile syntax.
+pub fn parse_structured_dofile(path: &str, content: &str) -> Result<Dofile, String> {
+    let document = if Path::new(path).extension().is_some_and(|extension| extension == "toml") { toml::parse(content)? } else { yaml::parse(content)? };
+    let members = match &document {
+        Json::Object(members) => members.as_slice(),
+        Json::Null => &[],
+        _ => return Err("expected a mapping of `targets`, `variables` and settings".to_string()),
+    };
+
+    // the targets refer to the variables as `$(NAME)` wherever they are declared
+    let mut declared = Vec::new();
+    for (key, value) in members {
+        match key.as_str() {
+            "variables" => declared.extend(mapping(value, key)?.iter().map(|(name, _)| name.clone())),
+            "make" => for text in list(value, key)? {
+                declared.extend(declared_variables(&text.lines().collect::<Vec<_>>()));
+            },
+            _ => {}
+        }
+    }
+
+    let mut dofile = Dofile::default();
+    for (key, value) in members {
+        match key.as_str() {
+            "shell" => {
+                let shell = text(value, key)?;
+                let mut words = shell.split_whitespace();
+                let name = words.next().ok_or("`shell` expects the name of a shell")?;
+                dofile.shell = Some(Shell { line: 0, span: 0..0, name: name.to_string(), flags: words.map(str::to_string).collect() });
+            }
+            "default" => dofile.default = Some(Goal { line: 0, span: 0..0, name: text(value, key)? }),
+            "oneshell" => dofile.oneshell = boolean(value, key)?,
+            "notparallel" => dofile.notparallel = boolean(value, key)?,
+            "include" => dofile.includes = includes(value, key)?,
+            "dotenv" => dofile.dotenvs = includes(value, key)?,
+            "use" => for (namespace, path) in mapping(value, key)? {
+                let path = text(path, &format!("use.{}", namespace))?;
+                dofile.uses.push(Use { line: 0, span: 0..0, path, namespace: namespace.clone() });
+            },
+            "variables" => dofile.variables = variables(value, key)?,
+            "make" => for text in list(value, key)? {
+                dofile.raws.push(Raw { line: 0, span: 0..0, text: text.trim_end().to_string() });
+            },
+            "targets" => for (name, target) in mapping(value, key)? {
+                dofile.commands.push(command(name, target, &declared)?);
+            },
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+    }
+    if dofile.oneshell {
+        for cmd in &mut dofile.commands {
+            cmd.oneshell = true;
+        }
+    }
+    Ok(dofile)
+}
+
+/// The target `name` of the `targets` mapping.
+fn command(name: &str, target: &Json, declared: &[String]) -> Result<Command, String> {
+    let key = |field: &str| format!("targets.{}.{}", name, field);
+    let mut cmd = Command {
+        line: 0,
+        span: 0..0,
+        name: name.to_string(),
+        file: false,
+        description: String::new(),
+        prior_commands: Vec::new(),
+        requires: Vec::new(),
+        group: None,
+        deprecated: None,
+        variables: Vec::new(),
+        oneshell: false,
+        notparallel: false,
+        instructions: Vec::new(),
+    };
+    // the automatic variables of make (e.g. `$<`, `$@`) are what pattern rules are written with
+    let (mut script, mut strip_comments, mut make_dollars) = (Vec::new(), false, name.contains('%'));
+    for (field, value) in mapping(target, &format!("targets.{}", name))? {
+        match field.as_str() {
+            "description" => cmd.description = text(value, &key(field))?.lines().map(str::trim).collect::<Vec<_>>().join("\n").trim().to_string(),
+            "deps" => cmd.prior_commands = words(value, &key(field))?,
+            "requires" => cmd.requires = words(value, &key(field))?,
+            "file" => cmd.file = boolean(value, &key(field))?,
+            "group" => cmd.group = Some(text(value, &key(field))?).filter(|group| !group.is_empty()),
+            "deprecated" => cmd.deprecated = Some(text(value, &key(field))?),
+            "vars" => cmd.variables = variables(value, &key(field))?,
+            "oneshell" => cmd.oneshell = boolean(value, &key(field))?,
+            "notparallel" => cmd.notparallel = boolean(value, &key(field))?,
+            "comments" => strip_comments = match text(value, &key(field))?.as_str() {
+                "keep" => false,
+                "strip" => true,
+                other => return Err(format!("`{}` expects `keep` or `strip`, not `{}`", key(field), other)),
+            },
+            "dollars" => make_dollars = match text(value, &key(field))?.as_str() {
+                "shell" => false,
+                "make" => true,
+                other => return Err(format!("`{}` expects `shell` or `make`, not `{}`", key(field), other)),
+            },
+            "script" => script = list(value, &key(field))?,
+            _ => return Err(format!("unknown key `{}`", key(field))),
+        }
+    }
+    if cmd.description.is_empty() {
+        return Err(format!("target `{}` has no `description`", name));
+    }
+
+    let declared = declared.iter().chain(cmd.variables.iter().map(|variable| &variable.name)).cloned().collect::<Vec<_>>();
+    for text in &script {
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            let mut instruction = line.trim().to_string();
+            // a `\` at the end carries the instruction on over the next line, kept as is
+            while instruction.ends_with('\\') {
+                let Some(more) = lines.next() else { break };
+                instruction.push('\n');
+                instruction.push_str(more.trim_end());
+            }
+            if instruction.is_empty() || (strip_comments && instruction.starts_with('#')) {
+                continue;
+            }
+            let instruction = if make_dollars { instruction } else { escape_dollars(&instruction, &declared) };
+            cmd.instructions.push(interpolate(&instruction));
+        }
+    }
+    debug!("target `{}`{}, {} instruction(s)", name, if cmd.prior_commands.is_empty() { String::new() } else { format!(" after `{}`", cmd.prior_commands.join("`, `")) }, cmd.instructions.len());
+    Ok(cmd)
+}
+
+/// The variables of a mapping of names to values, or to a `value` with its `assign` operator and
+/// whether to `export` it.
+fn variables(value: &Json, key: &str) -> Result<Vec<Variable>, String> {
+    let mut variables = Vec::new();
+    for (name, variable) in mapping(value, key)? {
+        let key = format!("{}.{}", key, name);
+        let (mut value, mut assignment, mut exported) = (String::new(), Assignment::Recursive, false);
+        match variable {
+            Json::Object(fields) => for (field, setting) in fields {
+                match field.as_str() {
+                    "value" => value = text(setting, &format!("{}.value", key))?,
+                    "assign" => {
+                        let operator = text(setting, &format!("{}.assign", key))?;
+                        let Some(found) = Assignment::ALL.into_iter().find(|assignment| assignment.operator() == operator) else {
+                            return Err(format!("`{}.assign` expects `=`, `:=`, `?=` or `+=`, not `{}`", key, operator));
+                        };
+                        assignment = found;
+                    }
+                    "export" => exported = boolean(setting, &format!("{}.export", key))?,
+                    _ => return Err(format!("unknown key `{}.{}`", key, field)),
+                }
+            },
+            Json::Null => {}
+            _ => value = text(variable, &key)?,
+        }
+        variables.push(Variable { line: 0, span: 0..0, name: name.clone(), assignment, value: interpolate(&value), exported });
+    }
+    Ok(variables)
+}
+
+/// The files of an `include` or `dotenv` key: paths, or a `path` that may be `optional`.
+fn includes(value: &Json, key: &str) -> Result<Vec<Include>, String> {
+    let items = match value {
+        Json::Array(items) => items.as_slice(),
+        _ => std::slice::from_ref(value),
+    };
+    items.iter().map(|item| match item {
+        Json::Object(_) => {
+            let path = item.get("path").ok_or_else(|| format!("`{}` expects a `path` for each file", key))?;
+            let optional = item.get("optional").map(|optional| boolean(optional, &format!("{}.optional", key))).transpose()?;
+            Ok(Include { line: 0, span: 0..0, path: text(path, &format!("{}.path", key))?, optional: optional.unwrap_or(false) })
+        }
+        _ => Ok(Include { line: 0, span: 0..0, path: text(item, key)?, optional: false }),
+    }).collect()
+}
+
+fn mapping<'a>(value: &'a Json, key: &str) -> Result<&'a [(String, Json)], String> {
+    match value {
+        Json::Object(members) => Ok(members),
+        Json::Null => Ok(&[]),
+        _ => Err(format!("`{}` expects a mapping", key)),
+    }
+}
+
+fn text(value: &Json, key: &str) -> Result<String, String> {
+    scalar_text(value).ok_or_else(|| format!("`{}` expects a string", key))
+}
+
+fn boolean(value: &Json, key: &str) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("`{}` expects `true` or `false`", key))
+}
+
+/// A string, or each one of a list of them.
+fn list(value: &Json, key: &str) -> Result<Vec<String>, String> {
+    match value {
+        Json::Array(items) => items.iter().map(|item| text(item, key)).collect(),
+        Json::Null => Ok(Vec::new()),
+        _ => Ok(vec![text(value, key)?]),
+    }
+}
+
+/// The words of a string, or of each one of a list of them.
+fn words(value: &Json, key: &str) -> Result<Vec<String>, String> {
+    Ok(list(value, key)?.iter().flat_map(|text| text.split([',', ' ', '\t'])).filter(|word| !word.is_empty()).map(str::to_string).collect())
+}
@@ -0,0 +1,398 @@
+//! Reads TOML configuration files into a [`Json`] value: tables and arrays of tables, dotted keys,
+//! strings, numbers, booleans, arrays and inline tables. The dates and times are read as strings.
+
+use crate::json::Json;
+
+/// The arrays and inline tables a value can be in, beyond which a document is refused.
+const MAX_DEPTH: usize = 128;
+
+/// Parses a TOML document.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { text, position: 0, depth: 0 };
+    let mut root = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    loop {
+        parser.skip_blank_lines();
+        // the errors of a whole line point at its start
+        let start = parser.position;
+        match parser.peek() {
+            None => return Ok(Json::Object(root)),
+            Some('[') => {
+                let array = parser.text[parser.position..].starts_with("[[");
+                parser.position += if array { 2 } else { 1 };
+                let path = parser.key()?;
+                parser.expect(']')?;
+                if array {
+                    parser.expect(']')?;
+                }
+                parser.end_of_line()?;
+                let (last, parents) = path.split_last().unwrap();
+                let table = table(&mut root, parents).map_err(|message| parser.error_at(start, &message))?;
+                match table.iter_mut().find(|(name, _)| name == last) {
+                    Some((_, Json::Array(tables))) if array => tables.push(Json::Object(Vec::new())),
+                    Some((_, Json::Object(_))) if !array => {}
+                    Some(_) => return Err(parser.error_at(start, &format!("`{}` is defined twice", path.join(".")))),
+                    None if array => table.push((last.clone(), Json::Array(vec![Json::Object(Vec::new())]))),
+                    None => table.push((last.clone(), Json::Object(Vec::new()))),
+                }
+                current = path;
+            }
+            Some(_) => {
+                let key = parser.key()?;
+                parser.expect('=')?;
+                let value = parser.value()?;
+                parser.end_of_line()?;
+                let table = table(&mut root, &current).map_err(|message| parser.error_at(start, &message))?;
+                insert(table, &key, value).map_err(|message| parser.error_at(start, &message))?;
+            }
+        }
+    }
+}
+
+/// The members of the table at `path` from `root`, the last one of an array of tables, created
+/// when missing.
+fn table<'a>(root: &'a mut Vec<(String, Json)>, path: &[String]) -> Result<&'a mut Vec<(String, Json)>, String> {
+    let mut members = root;
+    for (depth, name) in path.iter().enumerate() {
+        let index = match members.iter().position(|(key, _)| key == name) {
+            Some(index) => index,
+            None => {
+                members.push((name.clone(), Json::Object(Vec::new())));
+                members.len() - 1
+            }
+        };
+        let value = match &mut members[index].1 {
+            Json::Array(tables) => tables.last_mut(),
+            value => Some(value),
+        };
+        members = match value {
+            Some(Json::Object(inner)) => inner,
+            _ => return Err(format!("`{}` is not a table", path[..=depth].join("."))),
+        };
+    }
+    Ok(members)
+}
+
+/// Inserts `value` at the dotted `key` of the table.
+fn insert(members: &mut Vec<(String, Json)>, key: &[String], value: Json) -> Result<(), String> {
+    let (last, parents) = key.split_last().unwrap();
+    let members = table(members, parents)?;
+    if members.iter().any(|(name, _)| name == last) {
+        return Err(format!("`{}` is defined twice", key.join(".")));
+    }
+    members.push((last.clone(), value));
+    Ok(())
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// The byte offset of the next character.
+    position: usize,
+    /// The arrays and inline tables the next value is in.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        self.error_at(self.position, message)
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> String {
+        let before = &self.text[..position];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        format!("{} at line {} column {}", message, line, column)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.position += 1;
+        }
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.next();
+            }
+        }
+    }
+
+    /// Skips the spaces, comments and line breaks.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some('\n' | '\r') => self.position += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.next_if_eq('\r');
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            _ => Err(self.error("expected the end of the line")),
+        }
+    }
+
+    fn next_if_eq(&mut self, wanted: char) -> bool {
+        let found = self.peek() == Some(wanted);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, wanted: char) -> Result<(), String> {
+        self.skip_spaces();
+        match self.next() {
+            Some(c) if c == wanted => Ok(()),
+            _ => Err(self.error(&format!("expected `{}`", wanted))),
+        }
+    }
+
+    /// The parts of a dotted key, each bare or quoted.
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.string()?,
+                Some('\'') => self.literal()?,
+                _ => {
+                    let start = self.position;
+                    while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.position += 1;
+                    }
+                    if start == self.position {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.text[start..self.position].to_string()
+                }
+            };
+            parts.push(part);
+            self.skip_spaces();
+            if !self.next_if_eq('.') {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_spaces();
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some('"') => self.string().map(Json::String),
+            Some('\'') => self.literal().map(Json::String),
+            Some('[') => self.nested(Self::array),
+            Some('{') => self.nested(Self::inline_table),
+            Some(_) => {
+                let start = self.position;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | ':')) {
+                    self.position += 1;
+                }
+                // the time of a date can follow it after a space
+                if self.text[start..self.position].len() == 10 && self.text[self.position..].starts_with(' ') && self.text[self.position + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+                    self.position += 1;
+                    while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | ':')) {
+                        self.position += 1;
+                    }
+                }
+                let token = &self.text[start..self.position];
+                let number = token.replace('_', "");
+                let radix = [("0x", 16), ("0o", 8), ("0b", 2)].into_iter().find_map(|(prefix, radix)| number.strip_prefix(prefix).map(|digits| (digits, radix)));
+                match token {
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    "inf" | "+inf" => Ok(Json::Number(f64::INFINITY)),
+                    "-inf" => Ok(Json::Number(f64::NEG_INFINITY)),
+                    "nan" | "+nan" | "-nan" => Ok(Json::Number(f64::NAN)),
+                    _ if radix.is_some() => {
+                        let (digits, radix) = radix.unwrap();
+                        i64::from_str_radix(digits, radix).map(|value| Json::Number(value as f64)).map_err(|_| self.error("invalid number"))
+                    }
+                    _ if number.parse::<f64>().is_ok() => Ok(Json::Number(number.parse().unwrap())),
+                    // a date or a time
+                    _ if token.starts_with(|c: char| c.is_ascii_digit()) && token.contains(['-', ':']) => Ok(Json::String(token.to_string())),
+                    _ => {
+                        self.position = start;
+                        Err(self.error("invalid value"))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads an array or an inline table with `read`, one level deeper.
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.next_if_eq(']') {
+                return Ok(Json::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_blank_lines();
+            if !self.next_if_eq(',') {
+                self.skip_blank_lines();
+                return match self.next() {
+                    Some(']') => Ok(Json::Array(values)),
+                    _ => Err(self.error("expected `,` or `]`")),
+                };
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut members = Vec::new();
+        self.skip_spaces();
+        if self.next_if_eq('}') {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            let key = self.key()?;
+            self.expect('=')?;
+            let value = self.value()?;
+            insert(&mut members, &key, value).map_err(|message| self.error(&message))?;
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    /// A basic string, on a single line or between `"""` over several ones.
+    fn string(&mut self) -> Result<String, String> {
+        let multiline = self.text[self.position..].starts_with("\"\"\"");
+        self.position += if multiline { 3 } else { 1 };
+        if multiline {
+            self.skip_first_newline();
+        }
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('\n') if !multiline => return Err(self.error("unterminated string")),
+                Some('"') if !multiline => return Ok(value),
+                Some('"') => {
+                    // the last three quotes of a run end the string, up to two before them are content
+                    let run = 1 + self.text[self.position..].chars().take_while(|c| *c == '"').count();
+                    if run < 3 {
+                        value.push('"');
+                        continue;
+                    }
+                    value.push_str(&"\"".repeat(run - 3));
+                    self.position += run - 1;
+                    return Ok(value);
+                }
+                Some('\\') => match self.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('e') => value.push('\u{1b}'),
+                    Some(c @ ('u' | 'U')) => {
+                        let length = if c == 'u' { 4 } else { 8 };
+                        let digits = self.text.get(self.position..self.position + length).ok_or_else(|| self.error("invalid escape"))?;
+                        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid escape"))?;
+                        self.position += length;
+                        value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    // a `\` ending a line of a multiline string trims the blanks after it
+                    Some(' ' | '\t' | '\r' | '\n') if multiline => {
+                        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                            self.position += 1;
+                        }
+                    }
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    /// A literal string, with no escapes, on a single line or between `'''` over several ones.
+    fn literal(&mut self) -> Result<String, String> {
+        let multiline = self.text[self.position..].starts_with("'''");
+        let delimiter = if multiline { "'''" } else { "'" };
+        self.position += delimiter.len();
+        if multiline {
+            self.skip_first_newline();
+        }
+        let rest = &self.text[self.position..];
+        let Some(end) = rest.find(delimiter).filter(|end| multiline || !rest[..*end].contains('\n')) else {
+            return Err(self.error("unterminated string"));
+        };
+        // up to two quotes can end the content of a multiline string
+        let end = end + rest[end..].chars().take_while(|c| *c == '\'').count() - delimiter.len();
+        self.position += end + delimiter.len();
+        Ok(rest[..end].to_string())
+    }
+
+    /// Skips the line break right after the opening delimiter of a multiline string.
+    fn skip_first_newline(&mut self) {
+        if self.text[self.position..].starts_with("\r\n") {
+            self.position += 2;
+        } else if self.peek() == Some('\n') {
+            self.position += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_tables_and_values_are_read() {
+        let text = "shell = \"bash -eu\" # the shell\ndefault = 'build'\n\n[variables]\nPROFILE = \"debug\"\nJOBS = 0x10\n\n[targets.build]\ndeps = [\n  \"fmt\", # first\n  'lint',\n]\nscript = \"\"\"\ncargo build\"\"\"\nvars = { MODE = \"fast\", nested.level = 1.5 }\n\n[[targets.build.steps]]\nat = 1979-05-27 07:32:00\n[[targets.build.steps]]\nfile = true\n";
+        let expected = r#"{"shell": "bash -eu", "default": "build", "variables": {"PROFILE": "debug", "JOBS": 16},
+            "targets": {"build": {"deps": ["fmt", "lint"], "script": "cargo build", "vars": {"MODE": "fast", "nested": {"level": 1.5}},
+            "steps": [{"at": "1979-05-27 07:32:00"}, {"file": true}]}}}"#;
+        assert_eq!(parse(text).unwrap(), Json::parse(expected).unwrap());
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        for text in ["a =\n", "a = [1 2]\n", "a = {b = 1\n", "[a\n", "a = 1\na = 2\n", "a = \"open\n", "a = nope\n", "a = 1 b\n", "a = 1\n[a]\n"] {
+            assert!(parse(text).is_err(), "`{}` was read", text);
+        }
+        assert_eq!(parse("a = [1,\n  x]\n").unwrap_err(), "invalid value at line 2 column 3");
+        assert_eq!(parse("a = 1\na = 2\n").unwrap_err(), "`a` is defined twice at line 2 column 1");
+    }
+
+    #[test]
+    fn the_nesting_is_limited() {
+        let nested = |depth: usize| format!("a = {}{}\n", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)).unwrap_err(), "nested too deeply at line 1 column 133");
+        assert!(parse(&format!("a = {}", "{ b = ".repeat(100_000))).is_err());
+    }
+}
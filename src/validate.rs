@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use crate::{Command, Dofile, ErrorKind, ParseError, HELPER_TARGETS};
+
+/// Checks the targets of the Dofile together, once each of them is parsed.
+pub(crate) fn validate(content: &str, dofile: &Dofile) -> Vec<ParseError> {
+    let mut errors = cycles(content, &dofile.commands);
+    errors.extend(prerequisites(content, dofile));
+    errors.extend(default(content, dofile));
+    errors
+}
+
+/// The number of single character edits, including swapping two neighbours, between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i];
+        for j in 1..=b.len() {
+            let mut edits = (rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]))
+                .min(rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                edits = edits.min(rows[i - 2][j - 2] + 1);
+            }
+            row.push(edits);
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The defined target closest to `name`, if it is close enough to be a typo.
+fn suggestion<'a>(name: &str, defined: impl Iterator<Item = &'a str>) -> Option<String> {
+    let limit = (name.chars().count() / 3).max(1);
+    defined.map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// The bytes of the prerequisite `prior` of the target, on its header or on the line after it.
+pub fn prerequisite_span(content: &str, cmd: &Command, prior: &str) -> Range<usize> {
+    let rest = &content[cmd.span.end..];
+    let lines = rest.split_inclusive('\n').take(2).collect::<String>();
+    let mut offset = 0;
+    for word in lines.split_whitespace() {
+        offset += lines[offset..].find(word).unwrap();
+        if word == prior {
+            let start = cmd.span.end + offset;
+            return start..start + word.len();
+        }
+        offset += word.len();
+    }
+    cmd.span.clone()
+}
+
+/// Whether the pattern rule `pattern` (e.g. `%.o`) makes the file `name`.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    pattern.split_once('%').is_some_and(|(prefix, suffix)| {
+        name.len() > prefix.len() + suffix.len() && name.starts_with(prefix) && name.ends_with(suffix)
+    })
+}
+
+/// Finds the prerequisites that are neither targets nor existing files, which
+/// make could only fail on.
+fn prerequisites(content: &str, dofile: &Dofile) -> Vec<ParseError> {
+    let defined = || dofile.commands.iter().map(|cmd| cmd.name.as_str())
+        .chain(dofile.raws.iter().flat_map(|raw| raw.targets()))
+        .chain(HELPER_TARGETS.iter().copied());
+    let mut errors = Vec::new();
+    for cmd in &dofile.commands {
+        for prior in &cmd.prior_commands {
+            // the prerequisites of a pattern rule are patterns too, whose files make looks for
+            if is_used(prior, dofile) || prior.contains('%') || defined().any(|name| name == prior || matches_pattern(name, prior)) || fs::exists(prior).unwrap_or(false) {
+                continue;
+            }
+            let kind = ErrorKind::UndefinedPrerequisite(prior.clone(), suggestion(prior, defined()));
+            let related = vec![(cmd.span.clone(), format!("required by `{}`", cmd.name))];
+            errors.push(ParseError::new(content, prerequisite_span(content, cmd, prior), kind, related));
+        }
+    }
+    errors
+}
+
+/// Checks that the `default` target is defined.
+/// Whether `name` may be a target of a used Dofile, in its namespace or its directory,
+/// which are only known once it is loaded.
+fn is_used(name: &str, dofile: &Dofile) -> bool {
+    let within = |prefix: &str| !prefix.is_empty() && name.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'));
+    dofile.uses.iter().any(|import| {
+        let directory = import.path.rsplit_once('/').map(|(directory, _)| directory.trim_start_matches("./")).unwrap_or_default();
+        within(&import.namespace) || (directory != "." && within(directory))
+    })
+}
+
+fn default(content: &str, dofile: &Dofile) -> Option<ParseError> {
+    let goal = dofile.default.as_ref()?;
+    if is_used(&goal.name, dofile) {
+        return None;
+    }
+    let defined = || dofile.commands.iter().map(|cmd| cmd.name.as_str()).chain(dofile.raws.iter().flat_map(|raw| raw.targets()));
+    if defined().any(|name| name == goal.name) {
+        return None;
+    }
+    let kind = ErrorKind::UndefinedDefault(goal.name.clone(), suggestion(&goal.name, defined()));
+    Some(ParseError::new(content, goal.span.clone(), kind, Vec::new()))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Visiting,
+    Done,
+}
+
+/// Finds the targets that end up requiring themselves, reporting each cycle once.
+fn cycles(content: &str, commands: &[Command]) -> Vec<ParseError> {
+    let by_name = commands.iter().map(|cmd| (cmd.name.as_str(), cmd)).collect::<HashMap<_, _>>();
+    let mut states = HashMap::new();
+
+    fn visit<'a>(
+        cmd: &'a Command,
+        by_name: &HashMap<&str, &'a Command>,
+        states: &mut HashMap<&'a str, State>,
+        stack: &mut Vec<&'a Command>,
+        cycles: &mut Vec<Vec<&'a Command>>,
+    ) {
+        states.insert(&cmd.name, State::Visiting);
+        stack.push(cmd);
+        for prior in &cmd.prior_commands {
+            let Some(next) = by_name.get(prior.as_str()) else {
+                continue;
+            };
+            match states.get(prior.as_str()) {
+                None => visit(next, by_name, states, stack, cycles),
+                Some(State::Visiting) => {
+                    let start = stack.iter().position(|other| other.name == *prior).unwrap();
+                    cycles.push(stack[start..].to_vec());
+                }
+                Some(State::Done) => {}
+            }
+        }
+        stack.pop();
+        states.insert(&cmd.name, State::Done);
+    }
+
+    let mut cycles = Vec::new();
+    for cmd in commands {
+        if !states.contains_key(cmd.name.as_str()) {
+            visit(cmd, &by_name, &mut states, &mut Vec::new(), &mut cycles);
+        }
+    }
+
+    let mut errors = Vec::new();
+    for cycle in cycles {
+        let names = cycle.iter().chain([&cycle[0]]).map(|cmd| cmd.name.clone()).collect::<Vec<_>>();
+        let related = cycle.iter().zip(&names[1..]).skip(1)
+            .map(|(cmd, next)| (cmd.span.clone(), format!("requires `{}`", next)))
+            .collect();
+        errors.push(ParseError::new(content, cycle[0].span.clone(), ErrorKind::CircularDependency(names), related));
+    }
+    errors
+}
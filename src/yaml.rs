@@ -0,0 +1,434 @@
+//! Reads the YAML of configuration files into a [`Json`] value: block mappings and sequences,
+//! flow collections, quoted and plain scalars, and `|` and `>` block scalars. Anchors, tags and
+//! several documents in a file are not supported.
+
+use crate::json::Json;
+
+/// The collections a value can be in, beyond which a document is refused.
+const MAX_DEPTH: usize = 128;
+
+/// Parses a YAML document.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines().map(|line| line.strip_suffix('\r').unwrap_or(line)) {
+        let started = lines.iter().any(|line| !line.trim().is_empty());
+        match line.trim_end() {
+            // the directives and the start of the document, kept blank for the line numbers
+            "---" if !started => lines.push(String::new()),
+            _ if line.starts_with('%') && !started => lines.push(String::new()),
+            "---" | "..." => break,
+            _ => lines.push(line.to_string()),
+        }
+    }
+    let mut parser = Parser { lines, index: 0, depth: 0 };
+    parser.skip_blank();
+    let value = match parser.indent() {
+        Some(indent) => parser.block(indent),
+        None => Ok(Json::Null),
+    }
+    .map_err(|(message, index)| format!("{} at line {}", message, index + 1))?;
+    parser.skip_blank();
+    if parser.index < parser.lines.len() {
+        return Err(format!("unexpected indentation at line {}", parser.index + 1));
+    }
+    Ok(value)
+}
+
+/// An error, with the index of the line it is on.
+type Error = (String, usize);
+
+struct Parser {
+    lines: Vec<String>,
+    /// The index of the next line.
+    index: usize,
+    /// The blocks the next one is in.
+    depth: usize,
+}
+
+/// The text before the comment ending it, if any: a `#` at its start or after a blank, outside of quotes.
+fn uncomment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (None, '"' | '\'') if previous == ' ' || previous == '[' || previous == '{' || previous == ',' => quote = Some(c),
+            (None, '#') if previous == ' ' || previous == '\t' => return text[..index].trim_end(),
+            _ => {}
+        }
+        previous = c;
+    }
+    text.trim_end()
+}
+
+/// The key and the rest of a `key: value` line, split on its first `:` followed by a blank or the
+/// end of the line, outside of quotes and brackets.
+fn split_key(content: &str) -> Option<(String, &str)> {
+    let (mut quote, mut depth) = (None, 0);
+    let bytes = content.as_bytes();
+    for (index, c) in content.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if index == 0 => quote = Some(c),
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            (None, ':') if depth == 0 && matches!(bytes.get(index + 1), None | Some(b' ' | b'\t')) => {
+                let key = content[..index].trim();
+                let key = match scalar(key) {
+                    Json::String(key) => key,
+                    other => other.to_string(),
+                };
+                return Some((key, content[index + 1..].trim_start()));
+            }
+            (None, '#') if index == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value of a scalar on a single line: quoted, or plain and read as a null, boolean or number
+/// when it is one.
+fn scalar(text: &str) -> Json {
+    let text = text.trim();
+    if let Some(quoted) = text.strip_prefix('\'').and_then(|text| text.strip_suffix('\'')) {
+        return Json::String(quoted.replace("''", "'"));
+    }
+    if text.len() > 1 && text.starts_with('"') && text.ends_with('"') {
+        return Json::String(unescape(&text[1..text.len() - 1]));
+    }
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => Json::Null,
+        "true" | "True" | "TRUE" => Json::Bool(true),
+        "false" | "False" | "FALSE" => Json::Bool(false),
+        _ => match text.parse::<f64>() {
+            Ok(number) if text.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') && !text.ends_with('.') => Json::Number(number),
+            _ => Json::String(text.to_string()),
+        },
+    }
+}
+
+/// The text of a double-quoted scalar, with its escapes replaced.
+fn unescape(text: &str) -> String {
+    let mut value = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('0') => value.push('\0'),
+            Some(c) => value.push(c),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> Error {
+        (message.to_string(), self.index)
+    }
+
+    /// Skips the blank and comment lines.
+    fn skip_blank(&mut self) {
+        while self.lines.get(self.index).is_some_and(|line| line.trim().is_empty() || line.trim_start().starts_with('#')) {
+            self.index += 1;
+        }
+    }
+
+    /// The indentation of the next line, if any.
+    fn indent(&self) -> Option<usize> {
+        let line = self.lines.get(self.index)?;
+        Some(line.len() - line.trim_start_matches(' ').len())
+    }
+
+    /// The content of the next line, after its indentation and without its comment.
+    fn content(&self) -> &str {
+        uncomment(self.lines[self.index].trim_start_matches(' '))
+    }
+
+    fn is_item(&self) -> bool {
+        let content = self.content();
+        content == "-" || content.starts_with("- ")
+    }
+
+    /// The sequence, mapping or scalar whose first line is the next one, indented by `indent`.
+    fn block(&mut self, indent: usize) -> Result<Json, Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = if self.is_item() {
+            self.sequence(indent)
+        } else if split_key(self.content()).is_some() {
+            self.mapping(indent)
+        } else {
+            let content = self.content().to_string();
+            self.index += 1;
+            self.value(&content, indent)
+        };
+        self.depth -= 1;
+        value
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Json, Error> {
+        let mut values = Vec::new();
+        while self.indent() == Some(indent) && self.is_item() {
+            let rest = self.content()[1..].trim_start().to_string();
+            if rest.is_empty() {
+                self.index += 1;
+                values.push(self.nested(indent)?);
+            } else if split_key(&rest).is_some() || rest == "-" || rest.starts_with("- ") {
+                // a collection starting on the line of its `-`, read as if indented on its own line
+                let offset = self.lines[self.index].len() - self.lines[self.index].trim_start_matches(' ').len() + 1;
+                let spaces = self.lines[self.index][offset..].len() - self.lines[self.index][offset..].trim_start().len();
+                self.lines[self.index] = format!("{}{}", " ".repeat(offset + spaces), &self.lines[self.index][offset + spaces..]);
+                values.push(self.block(offset + spaces)?);
+            } else {
+                self.index += 1;
+                values.push(self.value(&rest, indent)?);
+            }
+            self.skip_blank();
+        }
+        Ok(Json::Array(values))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Json, Error> {
+        let mut members: Vec<(String, Json)> = Vec::new();
+        while self.indent() == Some(indent) && !self.is_item() {
+            let Some((key, rest)) = split_key(self.content()) else {
+                return Err(self.error("expected a `key: value`"));
+            };
+            let rest = rest.to_string();
+            self.index += 1;
+            let value = if rest.is_empty() {
+                self.skip_blank();
+                // a sequence may be as indented as its key
+                if self.indent() == Some(indent) && self.is_item() { self.sequence(indent)? } else { self.nested(indent)? }
+            } else {
+                self.value(&rest, indent)?
+            };
+            members.retain(|(name, _)| *name != key);
+            members.push((key, value));
+            self.skip_blank();
+        }
+        Ok(Json::Object(members))
+    }
+
+    /// The block on the next lines more indented than `indent`, or a null if there is none.
+    fn nested(&mut self, indent: usize) -> Result<Json, Error> {
+        self.skip_blank();
+        match self.indent() {
+            Some(inner) if inner > indent => self.block(inner),
+            _ => Ok(Json::Null),
+        }
+    }
+
+    /// The value written after a key or a `-` of the line before the next one, indented by `indent`.
+    fn value(&mut self, rest: &str, indent: usize) -> Result<Json, Error> {
+        if let Some(header) = rest.strip_prefix(['|', '>']) {
+            return Ok(Json::String(self.block_scalar(rest.starts_with('>'), header, indent)));
+        }
+        if rest.starts_with(['[', '{']) {
+            let (mut text, start) = (rest.to_string(), self.index - 1);
+            // a flow collection can go on over the next lines
+            while !balanced(&text) {
+                let Some(line) = self.lines.get(self.index) else {
+                    return Err(("unterminated flow collection".to_string(), start));
+                };
+                text.push(' ');
+                text.push_str(uncomment(line.trim()));
+                self.index += 1;
+            }
+            let mut chars = text.chars().peekable();
+            let value = flow(&mut chars, 0).map_err(|message| (message.to_string(), self.index - 1))?;
+            return Ok(value);
+        }
+        // a plain or quoted scalar can go on over the more indented lines
+        let mut text = rest.to_string();
+        while self.lines.get(self.index).is_some_and(|line| !line.trim().is_empty() && line.len() - line.trim_start_matches(' ').len() > indent && !line.trim_start().starts_with('#')) {
+            text.push(' ');
+            text.push_str(uncomment(self.lines[self.index].trim()));
+            self.index += 1;
+        }
+        Ok(scalar(&text))
+    }
+
+    /// The text of a `|` (literal) or `>` (folded) block scalar, on the next lines more indented
+    /// than `indent`, its final newlines chomped as its `header` says.
+    fn block_scalar(&mut self, folded: bool, header: &str, indent: usize) -> String {
+        let mut lines: Vec<&str> = Vec::new();
+        let start = self.index;
+        while self.lines.get(self.index).is_some_and(|line| line.trim().is_empty() || line.len() - line.trim_start_matches(' ').len() > indent) {
+            self.index += 1;
+        }
+        // the trailing blank lines belong to what follows, except for the chomping
+        let mut end = self.index;
+        while end > start && self.lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+        let inner = self.lines[start..end].iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start_matches(' ').len())
+            .min()
+            .unwrap_or(indent + 1);
+        for line in &self.lines[start..end] {
+            lines.push(line.get(inner..).unwrap_or_default());
+        }
+        let mut text = if folded {
+            let mut text = String::new();
+            for (index, line) in lines.iter().enumerate() {
+                // a blank line is a newline, and the line break after a line of text a space
+                let previous = if index > 0 { lines[index - 1] } else { "" };
+                if line.is_empty() || (!previous.is_empty() && (line.starts_with(' ') || previous.starts_with(' '))) {
+                    text.push('\n');
+                } else if !previous.is_empty() {
+                    text.push(' ');
+                }
+                text.push_str(line);
+            }
+            text
+        } else {
+            lines.join("\n")
+        };
+        let header = uncomment(header).trim();
+        if header.contains('+') {
+            text.push_str(&"\n".repeat(self.index - end + 1));
+        } else if !header.contains('-') && !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Whether the brackets of the flow collection are all closed.
+fn balanced(text: &str) -> bool {
+    let (mut quote, mut depth) = (None, 0);
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+/// The flow collection or scalar at the start of `chars`, up to a `,`, `]` or `}`, in `depth`
+/// collections.
+fn flow(chars: &mut std::iter::Peekable<std::str::Chars>, depth: usize) -> Result<Json, &'static str> {
+    const INVALID: &str = "invalid flow collection";
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    match *chars.peek().ok_or(INVALID)? {
+        '[' | '{' if depth == MAX_DEPTH => Err("nested too deeply"),
+        '[' => {
+            chars.next();
+            let mut values = Vec::new();
+            loop {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                if chars.next_if_eq(&']').is_some() {
+                    return Ok(Json::Array(values));
+                }
+                if chars.peek() == Some(&'}') {
+                    return Err(INVALID);
+                }
+                values.push(flow(chars, depth + 1)?);
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                chars.next_if_eq(&',');
+            }
+        }
+        '{' => {
+            chars.next();
+            let mut members = Vec::new();
+            loop {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                if chars.next_if_eq(&'}').is_some() {
+                    return Ok(Json::Object(members));
+                }
+                if chars.peek() == Some(&']') {
+                    return Err(INVALID);
+                }
+                let key = match flow(chars, depth + 1)? {
+                    Json::String(key) => key,
+                    other => other.to_string(),
+                };
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                let value = if chars.next_if_eq(&':').is_some() { flow(chars, depth + 1)? } else { Json::Null };
+                members.push((key, value));
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                chars.next_if_eq(&',');
+            }
+        }
+        quote @ ('"' | '\'') => {
+            let mut text = String::from(quote);
+            chars.next();
+            let mut escaped = false;
+            for c in chars.by_ref() {
+                text.push(c);
+                if c == quote && !escaped {
+                    break;
+                }
+                escaped = quote == '"' && c == '\\' && !escaped;
+            }
+            Ok(scalar(&text))
+        }
+        _ => {
+            let mut text = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !matches!(c, ',' | ']' | '}')) {
+                // a `:` followed by a blank ends the key of a flow mapping
+                if c == ':' && chars.clone().nth(1).is_some_and(char::is_whitespace) {
+                    break;
+                }
+                text.push(c);
+                chars.next();
+            }
+            Ok(scalar(&text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_block_and_flow_collections_are_read() {
+        let text = "%YAML 1.2\n---\nshell: bash -eu  # the shell\nempty:\ntargets:\n  build:\n    deps: [fmt, 'lint', {name: \"a b\"}]\n    script:\n    - cargo build\n    - - nested\n      - key: value\n  fmt:\n    script: |\n      cargo fmt\n        --check\n    note: >-\n      folded\n      text\n    plain: a long\n      scalar\n    count: 3\n    off: false\n...\nignored: true\n";
+        let expected = r#"{"shell": "bash -eu", "empty": null, "targets": {
+            "build": {"deps": ["fmt", "lint", {"name": "a b"}], "script": ["cargo build", ["nested", {"key": "value"}]]},
+            "fmt": {"script": "cargo fmt\n  --check\n", "note": "folded text", "plain": "a long scalar", "count": 3, "off": false}}}"#;
+        assert_eq!(parse(text).unwrap(), Json::parse(expected).unwrap());
+        assert_eq!(parse("# nothing\n").unwrap(), Json::Null);
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        for text in ["a: [1, 2\n", "a: [b}\n", "a: {b: ]\n", "a:\n  b: 1\n c: 2\n", "- a\nb: 1\n", "a: 1\n- b\n"] {
+            assert!(parse(text).is_err(), "`{}` was read", text);
+        }
+        assert_eq!(parse("a:\n  b: 1\n c: 2\n").unwrap_err(), "unexpected indentation at line 3");
+        assert_eq!(parse("a: 1\nb: [c,\n  {d]}\n").unwrap_err(), "invalid flow collection at line 3");
+        assert_eq!(parse("a: 1\nb: [c,\n  d\n").unwrap_err(), "unterminated flow collection at line 2");
+    }
+
+    #[test]
+    fn the_nesting_is_limited() {
+        let flow = |depth: usize| format!("a: {}{}\n", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&flow(MAX_DEPTH)).is_ok());
+        assert_eq!(parse(&flow(MAX_DEPTH + 1)).unwrap_err(), "nested too deeply at line 1");
+        let block = |depth: usize| format!("{}x\n", "- ".repeat(depth));
+        assert!(parse(&block(MAX_DEPTH)).is_ok());
+        assert_eq!(parse(&block(MAX_DEPTH + 1)).unwrap_err(), "nested too deeply at line 1");
+        assert!(parse(&"- ".repeat(10_000)).is_err());
+        assert!(parse(&"[".repeat(100_000)).is_err());
+    }
+}